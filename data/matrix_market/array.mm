@@ -0,0 +1,9 @@
+%%MatrixMarket matrix array real general
+% entries are given in column major order
+2 3
+1
+2
+0
+-1
+3
+0
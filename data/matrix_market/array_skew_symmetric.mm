@@ -0,0 +1,5 @@
+%%MatrixMarket matrix array integer skew-symmetric
+3 3
+1
+-2
+3
@@ -0,0 +1,8 @@
+%%MatrixMarket matrix array integer symmetric
+3 3
+1
+2
+0
+4
+5
+6
@@ -0,0 +1,3 @@
+%%MatrixMarket matrix coordinate double general
+2 2 1
+1 1 1.0
@@ -0,0 +1,6 @@
+%%MatrixMarket matrix coordinate real general
+% the second entry lies outside of the declared shape
+3 3 3
+1 1 1.5
+4 2 2.5
+3 3 3.5
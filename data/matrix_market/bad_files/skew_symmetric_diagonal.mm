@@ -0,0 +1,4 @@
+%%MatrixMarket matrix coordinate integer skew-symmetric
+3 3 2
+2 1 4
+2 2 1
@@ -0,0 +1,5 @@
+%%MatrixMarket matrix coordinate real general
+2 2 2
+1 1 1.0
+2 2 2.0
+1 2 3.0
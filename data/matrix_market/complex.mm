@@ -0,0 +1,7 @@
+%%MatrixMarket matrix coordinate complex general
+% a small complex matrix
+3 4 4
+1 1 1.5 -2
+2 3 0 1
+3 1 -3.25e1 4.5
+3 4 2 0
@@ -0,0 +1,6 @@
+%%MatrixMarket matrix coordinate complex hermitian
+3 3 4
+1 1 2 0
+2 1 1 -1
+3 2 0 3
+3 3 5 0
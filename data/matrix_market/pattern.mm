@@ -0,0 +1,7 @@
+%%MatrixMarket matrix coordinate pattern symmetric
+% lower triangle of the adjacency matrix of a small graph
+4 4 4
+2 1
+3 1
+4 3
+4 4
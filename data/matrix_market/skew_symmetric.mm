@@ -0,0 +1,7 @@
+%%MatrixMarket matrix coordinate real skew-symmetric
+% only the entries below the diagonal are stored, the ones above it being
+% their opposite
+4 4 3
+2 1 1.5
+3 1 -2.0
+4 3 4.25
@@ -11,9 +11,9 @@
 ///! Since the L matrix is diagonally dominant, we can use a Gauss-Seidel
 ///! iterative scheme to solve the system.
 ///!
-///! This shows how a laplacian matrix can be constructed by directly
-///! constructing the compressed structure, and how the resulting linear
-///! system can be solved using an iterative method.
+///! This shows how a laplacian matrix can be constructed from its
+///! diagonals, and how the resulting linear system can be solved using an
+///! iterative method.
 
 type VecView<'a, T> = ndarray::ArrayView<'a, T, ndarray::Ix1>;
 type VecViewMut<'a, T> = ndarray::ArrayViewMut<'a, T, ndarray::Ix1>;
@@ -39,44 +39,36 @@ fn is_border(row: usize, col: usize, shape: (usize, usize)) -> bool {
 /// We assume this operator operates on the C-order flattened version of
 /// the grid.
 ///
-/// This example shows how a relatively straightforward sparse matrix
-/// can be constructed with a minimal number of allocations by directly
-/// building up its sparse structure.
+/// This example shows how a banded sparse matrix can be described by its
+/// diagonals: the 5-point stencil couples each vertex to its neighbors in
+/// the same row, at offsets -1 and 1, and in the neighboring rows, at offsets
+/// -cols and cols. Border vertices only keep their diagonal entry, as the
+/// zero values of the diagonals are not stored.
 fn grid_laplacian(shape: (usize, usize)) -> sprs::CsMat<f64> {
     let (rows, cols) = shape;
     let nb_vert = rows * cols;
-    let mut indptr = Vec::with_capacity(nb_vert + 1);
-    let nnz = 5 * nb_vert + 5;
-    let mut indices = Vec::with_capacity(nnz);
-    let mut data = Vec::with_capacity(nnz);
-    let mut cumsum = 0;
-
-    for i in 0..rows {
-        for j in 0..cols {
-            indptr.push(cumsum);
-
-            let mut add_elt = |i, j, x| {
-                indices.push(i * rows + j);
-                data.push(x);
-                cumsum += 1;
-            };
-
-            if is_border(i, j, shape) {
-                // establish Dirichlet boundary conditions
-                add_elt(i, j, 1.);
-            } else {
-                add_elt(i - 1, j, 1.);
-                add_elt(i, j - 1, 1.);
-                add_elt(i, j, -4.);
-                add_elt(i, j + 1, 1.);
-                add_elt(i + 1, j, 1.);
-            }
-        }
-    }
-
-    indptr.push(cumsum);
-
-    sprs::CsMat::new((nb_vert, nb_vert), indptr, indices, data)
+    let offsets = [-(cols as isize), -1, 0, 1, cols as isize];
+    let diagonals: Vec<Vec<f64>> = offsets
+        .iter()
+        .map(|&offset| {
+            // the rows crossed by the diagonal of this offset
+            let first_row = (-offset).max(0) as usize;
+            let last_row = nb_vert - offset.max(0) as usize;
+            (first_row..last_row)
+                .map(|vert| {
+                    let border = is_border(vert / cols, vert % cols, shape);
+                    match (offset, border) {
+                        (0, true) => 1.,
+                        (0, false) => -4.,
+                        (_, true) => 0.,
+                        (_, false) => 1.,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    sprs::diags((nb_vert, nb_vert), &diagonals, &offsets)
 }
 
 /// Set a dirichlet boundary condition
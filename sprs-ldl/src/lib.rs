@@ -18,6 +18,11 @@
 ///! to precompute part of the factorization by using the `LdlSymbolic` struct.
 ///! This struct can the be converted into a `LdlNumeric` once the non-zero
 ///! values are known, using the `LdlSymbolic::factor` method.
+///!
+///! An incomplete decomposition, which drops part of the fill-in of L, can be
+///! computed using the `IncompleteLdl` builder. It is cheaper to compute and
+///! to store than the complete decomposition, and can serve as a
+///! preconditioner for the iterative solvers of `sprs::linalg::iterative`.
 // This method is adapted from the LDL library by Tim Davis:
 //
 // LDL Copyright (c) 2005 by Timothy A. Davis.  All Rights Reserved.
@@ -52,16 +57,18 @@
 //     Copyright, this License, and the Availability note are retained,
 //     and a notice that the code was modified is included.
 use std::ops::Deref;
-use std::ops::IndexMut;
+use std::ops::{Index, IndexMut};
 
-use num_traits::Num;
+use num_traits::{Float, Num};
 
 use sprs::errors::SprsError;
 use sprs::indexing::SpIndex;
 use sprs::linalg;
+use sprs::linalg::iterative::Preconditioner;
 use sprs::stack::DStack;
-use sprs::{is_symmetric, CsMatViewI, PermOwnedI, Permutation};
+use sprs::{is_symmetric, CsMatI, CsMatViewI, PermOwnedI, Permutation};
 use sprs::{FillInReduction, PermutationCheck, SymmetryCheck};
+use sprs::{PermViewI, SymCsMatI};
 
 #[cfg(feature = "sprs_suitesparse_ldl")]
 use sprs_suitesparse_ldl::LdlNumeric as LdlNumericC;
@@ -146,7 +153,9 @@ impl Ldl {
             }
             FillInReduction::CAMDSuiteSparse => {
                 #[cfg(not(feature = "sprs_suitesparse_camd"))]
-                panic!("Unavailable without the `sprs_suitesparse_camd` feature");
+                panic!(
+                    "Unavailable without the `sprs_suitesparse_camd` feature"
+                );
                 #[cfg(feature = "sprs_suitesparse_camd")]
                 sprs_suitesparse_camd::camd(mat.structure_view())
             }
@@ -166,6 +175,25 @@ impl Ldl {
         LdlSymbolic::new_perm(mat, self.perm(mat), self.check_symmetry)
     }
 
+    /// Compute the symbolic decomposition of a symmetric matrix storing
+    /// only its upper triangle. The symmetry check is not needed.
+    pub fn symbolic_sym<N, I>(self, mat: &SymCsMatI<N, I>) -> LdlSymbolic<I>
+    where
+        I: SpIndex,
+        N: Copy + PartialEq + Default,
+    {
+        // fill-in reducing orderings need the structure of the full matrix,
+        // which is mirrored from the structure of the stored triangle
+        let perm = match self.fill_red_method {
+            FillInReduction::NoReduction => PermOwnedI::identity(mat.rows()),
+            _ => {
+                let upper = mat.upper().structure_view().to_owned();
+                self.perm(SymCsMatI::new(upper).to_full().view())
+            }
+        };
+        LdlSymbolic::new_sym(mat, perm)
+    }
+
     #[cfg(feature = "sprs_suitesparse_ldl")]
     pub fn symbolic_c<N, I>(self, mat: CsMatViewI<N, I>) -> LdlSymbolicC
     where
@@ -197,6 +225,19 @@ impl Ldl {
         symb.factor(mat)
     }
 
+    /// Compute the numeric decomposition of a symmetric matrix storing
+    /// only its upper triangle.
+    pub fn numeric_sym<N, I>(
+        self,
+        mat: &SymCsMatI<N, I>,
+    ) -> Result<LdlNumeric<N, I>, SprsError>
+    where
+        I: SpIndex,
+        N: Copy + Num + PartialOrd + Default,
+    {
+        self.symbolic_sym(mat).factor_sym(mat)
+    }
+
     #[cfg(feature = "sprs_suitesparse_ldl")]
     pub fn numeric_c<N, I>(
         self,
@@ -280,6 +321,41 @@ impl<I: SpIndex> LdlSymbolic<I> {
         }
     }
 
+    /// Compute the symbolic decomposition L D L^T = P A P^T
+    /// of a symmetric matrix storing only its upper triangle,
+    /// where P is a permutation matrix.
+    pub fn new_sym<N>(
+        mat: &SymCsMatI<N, I>,
+        perm: PermOwnedI<I>,
+    ) -> LdlSymbolic<I>
+    where
+        N: Copy + PartialEq + Default,
+    {
+        let n = mat.rows();
+        let triangle = permuted_triangle(mat, perm.view());
+        let mut l_colptr = vec![I::zero(); n + 1];
+        let mut parents = linalg::etree::ParentsOwned::new(n);
+        let mut l_nz = vec![I::zero(); n];
+        let mut flag_workspace = vec![I::zero(); n];
+        ldl_symbolic(
+            triangle.view(),
+            &PermOwnedI::identity(n),
+            &mut l_colptr,
+            parents.view_mut(),
+            &mut l_nz,
+            &mut flag_workspace,
+            SymmetryCheck::DontCheckSymmetry,
+        );
+
+        LdlSymbolic {
+            colptr: l_colptr,
+            parents,
+            nz: l_nz,
+            flag_workspace,
+            perm,
+        }
+    }
+
     /// The size of the linear system associated with this decomposition
     #[inline]
     pub fn problem_size(&self) -> usize {
@@ -300,6 +376,28 @@ impl<I: SpIndex> LdlSymbolic<I> {
     ) -> Result<LdlNumeric<N, I>, SprsError>
     where
         N: Copy + Num + PartialOrd,
+    {
+        let mut ldl_numeric = self.into_numeric();
+        ldl_numeric.update(mat).map(|_| ldl_numeric)
+    }
+
+    /// Compute the numerical decomposition of the given symmetric matrix
+    /// storing only its upper triangle.
+    pub fn factor_sym<N>(
+        self,
+        mat: &SymCsMatI<N, I>,
+    ) -> Result<LdlNumeric<N, I>, SprsError>
+    where
+        N: Copy + Num + PartialOrd + Default,
+    {
+        let mut ldl_numeric = self.into_numeric();
+        ldl_numeric.update_sym(mat).map(|_| ldl_numeric)
+    }
+
+    /// Allocate the storage of the numerical decomposition
+    fn into_numeric<N>(self) -> LdlNumeric<N, I>
+    where
+        N: Copy + Num,
     {
         let n = self.problem_size();
         let nnz = self.nnz();
@@ -308,15 +406,14 @@ impl<I: SpIndex> LdlSymbolic<I> {
         let diag = vec![N::zero(); n];
         let y_workspace = vec![N::zero(); n];
         let pattern_workspace = DStack::with_capacity(n);
-        let mut ldl_numeric = LdlNumeric {
+        LdlNumeric {
             symbolic: self,
             l_indices,
             l_data,
             diag,
             y_workspace,
             pattern_workspace,
-        };
-        ldl_numeric.update(mat).map(|_| ldl_numeric)
+        }
     }
 }
 
@@ -377,6 +474,43 @@ impl<N, I: SpIndex> LdlNumeric<N, I> {
         )
     }
 
+    /// Compute the numeric decomposition L D L^T = P A P^T
+    /// of a symmetric matrix storing only its upper triangle,
+    /// where P is a permutation matrix.
+    pub fn new_sym(
+        mat: &SymCsMatI<N, I>,
+        perm: PermOwnedI<I>,
+    ) -> Result<Self, SprsError>
+    where
+        N: Copy + Num + PartialOrd + Default,
+    {
+        LdlSymbolic::new_sym(mat, perm).factor_sym(mat)
+    }
+
+    /// Update the decomposition with the given symmetric matrix storing
+    /// only its upper triangle. The matrix must have the same non-zero
+    /// pattern as the original matrix, otherwise the result is unspecified.
+    pub fn update_sym(&mut self, mat: &SymCsMatI<N, I>) -> Result<(), SprsError>
+    where
+        N: Copy + Num + PartialOrd + Default,
+    {
+        let triangle = permuted_triangle(mat, self.symbolic.perm.view());
+        let n = self.problem_size();
+        ldl_numeric(
+            triangle.view(),
+            &self.symbolic.colptr,
+            self.symbolic.parents.view(),
+            &PermOwnedI::identity(n),
+            &mut self.symbolic.nz,
+            &mut self.l_indices,
+            &mut self.l_data,
+            &mut self.diag,
+            &mut self.y_workspace,
+            &mut self.pattern_workspace,
+            &mut self.symbolic.flag_workspace,
+        )
+    }
+
     /// Solve the system A x = rhs
     pub fn solve<'a, V>(&self, rhs: &V) -> Vec<N>
     where
@@ -425,6 +559,268 @@ impl<N, I: SpIndex> LdlNumeric<N, I> {
     }
 }
 
+/// The fill-in kept by an incomplete LDLT decomposition
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum IncompleteFill<N> {
+    /// Only keep the entries of L in the sparsity pattern of the lower
+    /// triangle of the matrix, which gives the IC(0) decomposition
+    Pattern,
+    /// Also keep the fill-in entries such that
+    /// `|d_i * l_ki| >= tol * sqrt(|a_kk * a_ii|)`. A null tolerance
+    /// gives the complete decomposition.
+    DropTolerance(N),
+}
+
+/// The maximum number of diagonal shifts tried by an incomplete
+/// decomposition before reporting a breakdown
+const MAX_SHIFTS: usize = 20;
+
+/// Builder pattern structure to customize an incomplete LDLT decomposition.
+///
+/// The incomplete decomposition is computed like the complete one, using
+/// the elimination tree of the symbolic decomposition, but entries of L
+/// are dropped according to the fill strategy. As it is meant to
+/// precondition symmetric positive definite systems, the decomposition
+/// breaks down when a pivot is not positive. It can then be retried on a
+/// matrix with a shifted diagonal `A + alpha * diag(A)`, doubling `alpha`
+/// until success.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::iterative::ConjugateGradient;
+/// use sprs_ldl::{IncompleteFill, IncompleteLdl};
+/// let mat: sprs::CsMat<f64> =
+///     sprs::diags((5, 5), &[&[-1.][..], &[3.], &[-1.]], &[-1, 0, 1]);
+/// let ic = IncompleteLdl::new()
+///     .fill(IncompleteFill::Pattern)
+///     .diagonal_shift(1e-3)
+///     .numeric(mat.view())
+///     .unwrap();
+/// let rhs = [2., 1., 1., 1., 2.];
+/// let mut x = [0.; 5];
+/// ConjugateGradient::new()
+///     .solve(&mat, &ic, &rhs, &mut x)
+///     .unwrap();
+/// assert!(x.iter().all(|&x| (x - 1.).abs() < 1e-6));
+/// ```
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct IncompleteLdl<N> {
+    check_symmetry: SymmetryCheck,
+    fill_red_method: FillInReduction,
+    fill: IncompleteFill<N>,
+    initial_shift: Option<N>,
+}
+
+impl<N> Default for IncompleteLdl<N> {
+    fn default() -> Self {
+        Self {
+            check_symmetry: SymmetryCheck::CheckSymmetry,
+            fill_red_method: FillInReduction::ReverseCuthillMcKee,
+            fill: IncompleteFill::Pattern,
+            initial_shift: None,
+        }
+    }
+}
+
+/// Structure to hold an incomplete LDLT decomposition
+#[derive(Debug, Clone)]
+pub struct IncompleteLdlNumeric<N, I: SpIndex> {
+    perm: PermOwnedI<I>,
+    l: CsMatI<N, I>,
+    diag: Vec<N>,
+    shift: N,
+}
+
+impl<N: Float> IncompleteLdl<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check_symmetry(self, check: SymmetryCheck) -> Self {
+        Self {
+            check_symmetry: check,
+            ..self
+        }
+    }
+
+    pub fn fill_in_reduction(self, method: FillInReduction) -> Self {
+        Self {
+            fill_red_method: method,
+            ..self
+        }
+    }
+
+    /// Set the fill-in strategy, `IncompleteFill::Pattern` by default
+    pub fn fill(self, fill: IncompleteFill<N>) -> Self {
+        Self { fill, ..self }
+    }
+
+    /// Retry the decomposition on `A + alpha * diag(A)` on breakdown,
+    /// starting with `alpha = initial_shift`. No shift is tried by default.
+    pub fn diagonal_shift(self, initial_shift: N) -> Self {
+        Self {
+            initial_shift: Some(initial_shift),
+            ..self
+        }
+    }
+
+    /// Compute the incomplete decomposition L D L^T ~ P A P^T, where P is
+    /// the fill-in reducing permutation.
+    ///
+    /// # Errors
+    ///
+    /// - `SingularMatrix` if a non positive pivot is encountered, after
+    ///   trying diagonal shifts if they are enabled.
+    ///
+    /// # Panics
+    ///
+    /// * if mat is not symmetric
+    pub fn numeric<I>(
+        self,
+        mat: CsMatViewI<N, I>,
+    ) -> Result<IncompleteLdlNumeric<N, I>, SprsError>
+    where
+        I: SpIndex,
+    {
+        let perm = Ldl::new().fill_in_reduction(self.fill_red_method).perm(mat);
+        let parents = elimination_tree(mat, &perm, self.check_symmetry);
+        let mut shift = N::zero();
+        let mut nb_shifts = 0;
+        loop {
+            let res =
+                ildl_numeric(mat, parents.view(), &perm, self.fill, shift);
+            match (res, self.initial_shift) {
+                (Ok((l, diag)), _) => {
+                    return Ok(IncompleteLdlNumeric {
+                        perm,
+                        l,
+                        diag,
+                        shift,
+                    });
+                }
+                (Err(_), Some(initial_shift)) if nb_shifts < MAX_SHIFTS => {
+                    shift = if nb_shifts == 0 {
+                        initial_shift
+                    } else {
+                        shift + shift
+                    };
+                    nb_shifts += 1;
+                }
+                (Err(err), _) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<N, I: SpIndex> IncompleteLdlNumeric<N, I> {
+    /// Solve the system L D L^T x = rhs, approximating A x = rhs
+    pub fn solve<'a, V>(&self, rhs: &V) -> Vec<N>
+    where
+        N: 'a + Copy + Num,
+        V: Deref<Target = [N]>,
+    {
+        let mut x = &self.perm * &rhs[..];
+        let l = self.l();
+        ldl_lsolve(&l, &mut x);
+        linalg::diag_solve(&self.diag, &mut x);
+        ldl_ltsolve(&l, &mut x);
+        let pinv = self.perm.inv();
+        &pinv * &x
+    }
+
+    /// The diagonal factor D of the decomposition
+    pub fn d(&self) -> &[N] {
+        &self.diag[..]
+    }
+
+    /// The L factor of the decomposition, whose unit diagonal is not stored
+    pub fn l(&self) -> CsMatViewI<N, I> {
+        self.l.view()
+    }
+
+    /// The fill-in reducing permutation P of the decomposition
+    pub fn perm(&self) -> PermViewI<I> {
+        self.perm.view()
+    }
+
+    /// The diagonal shift `alpha` such that the decomposition approximates
+    /// `A + alpha * diag(A)`
+    pub fn shift(&self) -> N
+    where
+        N: Copy,
+    {
+        self.shift
+    }
+
+    /// The size of the linear system associated with this decomposition
+    #[inline]
+    pub fn problem_size(&self) -> usize {
+        self.diag.len()
+    }
+
+    /// The number of non-zero entries in L
+    #[inline]
+    pub fn nnz(&self) -> usize {
+        self.l.nnz()
+    }
+}
+
+impl<N, I> Preconditioner<N> for IncompleteLdlNumeric<N, I>
+where
+    N: Copy + Num,
+    I: SpIndex,
+{
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        // the system in P A P^T is solved in z, entry i of P r being
+        // stored at z[P(i)], so that no permuted copy is needed
+        z.copy_from_slice(r);
+        let mut x = PermutedVec {
+            vals: z,
+            perm: self.perm.view(),
+        };
+        let l = self.l();
+        ldl_lsolve(&l, &mut x);
+        for (i, &d) in self.diag.iter().enumerate() {
+            x[i] = x[i] / d;
+        }
+        ldl_ltsolve(&l, &mut x);
+    }
+}
+
+/// A vector indexed through a permutation
+struct PermutedVec<'a, N, I: SpIndex> {
+    vals: &'a mut [N],
+    perm: PermViewI<'a, I>,
+}
+
+impl<'a, N, I: SpIndex> Index<usize> for PermutedVec<'a, N, I> {
+    type Output = N;
+
+    fn index(&self, index: usize) -> &N {
+        &self.vals[self.perm.at(index)]
+    }
+}
+
+impl<'a, N, I: SpIndex> IndexMut<usize> for PermutedVec<'a, N, I> {
+    fn index_mut(&mut self, index: usize) -> &mut N {
+        &mut self.vals[self.perm.at(index)]
+    }
+}
+
+/// The upper triangle of P A P^T in CSC storage, which is the part of
+/// P A P^T read by the decomposition
+fn permuted_triangle<N, I>(
+    mat: &SymCsMatI<N, I>,
+    perm: PermViewI<I>,
+) -> CsMatI<N, I>
+where
+    N: Copy + Default,
+    I: SpIndex,
+{
+    mat.transform_papt(perm).into_upper().into_csc()
+}
+
 /// Perform a symbolic LDLt decomposition of a symmetric sparse matrix
 pub fn ldl_symbolic<N, I, PStorage>(
     mat: CsMatViewI<N, I>,
@@ -479,6 +875,41 @@ pub fn ldl_symbolic<N, I, PStorage>(
     l_colptr[n] = prev;
 }
 
+/// Compute the elimination tree of P A P^T, which is the only part of the
+/// symbolic decomposition needed by the incomplete decomposition
+fn elimination_tree<N, I>(
+    mat: CsMatViewI<N, I>,
+    perm: &PermOwnedI<I>,
+    check_symmetry: SymmetryCheck,
+) -> linalg::etree::ParentsOwned
+where
+    N: PartialEq,
+    I: SpIndex,
+{
+    if check_symmetry == SymmetryCheck::CheckSymmetry && !is_symmetric(&mat) {
+        panic!("Matrix is not symmetric")
+    }
+    let n = mat.rows();
+    let mut parents = linalg::etree::ParentsOwned::new(n);
+    // the ancestors of the visited nodes, pointing to the last row whose
+    // pattern reached them, which compresses the paths to the roots
+    let mut ancestors = vec![None; n];
+    let outer_it = mat.outer_iterator_papt(perm.view());
+    for (k, (_, vec)) in outer_it.enumerate() {
+        for (inner_ind, _) in vec.iter_perm(perm.inv()) {
+            let mut node = Some(inner_ind);
+            while let Some(i) = node.filter(|&i| i < k) {
+                node = ancestors[i];
+                ancestors[i] = Some(k);
+                if node.is_none() {
+                    parents.set_parent(i, k);
+                }
+            }
+        }
+    }
+    parents
+}
+
 /// Perform numeric LDLT decomposition
 ///
 /// pattern_workspace is a DStack of capacity n
@@ -575,6 +1006,99 @@ where
     Ok(())
 }
 
+/// Perform an incomplete LDLT decomposition of `P A P^T + shift diag(A)`,
+/// following the row by row structure of `ldl_numeric`. The dropped entries
+/// of a row are not propagated to the rest of the row, so that with the
+/// `Pattern` fill L D L^T matches the matrix on its sparsity pattern.
+fn ildl_numeric<N, I>(
+    mat: CsMatViewI<N, I>,
+    parents: linalg::etree::ParentsView,
+    perm: &PermOwnedI<I>,
+    fill: IncompleteFill<N>,
+    shift: N,
+) -> Result<(CsMatI<N, I>, Vec<N>), SprsError>
+where
+    N: Float,
+    I: SpIndex,
+{
+    let n = mat.outer_dims();
+    let mut columns: Vec<Vec<(usize, N)>> = vec![Vec::new(); n];
+    let mut diag = vec![N::zero(); n];
+    // the magnitude of the diagonal of P A P^T, scaling the drop tolerance
+    let mut mat_diag = vec![N::zero(); n];
+    let mut y_workspace = vec![N::zero(); n];
+    let mut in_pattern = vec![false; n];
+    // n is never a row index, so it marks all nodes as not visited
+    let mut flag_workspace = vec![n; n];
+    let mut pattern_workspace = DStack::with_capacity(n);
+    let outer_it = mat.outer_iterator_papt(perm.view());
+    for (k, (_, vec)) in outer_it.enumerate() {
+        flag_workspace[k] = k;
+        pattern_workspace.clear_right();
+        for (inner_ind, &val) in
+            vec.iter_perm(perm.inv()).filter(|&(i, _)| i <= k)
+        {
+            if inner_ind == k {
+                mat_diag[k] = val.abs();
+                y_workspace[k] = y_workspace[k] + val + shift * val;
+                continue;
+            }
+            y_workspace[inner_ind] = y_workspace[inner_ind] + val;
+            in_pattern[inner_ind] = true;
+            let mut i = inner_ind;
+            pattern_workspace.clear_left();
+            while flag_workspace[i] != k {
+                pattern_workspace.push_left(i);
+                flag_workspace[i] = k;
+                i = parents.get_parent(i).expect("enforced by ldl_symbolic");
+            }
+            pattern_workspace.push_left_on_right();
+        }
+
+        let mut dk = y_workspace[k];
+        y_workspace[k] = N::zero();
+        for &i in pattern_workspace.iter_right() {
+            let yi = y_workspace[i];
+            y_workspace[i] = N::zero();
+            let keep = in_pattern[i]
+                || match fill {
+                    IncompleteFill::Pattern => false,
+                    IncompleteFill::DropTolerance(tol) => {
+                        yi.abs() >= tol * (mat_diag[i] * mat_diag[k]).sqrt()
+                    }
+                };
+            in_pattern[i] = false;
+            if !keep {
+                continue;
+            }
+            for &(row, l_ri) in &columns[i] {
+                y_workspace[row] = y_workspace[row] - l_ri * yi;
+            }
+            let l_ki = yi / diag[i];
+            dk = dk - l_ki * yi;
+            columns[i].push((k, l_ki));
+        }
+        if dk.is_nan() || dk <= N::zero() {
+            return Err(SprsError::SingularMatrix);
+        }
+        diag[k] = dk;
+    }
+
+    let mut indptr = Vec::with_capacity(n + 1);
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    indptr.push(I::zero());
+    for column in columns {
+        for (row, val) in column {
+            indices.push(I::from_usize(row));
+            data.push(val);
+        }
+        indptr.push(I::from_usize(indices.len()));
+    }
+    let l = CsMatI::new_csc((n, n), indptr, indices, data);
+    Ok((l, diag))
+}
+
 /// Triangular solve specialized on lower triangular matrices
 /// produced by ldlt (diagonal terms are omitted and assumed to be 1).
 pub fn ldl_lsolve<N, I, V: ?Sized>(l: &CsMatViewI<N, I>, x: &mut V)
@@ -612,7 +1136,7 @@ where
 mod test {
     use super::SymmetryCheck;
     use sprs::stack::DStack;
-    use sprs::{self, linalg, CsMat, CsMatView, Permutation};
+    use sprs::{self, linalg, CsMat, CsMatView, Permutation, SymCsMat};
 
     fn test_mat1() -> CsMat<f64> {
         let indptr = vec![0, 2, 5, 6, 7, 13, 14, 17, 20, 24, 28];
@@ -850,6 +1374,46 @@ mod test {
         assert_eq!(x, x0);
     }
 
+    #[test]
+    fn sym_ldl_solve() {
+        let mat = test_mat1();
+        let sym = SymCsMat::from_full(&mat);
+        let b = test_vec1();
+        let ldlt = super::LdlNumeric::new(mat.view()).unwrap();
+        let identity = Permutation::identity(10);
+        let ldlt_sym = super::LdlNumeric::new_sym(&sym, identity).unwrap();
+        assert_eq!(ldlt_sym.d(), ldlt.d());
+        assert_eq!(ldlt_sym.l(), ldlt.l());
+        assert_eq!(ldlt_sym.solve(&b), ldlt.solve(&b));
+
+        let ldlt = super::Ldl::new()
+            .fill_in_reduction(super::FillInReduction::ReverseCuthillMcKee)
+            .numeric(mat.view())
+            .unwrap();
+        let ldlt_sym = super::Ldl::new()
+            .fill_in_reduction(super::FillInReduction::ReverseCuthillMcKee)
+            .numeric_sym(&sym)
+            .unwrap();
+        assert_eq!(ldlt_sym.d(), ldlt.d());
+        assert_eq!(ldlt_sym.l(), ldlt.l());
+        assert_eq!(ldlt_sym.solve(&b), ldlt.solve(&b));
+
+        // a CSR upper triangle with a permutation
+        let mat = CsMat::new_csc(
+            (4, 4),
+            vec![0, 2, 4, 6, 8],
+            vec![0, 3, 1, 2, 1, 2, 0, 3],
+            vec![1, 2, 21, 6, 6, 2, 2, 8],
+        );
+        let sym = SymCsMat::from_full(&mat.to_csr());
+        let perm = Permutation::new(vec![0, 2, 1, 3]);
+        let mut ldlt = super::LdlNumeric::new_sym(&sym, perm).unwrap();
+        assert_eq!(ldlt.solve(&vec![9, 60, 18, 34]), vec![1, 2, 3, 4]);
+        let doubled = SymCsMat::new(sym.upper().map(|&x| 2 * x));
+        ldlt.update_sym(&doubled).unwrap();
+        assert_eq!(ldlt.solve(&vec![18, 120, 36, 68]), vec![1, 2, 3, 4]);
+    }
+
     #[cfg(feature = "sprs_suitesparse_ldl")]
     #[test]
     fn cuthill_ldl_solve_c() {
@@ -915,4 +1479,116 @@ mod test {
         assert!(ldlt_camd.nnz() < ldlt_raw.nnz());
         assert!(ldlt_camd.nnz() < ldlt_cuthill.nnz());
     }
+
+    /// The 5 point laplacian on a n by n grid
+    fn grid_laplacian(n: usize) -> CsMat<f64> {
+        let offsets = [-(n as isize), -1, 0, 1, n as isize];
+        let diagonals: Vec<Vec<f64>> = offsets
+            .iter()
+            .map(|&offset| {
+                let len = n * n - offset.abs() as usize;
+                (0..len)
+                    .map(|k| match offset {
+                        0 => 4.,
+                        -1 | 1 if (k + 1) % n == 0 => 0.,
+                        _ => -1.,
+                    })
+                    .collect()
+            })
+            .collect();
+        sprs::diags((n * n, n * n), &diagonals, &offsets)
+    }
+
+    #[test]
+    fn ic0_pattern() {
+        let mat = grid_laplacian(6);
+        let ic = super::IncompleteLdl::new()
+            .fill_in_reduction(super::FillInReduction::NoReduction)
+            .numeric(mat.view())
+            .unwrap();
+        assert_eq!(ic.shift(), 0.);
+        assert_eq!(ic.nnz(), (mat.nnz() - 36) / 2);
+        // L D L^T matches the matrix on its pattern
+        let l = &ic.l() + &CsMat::eye_csc(36);
+        let d = CsMat::new_csc(
+            (36, 36),
+            (0..=36).collect(),
+            (0..36).collect(),
+            ic.d().to_vec(),
+        );
+        let ldlt = &(&l * &d) * &l.transpose_view();
+        for (&val, (i, j)) in mat.iter() {
+            assert!((ldlt.get(i, j).unwrap() - val).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn ildl_drop_tolerance() {
+        let mat = test_mat1();
+        let b = test_vec1();
+        let ldlt = super::LdlNumeric::new(mat.view()).unwrap();
+        let complete = super::IncompleteLdl::new()
+            .fill_in_reduction(super::FillInReduction::NoReduction)
+            .fill(super::IncompleteFill::DropTolerance(0.))
+            .numeric(mat.view())
+            .unwrap();
+        assert_eq!(complete.nnz(), ldlt.nnz());
+        for (x, y) in complete.solve(&b).iter().zip(&ldlt.solve(&b)) {
+            assert!((x - y).abs() < 1e-12);
+        }
+
+        let mat = grid_laplacian(6);
+        let ic0 = super::IncompleteLdl::new().numeric(mat.view()).unwrap();
+        let ict = super::IncompleteLdl::new()
+            .fill(super::IncompleteFill::DropTolerance(1e-2))
+            .numeric(mat.view())
+            .unwrap();
+        let full = super::Ldl::new().numeric(mat.view()).unwrap();
+        assert!(ic0.nnz() < ict.nnz());
+        assert!(ict.nnz() < full.nnz());
+    }
+
+    #[test]
+    fn ic0_preconditioned_cg() {
+        use sprs::linalg::iterative::{
+            ConjugateGradient, IdentityPreconditioner, Preconditioner,
+        };
+        let mat = grid_laplacian(12);
+        let rhs: Vec<f64> = (0..144).map(|i| (i % 5) as f64 - 2.).collect();
+        let solver = ConjugateGradient::new().tolerance(1e-10);
+        let mut x = vec![0.; 144];
+        let plain = solver
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+        let ic = super::IncompleteLdl::new().numeric(mat.view()).unwrap();
+        let mut x = vec![0.; 144];
+        let stats = solver.solve(&mat, &ic, &rhs, &mut x).unwrap();
+        assert!(stats.iterations < plain.iterations);
+        // applying the preconditioner solves the permuted system in place
+        assert!(!ic.perm().is_identity());
+        let mut z = vec![0.; 144];
+        ic.apply(&rhs, &mut z);
+        assert_eq!(z, ic.solve(&rhs));
+    }
+
+    #[test]
+    fn ic0_breakdown_shift() {
+        // Kershaw's matrix is positive definite, but its IC(0) has a
+        // negative pivot
+        let mat = CsMat::new(
+            (4, 4),
+            vec![0, 3, 6, 9, 12],
+            vec![0, 1, 3, 0, 1, 2, 1, 2, 3, 0, 2, 3],
+            vec![3., -2., 2., -2., 3., -2., -2., 3., -2., 2., -2., 3.],
+        );
+        let builder = super::IncompleteLdl::new()
+            .fill_in_reduction(super::FillInReduction::NoReduction);
+        assert_eq!(
+            builder.numeric(mat.view()).unwrap_err(),
+            super::SprsError::SingularMatrix
+        );
+        let ic = builder.diagonal_shift(0.1).numeric(mat.view()).unwrap();
+        assert!(ic.shift() >= 0.1);
+        assert!(ic.d().iter().all(|&d| d > 0.));
+    }
 }
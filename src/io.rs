@@ -4,20 +4,50 @@ use std::error::Error;
 use std::fmt;
 use std::fs::File;
 use std::io;
-use std::io::{Seek, SeekFrom, Write};
+use std::io::Write;
+use std::ops::Neg;
 use std::path::Path;
 
+use ndarray::Array2;
+use num_complex::Complex;
 use num_traits::cast::NumCast;
+use num_traits::{Num, Zero};
 
 use crate::indexing::SpIndex;
 use crate::num_kinds::{NumKind, PrimitiveKind};
-use crate::sparse::{SparseMat, TriMatI};
+use crate::sparse::{CsMatI, SparseMat, TriMatI};
+
+#[cfg(feature = "mmap")]
+pub mod binary;
+pub mod graph;
+pub mod mex;
+#[cfg(feature = "npz")]
+pub mod npz;
+#[cfg(feature = "multi_thread")]
+mod par;
+pub mod rutherford_boeing;
+pub mod svmlight;
+
+#[cfg(feature = "multi_thread")]
+pub use self::par::{
+    read_matrix_market_par, read_matrix_market_par_from_bufread,
+    read_matrix_market_par_with_fill,
+    read_matrix_market_par_with_fill_from_bufread,
+};
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum IoError {
     Io(io::Error),
-    BadMatrixMarketFile,
-    UnsupportedMatrixMarketFormat,
+    BadMatrixMarketFile(MatrixMarketError),
+    UnsupportedMatrixMarketFormat(MatrixMarketError),
+    MismatchedLabels,
+    BadRutherfordBoeingFile(LineError),
+    #[cfg(feature = "npz")]
+    BadNpzFile(npz::NpzError),
+    BadBinaryFile(&'static str),
+    BadSvmLightFile(LineError),
+    BadGraphFile(LineError),
 }
 
 use self::IoError::*;
@@ -26,11 +56,28 @@ impl fmt::Display for IoError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             IoError::Io(ref err) => err.fmt(f),
-            IoError::BadMatrixMarketFile => {
-                write!(f, "Bad matrix market file.")
+            IoError::BadMatrixMarketFile(ref err) => {
+                write!(f, "Bad matrix market file, {}.", err)
+            }
+            IoError::UnsupportedMatrixMarketFormat(ref err) => {
+                write!(f, "Unsupported matrix market format, {}.", err)
+            }
+            IoError::MismatchedLabels => {
+                write!(f, "Number of labels does not match the matrix shape.")
+            }
+            IoError::BadRutherfordBoeingFile(ref err) => {
+                write!(f, "Bad Rutherford-Boeing file, {}.", err)
+            }
+            #[cfg(feature = "npz")]
+            IoError::BadNpzFile(ref err) => write!(f, "Bad npz file, {}.", err),
+            IoError::BadBinaryFile(reason) => {
+                write!(f, "Bad binary matrix file, {}.", reason)
+            }
+            IoError::BadSvmLightFile(ref err) => {
+                write!(f, "Bad SVMLight file, {}.", err)
             }
-            IoError::UnsupportedMatrixMarketFormat => {
-                write!(f, "Bad matrix market file.")
+            IoError::BadGraphFile(ref err) => {
+                write!(f, "Bad graph file, {}.", err)
             }
         }
     }
@@ -46,213 +93,1346 @@ impl From<io::Error> for IoError {
 
 impl PartialEq for IoError {
     fn eq(&self, rhs: &IoError) -> bool {
-        match *self {
-            IoError::BadMatrixMarketFile => {
-                matches!(*rhs, IoError::BadMatrixMarketFile)
-            }
-            IoError::UnsupportedMatrixMarketFormat => {
-                matches!(*rhs, IoError::UnsupportedMatrixMarketFormat)
+        match (self, rhs) {
+            (BadMatrixMarketFile(lhs), BadMatrixMarketFile(rhs)) => lhs == rhs,
+            (
+                UnsupportedMatrixMarketFormat(lhs),
+                UnsupportedMatrixMarketFormat(rhs),
+            ) => lhs == rhs,
+            (MismatchedLabels, MismatchedLabels) => true,
+            (BadRutherfordBoeingFile(lhs), BadRutherfordBoeingFile(rhs)) => {
+                lhs == rhs
             }
+            #[cfg(feature = "npz")]
+            (BadNpzFile(lhs), BadNpzFile(rhs)) => lhs == rhs,
+            (BadBinaryFile(lhs), BadBinaryFile(rhs)) => lhs == rhs,
+            (BadSvmLightFile(lhs), BadSvmLightFile(rhs)) => lhs == rhs,
+            (BadGraphFile(lhs), BadGraphFile(rhs)) => lhs == rhs,
             _ => false,
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
-enum DataType {
-    Integer,
-    Real,
-    Complex,
+/// The reason why a Matrix Market file could not be read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MatrixMarketErrorKind {
+    /// A field of the header line is not recognized
+    BadHeaderField,
+    /// A field of the size line is not a valid dimension or entry count
+    BadSizeField,
+    /// A non-square matrix is declared with a symmetric storage
+    NonSquareSymmetric,
+    /// A line lacks a field
+    MissingField,
+    /// An index is not a valid 1-based index
+    BadIndex,
+    /// An index exceeds the shape declared in the size line
+    IndexOutOfBounds,
+    /// A value cannot be parsed, or cannot be represented by the scalar
+    /// type of the matrix
+    BadValue,
+    /// An entry line has more fields than expected
+    TrailingField,
+    /// An entry lies in an earlier row than the previous entry, when
+    /// reading row blocks
+    UnsortedRows,
+    /// The file ends before the declared number of entries
+    NotEnoughEntries,
+    /// The file contains more entries than declared
+    TooManyEntries,
+    /// A skew-symmetric file stores a diagonal entry
+    DiagonalInSkewSymmetric,
+    /// Complex entries cannot be read into a real scalar type
+    ComplexIntoReal,
+    /// Pattern entries have no natural value for this scalar type, and no
+    /// fill value was given
+    PatternWithoutFill,
+    /// A coordinate file cannot be read into a dense array
+    CoordinateIntoArray,
+}
+
+impl fmt::Display for MatrixMarketErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::MatrixMarketErrorKind::*;
+        let reason = match *self {
+            BadHeaderField => "invalid header field",
+            BadSizeField => "invalid size field",
+            NonSquareSymmetric => "non-square matrix declared as symmetric",
+            MissingField => "missing field",
+            BadIndex => "invalid index",
+            IndexOutOfBounds => "index out of bounds of the declared shape",
+            BadValue => "invalid value",
+            TrailingField => "unexpected trailing field",
+            UnsortedRows => "entry not sorted by row",
+            NotEnoughEntries => "fewer entries than declared",
+            TooManyEntries => "more entries than declared",
+            DiagonalInSkewSymmetric => {
+                "diagonal entry in a skew-symmetric matrix"
+            }
+            ComplexIntoReal => "complex entry read into a real type",
+            PatternWithoutFill => "pattern entry read without a fill value",
+            CoordinateIntoArray => "coordinate file read as a dense array",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Description of a failure to read a Matrix Market file, locating the
+/// offending line and token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatrixMarketError {
+    /// The 1-based number of the line where the error was detected
+    pub line: usize,
+    /// The offending token, if the error can be attributed to one
+    pub token: Option<String>,
+    /// The reason of the failure
+    pub kind: MatrixMarketErrorKind,
+}
+
+impl MatrixMarketError {
+    fn new(
+        line: usize,
+        kind: MatrixMarketErrorKind,
+        token: Option<&str>,
+    ) -> Self {
+        Self {
+            line,
+            token: token.map(str::to_string),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for MatrixMarketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)?;
+        if let Some(ref token) = self.token {
+            write!(f, " `{}`", token)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<MatrixMarketError> for IoError {
+    fn from(err: MatrixMarketError) -> IoError {
+        use self::MatrixMarketErrorKind::*;
+        match err.kind {
+            ComplexIntoReal | PatternWithoutFill | CoordinateIntoArray => {
+                UnsupportedMatrixMarketFormat(err)
+            }
+            _ => BadMatrixMarketFile(err),
+        }
+    }
+}
+
+/// Description of a failure to read a text file, locating the offending
+/// line and token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineError {
+    /// The 1-based number of the line where the error was detected
+    pub line: usize,
+    /// The offending token, if the error can be attributed to one
+    pub token: Option<String>,
+    /// The reason of the failure
+    pub reason: &'static str,
+}
+
+impl LineError {
+    fn new(line: usize, reason: &'static str, token: Option<&str>) -> Self {
+        Self {
+            line,
+            token: token.map(str::to_string),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)?;
+        if let Some(ref token) = self.token {
+            write!(f, " `{}`", token)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error kind along with the offending token, to be located by the caller
+type TokenError<'a> = (MatrixMarketErrorKind, Option<&'a str>);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Coordinate,
+    Array,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum DataType {
+    Integer,
+    Real,
+    Complex,
+    Pattern,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SymmetryMode {
+    General,
+    Hermitian,
+    Symmetric,
+    SkewSymmetric,
+}
+
+/// Scalar types that can be read from a Matrix Market file.
+///
+/// Matrix Market files store integer, real or complex entries. This trait
+/// describes how such entries are converted into the scalar type of the
+/// matrix being read, and how the mirrored entries of hermitian and
+/// skew-symmetric matrices are obtained. It is implemented for the
+/// primitive numeric types and for `num_complex::Complex`.
+pub trait MatrixMarketRead: Sized + Clone {
+    /// Convert an integer entry, returning `None` if it cannot be
+    /// represented by this type.
+    fn from_mm_integer(val: isize) -> Option<Self>;
+
+    /// Convert a real entry, returning `None` if it cannot be
+    /// represented by this type.
+    fn from_mm_real(val: f64) -> Option<Self>;
+
+    /// Convert a complex entry, returning `None` if it cannot be
+    /// represented by this type.
+    fn from_mm_complex(re: f64, im: f64) -> Option<Self>;
+
+    /// The value of the entries of a pattern file, if this type has a
+    /// natural value for them.
+    fn from_mm_pattern() -> Option<Self> {
+        None
+    }
+
+    /// The complex conjugate of this value, used to mirror the entries
+    /// of hermitian matrices. This is the identity for real types.
+    fn mm_conj(&self) -> Self;
+
+    /// The opposite of this value, used to mirror the entries of
+    /// skew-symmetric matrices, or `None` if it cannot be represented by
+    /// this type.
+    fn mm_neg(&self) -> Option<Self>;
+
+    /// Test whether this value is zero, in which case it is not stored
+    /// when reading a dense file into a sparse matrix.
+    fn mm_is_zero(&self) -> bool;
+}
+
+macro_rules! mm_read_prim_impl {
+    ($prim: ty, $neg: expr) => {
+        impl MatrixMarketRead for $prim {
+            fn from_mm_integer(val: isize) -> Option<Self> {
+                NumCast::from(val)
+            }
+
+            fn from_mm_real(val: f64) -> Option<Self> {
+                NumCast::from(val)
+            }
+
+            fn from_mm_complex(_re: f64, _im: f64) -> Option<Self> {
+                None
+            }
+
+            fn mm_conj(&self) -> Self {
+                *self
+            }
+
+            fn mm_neg(&self) -> Option<Self> {
+                $neg(*self)
+            }
+
+            fn mm_is_zero(&self) -> bool {
+                *self == 0 as $prim
+            }
+        }
+    };
+}
+
+mm_read_prim_impl!(i8, i8::checked_neg);
+mm_read_prim_impl!(u8, u8::checked_neg);
+mm_read_prim_impl!(i16, i16::checked_neg);
+mm_read_prim_impl!(u16, u16::checked_neg);
+mm_read_prim_impl!(i32, i32::checked_neg);
+mm_read_prim_impl!(u32, u32::checked_neg);
+mm_read_prim_impl!(i64, i64::checked_neg);
+mm_read_prim_impl!(u64, u64::checked_neg);
+mm_read_prim_impl!(isize, isize::checked_neg);
+mm_read_prim_impl!(usize, usize::checked_neg);
+mm_read_prim_impl!(f32, |val: f32| Some(-val));
+mm_read_prim_impl!(f64, |val: f64| Some(-val));
+
+impl<T> MatrixMarketRead for Complex<T>
+where
+    T: MatrixMarketRead + Num + Neg<Output = T>,
+{
+    fn from_mm_integer(val: isize) -> Option<Self> {
+        T::from_mm_integer(val).map(|re| Complex::new(re, T::zero()))
+    }
+
+    fn from_mm_real(val: f64) -> Option<Self> {
+        T::from_mm_real(val).map(|re| Complex::new(re, T::zero()))
+    }
+
+    fn from_mm_complex(re: f64, im: f64) -> Option<Self> {
+        Some(Complex::new(T::from_mm_real(re)?, T::from_mm_real(im)?))
+    }
+
+    fn mm_conj(&self) -> Self {
+        self.conj()
+    }
+
+    fn mm_neg(&self) -> Option<Self> {
+        Some(Complex::new(self.re.mm_neg()?, self.im.mm_neg()?))
+    }
+
+    fn mm_is_zero(&self) -> bool {
+        self.is_zero()
+    }
+}
+
+/// The unit type can be used to read the structure of any Matrix Market
+/// file, ignoring its values.
+impl MatrixMarketRead for () {
+    fn from_mm_integer(_val: isize) -> Option<Self> {
+        Some(())
+    }
+
+    fn from_mm_real(_val: f64) -> Option<Self> {
+        Some(())
+    }
+
+    fn from_mm_complex(_re: f64, _im: f64) -> Option<Self> {
+        Some(())
+    }
+
+    fn from_mm_pattern() -> Option<Self> {
+        Some(())
+    }
+
+    fn mm_conj(&self) -> Self {}
+
+    fn mm_neg(&self) -> Option<Self> {
+        Some(())
+    }
+
+    fn mm_is_zero(&self) -> bool {
+        false
+    }
+}
+
+/// Parse a value written as an integer or a real, returning `None` if it
+/// cannot be parsed or represented by the scalar type.
+fn parse_number<N: MatrixMarketRead>(token: &str) -> Option<N> {
+    match token.parse::<isize>() {
+        Ok(val) => N::from_mm_integer(val),
+        Err(_) => token.parse::<f64>().ok().and_then(N::from_mm_real),
+    }
+}
+
+/// Scalar types that can be written into a Matrix Market file.
+///
+/// Matrix Market entries store complex values as their real and imaginary
+/// parts separated by whitespace, which differs from their `Display`
+/// representation. The formatter's options, such as the precision, are
+/// forwarded to the formatting of the underlying values.
+pub trait MatrixMarketDisplay {
+    /// Format this value as it should appear in a Matrix Market entry.
+    fn mm_fmt(&self, f: &mut fmt::Formatter) -> fmt::Result;
+
+    /// Format this value in scientific notation, if this is meaningful for
+    /// this type. Integer types should use their plain representation, as
+    /// scientific notation is not valid in integer Matrix Market files.
+    fn mm_fmt_exp(&self, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+macro_rules! mm_display_int_impl {
+    ($prim: ty) => {
+        impl MatrixMarketDisplay for $prim {
+            fn mm_fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+
+            fn mm_fmt_exp(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+        }
+    };
+}
+
+mm_display_int_impl!(i8);
+mm_display_int_impl!(u8);
+mm_display_int_impl!(i16);
+mm_display_int_impl!(u16);
+mm_display_int_impl!(i32);
+mm_display_int_impl!(u32);
+mm_display_int_impl!(i64);
+mm_display_int_impl!(u64);
+mm_display_int_impl!(isize);
+mm_display_int_impl!(usize);
+
+macro_rules! mm_display_float_impl {
+    ($prim: ty) => {
+        impl MatrixMarketDisplay for $prim {
+            fn mm_fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+
+            fn mm_fmt_exp(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::LowerExp::fmt(self, f)
+            }
+        }
+    };
+}
+
+mm_display_float_impl!(f32);
+mm_display_float_impl!(f64);
+
+impl<T: MatrixMarketDisplay> MatrixMarketDisplay for Complex<T> {
+    fn mm_fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.re.mm_fmt(f)?;
+        f.write_str(" ")?;
+        self.im.mm_fmt(f)
+    }
+
+    fn mm_fmt_exp(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.re.mm_fmt_exp(f)?;
+        f.write_str(" ")?;
+        self.im.mm_fmt_exp(f)
+    }
+}
+
+/// Adapter to use `MatrixMarketDisplay` in formatting macros
+struct MmDisplay<'a, N>(&'a N);
+
+impl<'a, N: MatrixMarketDisplay> fmt::Display for MmDisplay<'a, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.mm_fmt(f)
+    }
+}
+
+impl<'a, N: MatrixMarketDisplay> fmt::LowerExp for MmDisplay<'a, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.mm_fmt_exp(f)
+    }
+}
+
+/// The parsed header line of a Matrix Market file
+struct Header {
+    format: Format,
+    data_type: DataType,
+    sym_mode: SymmetryMode,
+}
+
+fn parse_header(header: &str) -> Result<Header, TokenError<'_>> {
+    use self::MatrixMarketErrorKind::*;
+    // all tags are case insensitive
+    let mut tokens = header
+        .split_whitespace()
+        .map(|token| (token.to_lowercase(), token));
+    let mut next_token = || tokens.next().ok_or((MissingField, None));
+    for &expected in &["%%matrixmarket", "matrix"] {
+        let (tag, token) = next_token()?;
+        if tag != expected {
+            return Err((BadHeaderField, Some(token)));
+        }
+    }
+    let (tag, token) = next_token()?;
+    let format = match tag.as_str() {
+        "coordinate" => Format::Coordinate,
+        "array" => Format::Array,
+        _ => return Err((BadHeaderField, Some(token))),
+    };
+    let (tag, token) = next_token()?;
+    let data_type = match tag.as_str() {
+        "real" => DataType::Real,
+        "integer" => DataType::Integer,
+        "complex" => DataType::Complex,
+        // pattern matrices only make sense in the sparse format
+        "pattern" if format == Format::Coordinate => DataType::Pattern,
+        _ => return Err((BadHeaderField, Some(token))),
+    };
+    let (tag, token) = next_token()?;
+    let sym_mode = match tag.as_str() {
+        "general" => SymmetryMode::General,
+        "symmetric" => SymmetryMode::Symmetric,
+        "skew-symmetric" => SymmetryMode::SkewSymmetric,
+        "hermitian" => SymmetryMode::Hermitian,
+        _ => return Err((BadHeaderField, Some(token))),
+    };
+    if let Some((_, token)) = tokens.next() {
+        return Err((TrailingField, Some(token)));
+    }
+    Ok(Header {
+        format,
+        data_type,
+        sym_mode,
+    })
+}
+
+/// Line by line reader of a Matrix Market file, keeping track of the
+/// line number to locate errors.
+struct MmLines<R> {
+    reader: R,
+    line: String,
+    line_nb: usize,
+}
+
+impl<R: io::BufRead> MmLines<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            // MatrixMarket format specifies lines of at most 1024 chars
+            line: String::with_capacity(1024),
+            line_nb: 0,
+        }
+    }
+
+    /// Read the next line, returning false at the end of the input
+    fn next_line(&mut self) -> Result<bool, IoError> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_nb += 1;
+        Ok(true)
+    }
+
+    /// Read the next non-empty line (no comment line should appear)
+    fn next_entry_line(&mut self) -> Result<(), IoError> {
+        while self.next_line()? {
+            // check for an all whitespace line
+            if self.line.split_whitespace().next().is_some() {
+                return Ok(());
+            }
+        }
+        Err(self.error((MatrixMarketErrorKind::NotEnoughEntries, None)))
+    }
+
+    /// Check that only empty lines remain
+    fn check_end(&mut self) -> Result<(), IoError> {
+        while self.next_line()? {
+            if let Some(token) = self.line.split_whitespace().next() {
+                let kind = MatrixMarketErrorKind::TooManyEntries;
+                return Err(self.error((kind, Some(token))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Locate an error on the current line
+    fn error(&self, (kind, token): TokenError<'_>) -> IoError {
+        MatrixMarketError::new(self.line_nb, kind, token).into()
+    }
+}
+
+/// Parse the header, skip the comments, and parse the size line of a
+/// Matrix Market file. The size line contains the shape of the matrix,
+/// followed by the number of entries for the coordinate format.
+fn read_header_and_size<R: io::BufRead>(
+    lines: &mut MmLines<R>,
+) -> Result<(Header, usize, usize, Option<usize>), IoError> {
+    use self::MatrixMarketErrorKind::*;
+    if !lines.next_line()? {
+        return Err(lines.error((MissingField, None)));
+    }
+    let header = parse_header(&lines.line).map_err(|e| lines.error(e))?;
+    // The header is followed by any number of comment or empty lines, skip
+    loop {
+        if !lines.next_line()? {
+            return Err(lines.error((MissingField, None)));
+        }
+        let line = &lines.line;
+        if !line.starts_with('%') && line.split_whitespace().next().is_some() {
+            break;
+        }
+    }
+    let (rows, cols, entries) =
+        parse_size(&lines.line, &header).map_err(|e| lines.error(e))?;
+    Ok((header, rows, cols, entries))
+}
+
+/// Parse the size line, returning the shape of the matrix and the number
+/// of entries for the coordinate format.
+fn parse_size<'a>(
+    line: &'a str,
+    header: &Header,
+) -> Result<(usize, usize, Option<usize>), TokenError<'a>> {
+    use self::MatrixMarketErrorKind::*;
+    // this is a line like:
+    // rows cols entries
+    // with arbitrary amounts of whitespace
+    let mut infos = line.split_whitespace();
+    let mut next_info = || {
+        let token = infos.next().ok_or((MissingField, None))?;
+        token.parse::<usize>().or(Err((BadSizeField, Some(token))))
+    };
+    let rows = next_info()?;
+    let cols = next_info()?;
+    let entries = match header.format {
+        Format::Coordinate => Some(next_info()?),
+        Format::Array => None,
+    };
+    if let Some(token) = infos.next() {
+        return Err((TrailingField, Some(token)));
+    }
+    if header.sym_mode != SymmetryMode::General && rows != cols {
+        return Err((NonSquareSymmetric, None));
+    }
+    Ok((rows, cols, entries))
+}
+
+/// Parse a 1-based index, checking it against the dimension `dim`.
+fn parse_index<'a, E>(
+    entry: &mut E,
+    dim: usize,
+) -> Result<usize, TokenError<'a>>
+where
+    E: Iterator<Item = &'a str>,
+{
+    use self::MatrixMarketErrorKind::*;
+    let token = entry.next().ok_or((MissingField, None))?;
+    let index = token.parse::<usize>().or(Err((BadIndex, Some(token))))?;
+    // MatrixMarket indices are 1-based
+    match index.checked_sub(1) {
+        Some(index) if index < dim => Ok(index),
+        Some(_) => Err((IndexOutOfBounds, Some(token))),
+        None => Err((BadIndex, Some(token))),
+    }
+}
+
+/// Parse the value of an entry. Pattern entries take the value
+/// `pattern_fill` if available, or the value given by
+/// `MatrixMarketRead::from_mm_pattern`.
+fn parse_value<'a, N, E>(
+    data_type: &DataType,
+    entry: &mut E,
+    pattern_fill: Option<&N>,
+) -> Result<N, TokenError<'a>>
+where
+    N: MatrixMarketRead,
+    E: Iterator<Item = &'a str>,
+{
+    use self::MatrixMarketErrorKind::*;
+    let mut next_token = || entry.next().ok_or((MissingField, None));
+    let parse_real =
+        |token: &'a str| token.parse::<f64>().or(Err((BadValue, Some(token))));
+    match *data_type {
+        DataType::Integer => {
+            let token = next_token()?;
+            token
+                .parse::<isize>()
+                .ok()
+                .and_then(N::from_mm_integer)
+                .ok_or((BadValue, Some(token)))
+        }
+        DataType::Real => {
+            let token = next_token()?;
+            N::from_mm_real(parse_real(token)?).ok_or((BadValue, Some(token)))
+        }
+        DataType::Complex => {
+            let re = parse_real(next_token()?)?;
+            let im = parse_real(next_token()?)?;
+            N::from_mm_complex(re, im).ok_or_else(|| {
+                // a real scalar type cannot hold any complex value
+                if N::from_mm_complex(0., 0.).is_none() {
+                    (ComplexIntoReal, None)
+                } else {
+                    (BadValue, None)
+                }
+            })
+        }
+        DataType::Pattern => pattern_fill
+            .cloned()
+            .or_else(N::from_mm_pattern)
+            .ok_or((PatternWithoutFill, None)),
+    }
+}
+
+/// Read a sparse matrix file in the Matrix Market format and return a
+/// corresponding triplet matrix.
+///
+/// General, symmetric, skew-symmetric and hermitian matrices are supported,
+/// the entries of the non-general matrices being mirrored across the
+/// diagonal. Complex entries can be read into `num_complex::Complex`
+/// matrices, see `MatrixMarketRead` for the supported scalar types.
+///
+/// Pattern files, which only describe the structure of a matrix, can be
+/// read into a `TriMatI<(), I>`, see `read_matrix_market_with_fill` to read
+/// them into numeric matrices. Files in the dense array format are converted
+/// to a sparse matrix by dropping their zero entries, see
+/// `read_matrix_market_array` to read them into a dense array.
+///
+/// See `MatrixMarketReader` to process the entries of a file without
+/// storing them all in memory.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::read_matrix_market;
+/// use sprs::TriMat;
+/// let path = "data/matrix_market/pattern.mm";
+/// let pattern: TriMat<()> = read_matrix_market(path).unwrap();
+/// let structure = pattern.to_csr_structure();
+/// assert_eq!(structure.nnz(), 7);
+/// ```
+pub fn read_matrix_market<N, I, P>(mm_file: P) -> Result<TriMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    P: AsRef<Path>,
+{
+    let mm_file = mm_file.as_ref();
+    let f = File::open(mm_file)?;
+    let mut reader = io::BufReader::new(f);
+    read_matrix_market_from_bufread(&mut reader)
+}
+
+/// Read a sparse matrix in the Matrix Market format from an `io::BufRead` and return a
+/// corresponding triplet matrix.
+///
+/// See `read_matrix_market` for the supported formats.
+pub fn read_matrix_market_from_bufread<N, I, R>(
+    reader: &mut R,
+) -> Result<TriMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    R: io::BufRead,
+{
+    read_sparse_matrix_market(reader, None)
+}
+
+/// Read a sparse matrix file in the Matrix Market format and return a
+/// corresponding triplet matrix, giving the value `fill` to all the entries
+/// of a pattern file.
+///
+/// Files with another data type are read as in `read_matrix_market`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::read_matrix_market_with_fill;
+/// let path = "data/matrix_market/pattern.mm";
+/// let mat = read_matrix_market_with_fill::<f64, usize, _>(path, 1.).unwrap();
+/// assert_eq!(mat.data(), &[1.; 7]);
+/// ```
+pub fn read_matrix_market_with_fill<N, I, P>(
+    mm_file: P,
+    fill: N,
+) -> Result<TriMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    P: AsRef<Path>,
+{
+    let mm_file = mm_file.as_ref();
+    let f = File::open(mm_file)?;
+    let mut reader = io::BufReader::new(f);
+    read_matrix_market_with_fill_from_bufread(&mut reader, fill)
+}
+
+/// Read a sparse matrix in the Matrix Market format from an `io::BufRead`
+/// and return a corresponding triplet matrix, giving the value `fill` to all
+/// the entries of a pattern file.
+///
+/// See `read_matrix_market_with_fill` for more details.
+pub fn read_matrix_market_with_fill_from_bufread<N, I, R>(
+    reader: &mut R,
+    fill: N,
+) -> Result<TriMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    R: io::BufRead,
+{
+    read_sparse_matrix_market(reader, Some(fill))
+}
+
+fn read_sparse_matrix_market<N, I, R>(
+    reader: R,
+    pattern_fill: Option<N>,
+) -> Result<TriMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    R: io::BufRead,
+{
+    let mut entries = MatrixMarketReader::new(reader)?;
+    entries.pattern_fill = pattern_fill;
+    collect_triplets(entries)
+}
+
+/// Collect the remaining entries of a reader into a triplet matrix.
+fn collect_triplets<N, I, R>(
+    entries: MatrixMarketReader<N, I, R>,
+) -> Result<TriMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    R: io::BufRead,
+{
+    let shape = entries.shape();
+    let nnz_max = if entries.symmetry() == SymmetryMode::General {
+        entries.nb_entries()
+    } else {
+        2 * entries.nb_entries()
+    };
+    let mut row_inds = Vec::with_capacity(nnz_max);
+    let mut col_inds = Vec::with_capacity(nnz_max);
+    let mut data = Vec::with_capacity(nnz_max);
+    for entry in entries {
+        let (val, (row, col)) = entry?;
+        row_inds.push(row);
+        col_inds.push(col);
+        data.push(val);
+    }
+    Ok(TriMatI::from_triplets(shape, row_inds, col_inds, data))
+}
+
+/// Parse an entry line, returning its location and value. The location
+/// is read from the line for coordinate files, and given by `location` for
+/// array files.
+fn parse_entry<'a, N>(
+    line: &'a str,
+    header: &Header,
+    (rows, cols): (usize, usize),
+    location: Option<(usize, usize)>,
+    pattern_fill: Option<&N>,
+) -> Result<(usize, usize, N, Option<N>), TokenError<'a>>
+where
+    N: MatrixMarketRead,
+{
+    // Non-zero entries are lines of the form:
+    // row col value
+    // if the data type is integer of real,
+    // row col real imag
+    // if the data type is complex, and
+    // row col
+    // if the data type is pattern.
+    // The location is implicit for array files.
+    // Again, this is with arbitrary amounts of whitespace
+    let mut entry = line.split_whitespace();
+    let (row, col) = match location {
+        Some(location) => location,
+        None => {
+            let row = parse_index(&mut entry, rows)?;
+            (row, parse_index(&mut entry, cols)?)
+        }
+    };
+    let value_token = entry.clone().next();
+    let val = parse_value(&header.data_type, &mut entry, pattern_fill)?;
+    if let Some(token) = entry.next() {
+        return Err((MatrixMarketErrorKind::TrailingField, Some(token)));
+    }
+    if header.sym_mode == SymmetryMode::SkewSymmetric && row == col {
+        return Err((MatrixMarketErrorKind::DiagonalInSkewSymmetric, None));
+    }
+    // The off-diagonal entries of non-general matrices are mirrored
+    let mirrored = match header.sym_mode {
+        _ if row == col => None,
+        SymmetryMode::General => None,
+        SymmetryMode::Symmetric => Some(val.clone()),
+        SymmetryMode::SkewSymmetric => Some(
+            val.mm_neg()
+                .ok_or((MatrixMarketErrorKind::BadValue, value_token))?,
+        ),
+        SymmetryMode::Hermitian => Some(val.mm_conj()),
+    };
+    Ok((row, col, val, mirrored))
+}
+
+/// An entry of a matrix, as a `(value, (row, col))` tuple
+type Entry<N, I> = (N, (I, I));
+
+/// Streaming reader over the entries of a Matrix Market file.
+///
+/// The header is parsed on creation, and the entries are then parsed one
+/// at a time as the reader is iterated, yielding `(value, (row, col))`
+/// items. This enables processing files that do not fit in memory, or
+/// filtering their entries on the fly.
+///
+/// The items are the ones that `read_matrix_market` would store: the
+/// entries of non-general matrices are followed by their mirror across the
+/// diagonal, and the zero entries of array files are skipped. Once an error
+/// is yielded, the iteration stops.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::MatrixMarketReader;
+/// let path = "data/matrix_market/simple.mm";
+/// let reader = MatrixMarketReader::<f64, usize, _>::open(path).unwrap();
+/// assert_eq!(reader.shape(), (5, 5));
+/// // compute the sum of the diagonal in a single pass
+/// let mut trace = 0.;
+/// for entry in reader {
+///     let (val, (row, col)) = entry.unwrap();
+///     if row == col {
+///         trace += val;
+///     }
+/// }
+/// assert_eq!(trace, 1. + 10.5 + 1.5e-2 - 2.8e2 + 1.2e1);
+/// ```
+pub struct MatrixMarketReader<N, I, R> {
+    lines: MmLines<R>,
+    header: Header,
+    shape: (usize, usize),
+    entries: usize,
+    entry_nb: usize,
+    array_locations: ArrayLocations,
+    pattern_fill: Option<N>,
+    mirrored: Option<Entry<N, I>>,
+    done: bool,
+}
+
+impl<N, I> MatrixMarketReader<N, I, io::BufReader<File>>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+{
+    /// Open a Matrix Market file and parse its header.
+    pub fn open<P: AsRef<Path>>(mm_file: P) -> Result<Self, IoError> {
+        let f = File::open(mm_file.as_ref())?;
+        Self::new(io::BufReader::new(f))
+    }
+}
+
+impl<N, I, R> MatrixMarketReader<N, I, R>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    R: io::BufRead,
+{
+    /// Parse the header of a Matrix Market file from an `io::BufRead`,
+    /// leaving the entries to be read by iteration.
+    pub fn new(reader: R) -> Result<Self, IoError> {
+        let mut lines = MmLines::new(reader);
+        let (header, rows, cols, entries) = read_header_and_size(&mut lines)?;
+        let entries = match header.format {
+            Format::Coordinate => entries.unwrap_or(0),
+            Format::Array => nb_array_entries(rows, cols, header.sym_mode),
+        };
+        let array_locations = array_locations(rows, cols, header.sym_mode);
+        Ok(Self {
+            lines,
+            header,
+            shape: (rows, cols),
+            entries,
+            entry_nb: 0,
+            array_locations,
+            pattern_fill: None,
+            mirrored: None,
+            done: false,
+        })
+    }
+
+    /// Give the value `fill` to the entries of a pattern file. Files with
+    /// another data type are not affected.
+    pub fn with_fill(mut self, fill: N) -> Self {
+        self.pattern_fill = Some(fill);
+        self
+    }
+
+    /// The number of rows of the matrix
+    pub fn rows(&self) -> usize {
+        self.shape.0
+    }
+
+    /// The number of cols of the matrix
+    pub fn cols(&self) -> usize {
+        self.shape.1
+    }
+
+    /// The shape of the matrix, as a `(rows, cols)` tuple
+    pub fn shape(&self) -> (usize, usize) {
+        self.shape
+    }
+
+    /// The number of entries stored in the file, as declared by its size
+    /// line for coordinate files. Mirrored entries are not counted.
+    pub fn nb_entries(&self) -> usize {
+        self.entries
+    }
+
+    /// The symmetry of the matrix, as declared by the header
+    pub fn symmetry(&self) -> SymmetryMode {
+        self.header.sym_mode
+    }
+
+    /// Group the entries into CSR matrices of `block_rows` consecutive
+    /// rows, see `MatrixMarketRowBlocks`.
+    ///
+    /// # Panics
+    ///
+    /// If `block_rows` is zero.
+    pub fn row_blocks(self, block_rows: usize) -> MatrixMarketRowBlocks<N, I, R>
+    where
+        N: Num,
+    {
+        assert!(block_rows > 0, "row blocks cannot be empty");
+        MatrixMarketRowBlocks {
+            entries: self,
+            block_rows,
+            next_row: 0,
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Read the next stored entry of the file
+    fn next_entry(&mut self) -> Result<Option<Entry<N, I>>, IoError> {
+        loop {
+            if self.entry_nb == self.entries {
+                self.lines.check_end()?;
+                return Ok(None);
+            }
+            self.lines.next_entry_line()?;
+            self.entry_nb += 1;
+            // The number of entries matches the number of locations
+            let location = match self.header.format {
+                Format::Coordinate => None,
+                Format::Array => self.array_locations.next(),
+            };
+            let (row, col, val, mirrored) = parse_entry(
+                &self.lines.line,
+                &self.header,
+                self.shape,
+                location,
+                self.pattern_fill.as_ref(),
+            )
+            .map_err(|e| self.lines.error(e))?;
+            if self.header.format == Format::Array && val.mm_is_zero() {
+                continue;
+            }
+            let (row, col) = (I::from_usize(row), I::from_usize(col));
+            if let Some(mirrored) = mirrored {
+                self.mirrored = Some((mirrored, (col, row)));
+            }
+            return Ok(Some((val, (row, col))));
+        }
+    }
+}
+
+impl<N, I, R> Iterator for MatrixMarketReader<N, I, R>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+    R: io::BufRead,
+{
+    type Item = Result<(N, (I, I)), IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(mirrored) = self.mirrored.take() {
+            return Some(Ok(mirrored));
+        }
+        if self.done {
+            return None;
+        }
+        let entry = self.next_entry().transpose();
+        if !matches!(entry, Some(Ok(_))) {
+            self.done = true;
+        }
+        entry
+    }
+}
+
+/// Streaming reader yielding the rows of a Matrix Market file by blocks.
+///
+/// Each item is a CSR matrix holding `block_rows` consecutive rows of the
+/// matrix, along with the index of its first row. The last block can be
+/// shorter, and blocks without entries are yielded as empty matrices, so
+/// that stacking the blocks gives the whole matrix. Duplicate entries are
+/// summed.
+///
+/// The entries of the file must be sorted by row, which is not the case of
+/// array files and of non-general matrices whose mirrored entries lie in
+/// earlier rows. An entry preceding the previous one's row is reported as
+/// a `MatrixMarketErrorKind::UnsortedRows` error, and stops the iteration.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::{write_matrix_market_to_bufwrite, MatrixMarketReader};
+/// use sprs::CsMat;
+/// let eye: CsMat<f64> = CsMat::eye(5);
+/// // CSR matrices are written sorted by row
+/// let mut buffer = Vec::new();
+/// write_matrix_market_to_bufwrite(&mut buffer, &eye).unwrap();
+/// let reader = MatrixMarketReader::<f64, usize, _>::new(&buffer[..]).unwrap();
+/// let blocks: Vec<_> = reader.row_blocks(2).map(Result::unwrap).collect();
+/// assert_eq!(blocks.len(), 3);
+/// assert_eq!(blocks[2].0, 4);
+/// assert_eq!(blocks[2].1, CsMat::new((1, 5), vec![0, 1], vec![4], vec![1.]));
+/// ```
+pub struct MatrixMarketRowBlocks<N, I, R> {
+    entries: MatrixMarketReader<N, I, R>,
+    block_rows: usize,
+    next_row: usize,
+    pending: Option<Entry<N, I>>,
+    done: bool,
+}
+
+impl<N, I, R> MatrixMarketRowBlocks<N, I, R>
+where
+    I: SpIndex,
+    N: MatrixMarketRead + Num,
+    R: io::BufRead,
+{
+    /// Read the entries of the rows `start..end`
+    fn read_block(
+        &mut self,
+        start: usize,
+        end: usize,
+    ) -> Result<CsMatI<N, I>, IoError> {
+        let mut indptr = Vec::with_capacity(end - start + 1);
+        indptr.push(I::zero());
+        let mut row_entries = Vec::new();
+        let mut block_entries = Vec::new();
+        let mut row = start;
+        loop {
+            let entry = match self.pending.take() {
+                Some(entry) => Some(entry),
+                None => self.entries.next().transpose()?,
+            };
+            let entry_row = entry.as_ref().map(|&(_, (r, _))| r.index());
+            if let Some(entry_row) = entry_row {
+                if entry_row < row {
+                    let kind = MatrixMarketErrorKind::UnsortedRows;
+                    return Err(self.entries.lines.error((kind, None)));
+                }
+            }
+            // complete the rows preceding the entry
+            let next_row = entry_row.unwrap_or(end).min(end);
+            while row < next_row {
+                let len = sum_duplicates(&mut row_entries);
+                block_entries.extend(row_entries.drain(..).take(len));
+                indptr.push(I::from_usize(block_entries.len()));
+                row += 1;
+            }
+            match entry {
+                Some((val, (r, col))) if r.index() < end => {
+                    row_entries.push((col, val))
+                }
+                entry => {
+                    self.pending = entry;
+                    break;
+                }
+            }
+        }
+        let (indices, data) = block_entries.into_iter().unzip();
+        let shape = (end - start, self.entries.cols());
+        Ok(CsMatI::new_trusted(
+            crate::CSR,
+            shape,
+            indptr,
+            indices,
+            data,
+        ))
+    }
+}
+
+impl<N, I, R> Iterator for MatrixMarketRowBlocks<N, I, R>
+where
+    I: SpIndex,
+    N: MatrixMarketRead + Num,
+    R: io::BufRead,
+{
+    type Item = Result<(usize, CsMatI<N, I>), IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let start = self.next_row;
+        let rows = self.entries.rows();
+        if start >= rows {
+            // all the entries have been read, but the lines of an empty
+            // matrix still need to be checked
+            self.done = true;
+            return match self.entries.next() {
+                Some(Err(err)) => Some(Err(err)),
+                _ => None,
+            };
+        }
+        let end = rows.min(start + self.block_rows);
+        self.next_row = end;
+        let block = self.read_block(start, end);
+        if block.is_err() {
+            self.done = true;
+        }
+        Some(block.map(|block| (start, block)))
+    }
+}
+
+/// Sort the entries of a compressed dimension by inner index, and sum the
+/// duplicates in file order. Returns the number of distinct entries, which
+/// are moved at the start of the slice.
+fn sum_duplicates<I, N>(entries: &mut [(I, N)]) -> usize
+where
+    I: SpIndex,
+    N: Num + Clone,
+{
+    // a stable sort keeps duplicates in file order
+    entries.sort_by_key(|&(inner, _)| inner);
+    let mut len = 0;
+    for pos in 0..entries.len() {
+        if len > 0 && entries[len - 1].0 == entries[pos].0 {
+            let val = entries[pos].1.clone();
+            entries[len - 1].1 = entries[len - 1].1.clone() + val;
+        } else {
+            entries.swap(len, pos);
+            len += 1;
+        }
+    }
+    len
+}
+
+/// The number of entries stored in an array file, whose shape has been
+/// checked to be square for non-general matrices.
+fn nb_array_entries(rows: usize, cols: usize, sym_mode: SymmetryMode) -> usize {
+    match sym_mode {
+        SymmetryMode::General => rows * cols,
+        SymmetryMode::SkewSymmetric => rows * cols.saturating_sub(1) / 2,
+        _ => rows * (cols + 1) / 2,
+    }
+}
+
+/// Iterator over the locations of the entries stored in an array file.
+/// Entries are stored in column major order, and only the lower triangle is
+/// stored for non-general matrices.
+struct ArrayLocations {
+    rows: usize,
+    cols: usize,
+    sym_mode: SymmetryMode,
+    row: usize,
+    col: usize,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum SymmetryMode {
-    General,
-    Hermitian,
-    Symmetric,
-    SkewSymmetric,
+impl ArrayLocations {
+    /// The first row stored for the column `col`
+    fn first_row(&self, col: usize) -> usize {
+        match self.sym_mode {
+            SymmetryMode::General => 0,
+            SymmetryMode::SkewSymmetric => col + 1,
+            _ => col,
+        }
+    }
 }
 
-fn parse_header(header: &str) -> Result<(SymmetryMode, DataType), IoError> {
-    if !header.starts_with("%%matrixmarket matrix coordinate") {
-        return Err(BadMatrixMarketFile);
+impl Iterator for ArrayLocations {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        while self.col < self.cols {
+            if self.row < self.rows {
+                self.row += 1;
+                return Some((self.row - 1, self.col));
+            }
+            self.col += 1;
+            self.row = self.first_row(self.col);
+        }
+        None
     }
-    let data_type = if header.contains("real") {
-        DataType::Real
-    } else if header.contains("integer") {
-        DataType::Integer
-    } else if header.contains("complex") {
-        DataType::Complex
-    } else {
-        return Err(BadMatrixMarketFile);
-    };
-    let sym_mode = if header.contains("general") {
-        SymmetryMode::General
-    } else if header.contains("symmetric") {
-        SymmetryMode::Symmetric
-    } else if header.contains("skew-symmetric") {
-        SymmetryMode::SkewSymmetric
-    } else if header.contains("hermitian") {
-        SymmetryMode::Hermitian
-    } else {
-        return Err(BadMatrixMarketFile);
+}
+
+fn array_locations(
+    rows: usize,
+    cols: usize,
+    sym_mode: SymmetryMode,
+) -> ArrayLocations {
+    let mut locations = ArrayLocations {
+        rows,
+        cols,
+        sym_mode,
+        row: 0,
+        col: 0,
     };
-    Ok((sym_mode, data_type))
+    locations.row = locations.first_row(0);
+    locations
 }
 
-/// Read a sparse matrix file in the Matrix Market format and return a
-/// corresponding triplet matrix.
+/// Read a file in the Matrix Market array format and return the
+/// corresponding dense matrix.
 ///
-/// Presently, only general matrices are supported, but symmetric and hermitian
-/// matrices should be supported in the future.
-pub fn read_matrix_market<N, I, P>(mm_file: P) -> Result<TriMatI<N, I>, IoError>
+/// The entries of non-general matrices are mirrored across the diagonal.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::read_matrix_market_array;
+/// let path = "data/matrix_market/array.mm";
+/// let mat = read_matrix_market_array::<f64, _>(path).unwrap();
+/// assert_eq!(mat, ndarray::arr2(&[[1., 0., 3.], [2., -1., 0.]]));
+/// ```
+pub fn read_matrix_market_array<N, P>(mm_file: P) -> Result<Array2<N>, IoError>
 where
-    I: SpIndex,
-    N: NumCast + Clone,
+    N: MatrixMarketRead + Zero,
     P: AsRef<Path>,
 {
     let mm_file = mm_file.as_ref();
     let f = File::open(mm_file)?;
     let mut reader = io::BufReader::new(f);
-    read_matrix_market_from_bufread(&mut reader)
+    read_matrix_market_array_from_bufread(&mut reader)
 }
 
-/// Read a sparse matrix in the Matrix Market format from an `io::BufRead` and return a
-/// corresponding triplet matrix.
+/// Read a matrix in the Matrix Market array format from an `io::BufRead`
+/// and return the corresponding dense matrix.
 ///
-/// Presently, only general matrices are supported, but symmetric and hermitian
-/// matrices should be supported in the future.
-pub fn read_matrix_market_from_bufread<N, I, R>(
+/// See `read_matrix_market_array` for more details.
+pub fn read_matrix_market_array_from_bufread<N, R>(
     reader: &mut R,
-) -> Result<TriMatI<N, I>, IoError>
+) -> Result<Array2<N>, IoError>
 where
-    I: SpIndex,
-    N: NumCast + Clone,
+    N: MatrixMarketRead + Zero,
     R: io::BufRead,
 {
-    // MatrixMarket format specifies lines of at most 1024 chars
-    let mut line = String::with_capacity(1024);
-
-    // Parse the header line, all tags are case insensitive.
-    reader.read_line(&mut line)?;
-    let header = line.to_lowercase();
-    let (sym_mode, data_type) = parse_header(&header)?;
-    if data_type == DataType::Complex {
-        // we currently don't support complex
-        return Err(UnsupportedMatrixMarketFormat);
-    }
-    if sym_mode == SymmetryMode::Hermitian {
-        // support for Hermitian requires complex support
-        return Err(UnsupportedMatrixMarketFormat);
-    }
-    // The header is followed by any number of comment or empty lines, skip
-    loop {
-        line.clear();
-        let len = reader.read_line(&mut line)?;
-        if len == 0 || line.starts_with('%') {
-            continue;
-        } else {
-            break;
-        }
+    let mut lines = MmLines::new(reader);
+    let (header, rows, cols, _) = read_header_and_size(&mut lines)?;
+    if header.format != Format::Array {
+        let kind = MatrixMarketErrorKind::CoordinateIntoArray;
+        return Err(MatrixMarketError::new(1, kind, None).into());
     }
-    // read shape and number of entries
-    // this is a line like:
-    // rows cols entries
-    // with arbitrary amounts of whitespace
-    let (rows, cols, entries) = {
-        let mut infos = line
-            .split_whitespace()
-            .filter_map(|s| s.parse::<usize>().ok());
-        let rows = infos.next().ok_or(BadMatrixMarketFile)?;
-        let cols = infos.next().ok_or(BadMatrixMarketFile)?;
-        let entries = infos.next().ok_or(BadMatrixMarketFile)?;
-        if infos.next().is_some() {
-            return Err(BadMatrixMarketFile);
-        }
-        (rows, cols, entries)
-    };
-    let nnz_max = if sym_mode == SymmetryMode::General {
-        entries
-    } else {
-        2 * entries
-    };
-    let mut row_inds = Vec::with_capacity(nnz_max);
-    let mut col_inds = Vec::with_capacity(nnz_max);
-    let mut data = Vec::with_capacity(nnz_max);
-    // one non-zero entry per non-empty line
-    for _ in 0..entries {
-        // skip empty lines (no comment line should appear)
-        loop {
-            line.clear();
-            let len = reader.read_line(&mut line)?;
-            // check for an all whitespace line
-            if len != 0 && line.split_whitespace().next() == None {
-                continue;
-            } else {
-                break;
-            }
-        }
-        // Non-zero entries are lines of the form:
-        // row col value
-        // if the data type is integer of real, and
-        // row col real imag
-        // if the data type is complex.
-        // Again, this is with arbitrary amounts of whitespace
-        let mut entry = line.split_whitespace();
-        let row = entry
-            .next()
-            .ok_or(BadMatrixMarketFile)
-            .and_then(|s| s.parse::<usize>().or(Err(BadMatrixMarketFile)))?;
-        let col = entry
-            .next()
-            .ok_or(BadMatrixMarketFile)
-            .and_then(|s| s.parse::<usize>().or(Err(BadMatrixMarketFile)))?;
-        // MatrixMarket indices are 1-based
-        let row = row.checked_sub(1).ok_or(BadMatrixMarketFile)?;
-        let col = col.checked_sub(1).ok_or(BadMatrixMarketFile)?;
-        let val: N = match data_type {
-            DataType::Integer => {
-                let val =
-                    entry.next().ok_or(BadMatrixMarketFile).and_then(|s| {
-                        s.parse::<isize>().or(Err(BadMatrixMarketFile))
-                    })?;
-                NumCast::from(val).unwrap()
-            }
-            DataType::Real => {
-                let val =
-                    entry.next().ok_or(BadMatrixMarketFile).and_then(|s| {
-                        s.parse::<f64>().or(Err(BadMatrixMarketFile))
-                    })?;
-                NumCast::from(val).unwrap()
-            }
-            DataType::Complex => unreachable!(),
-        };
-        row_inds.push(I::from_usize(row));
-        col_inds.push(I::from_usize(col));
-        data.push(val.clone());
-        if sym_mode != SymmetryMode::General && row != col {
-            if sym_mode == SymmetryMode::Hermitian {
-                unreachable!();
-            } else {
-                row_inds.push(I::from_usize(col));
-                col_inds.push(I::from_usize(row));
-                data.push(val);
-            }
-        }
-        if sym_mode == SymmetryMode::SkewSymmetric && row == col {
-            return Err(BadMatrixMarketFile);
-        }
-        if entry.next().is_some() {
-            return Err(BadMatrixMarketFile);
+    let sym_mode = header.sym_mode;
+    let mut mat = Array2::<N>::zeros((rows, cols));
+    for (row, col) in array_locations(rows, cols, sym_mode) {
+        lines.next_entry_line()?;
+        let (_, _, val, mirrored) = parse_entry(
+            &lines.line,
+            &header,
+            (rows, cols),
+            Some((row, col)),
+            None,
+        )
+        .map_err(|e| lines.error(e))?;
+        if let Some(mirrored) = mirrored {
+            mat[[col, row]] = mirrored;
         }
+        mat[[row, col]] = val;
     }
+    lines.check_end()?;
+    Ok(mat)
+}
 
-    Ok(TriMatI::from_triplets(
-        (rows, cols),
-        row_inds,
-        col_inds,
-        data,
-    ))
+/// Formatting of the values written into a Matrix Market file.
+///
+/// This only affects floating point values, integers are always written
+/// in full.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValueFormat {
+    /// The shortest decimal representation that reads back to the same
+    /// value, as given by `Display`.
+    Shortest,
+    /// Decimal representation with the given number of digits after the
+    /// decimal point.
+    Fixed(usize),
+    /// Scientific notation with the given number of digits after the
+    /// decimal point.
+    Scientific(usize),
 }
 
 /// Write a sparse matrix into the matrix market format.
@@ -275,35 +1455,54 @@ pub fn write_matrix_market<'a, N, I, M, P>(
 ) -> Result<(), io::Error>
 where
     I: 'a + SpIndex + fmt::Display,
-    N: 'a + PrimitiveKind + Copy + fmt::Display,
+    N: 'a + PrimitiveKind + Copy + MatrixMarketDisplay,
     M: IntoIterator<Item = (&'a N, (I, I))> + SparseMat,
     P: AsRef<Path>,
 {
-    let (rows, cols, nnz) = (mat.rows(), mat.cols(), mat.nnz());
     let f = File::create(path)?;
     let mut writer = io::BufWriter::new(f);
+    write_matrix_market_to_bufwrite(&mut writer, mat)?;
+    writer.flush()
+}
 
-    // header
-    let data_type = match N::num_kind() {
-        NumKind::Integer => "integer",
-        NumKind::Float => "real",
-        NumKind::Complex => "complex",
-    };
-    writeln!(
+/// Write a sparse matrix into the matrix market format, using any
+/// `io::Write` as output.
+///
+/// No buffering is performed, so it is advised to wrap unbuffered writers
+/// such as files or sockets in an `io::BufWriter`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::CsMat;
+/// let eye: CsMat<i32> = CsMat::eye(2);
+/// let mut buffer = Vec::new();
+/// sprs::io::write_matrix_market_to_bufwrite(&mut buffer, &eye).unwrap();
+/// let expected = "%%MatrixMarket matrix coordinate integer general\n\
+///                 % written by sprs\n\
+///                 2 2 2\n\
+///                 1 1 1\n\
+///                 2 2 1\n";
+/// assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+/// ```
+pub fn write_matrix_market_to_bufwrite<'a, N, I, M, W>(
+    writer: &mut W,
+    mat: M,
+) -> Result<(), io::Error>
+where
+    I: 'a + SpIndex + fmt::Display,
+    N: 'a + PrimitiveKind + Copy + MatrixMarketDisplay,
+    M: IntoIterator<Item = (&'a N, (I, I))> + SparseMat,
+    W: Write + ?Sized,
+{
+    let nnz = mat.nnz();
+    write_matrix_market_impl(
         writer,
-        "%%MatrixMarket matrix coordinate {} general",
-        data_type
-    )?;
-    writeln!(writer, "% written by sprs")?;
-
-    // dimensions and nnz
-    writeln!(writer, "{} {} {}", rows, cols, nnz)?;
-
-    // entries
-    for (val, (row, col)) in mat.into_iter() {
-        writeln!(writer, "{} {} {}", row.index() + 1, col.index() + 1, val)?;
-    }
-    Ok(())
+        mat,
+        SymmetryMode::General,
+        nnz,
+        ValueFormat::Shortest,
+    )
 }
 
 /// Write a symmetric sparse matrix into the matrix market format.
@@ -324,13 +1523,112 @@ pub fn write_matrix_market_sym<'a, N, I, M, P>(
 ) -> Result<(), io::Error>
 where
     I: 'a + SpIndex + fmt::Display,
-    N: 'a + PrimitiveKind + Copy + fmt::Display,
-    M: IntoIterator<Item = (&'a N, (I, I))> + SparseMat,
+    N: 'a + PrimitiveKind + Copy + MatrixMarketDisplay,
+    M: IntoIterator<Item = (&'a N, (I, I))> + SparseMat + Clone,
     P: AsRef<Path>,
 {
-    let (rows, cols, nnz) = (mat.rows(), mat.cols(), mat.nnz());
     let f = File::create(path)?;
     let mut writer = io::BufWriter::new(f);
+    write_matrix_market_sym_to_bufwrite(&mut writer, mat, sym)?;
+    writer.flush()
+}
+
+/// Write a symmetric sparse matrix into the matrix market format, using
+/// any `io::Write` as output.
+///
+/// See `write_matrix_market_sym` for the handling of symmetry, and
+/// `write_matrix_market_to_bufwrite` for the buffering of the output.
+///
+/// The matrix is iterated twice, once to count the entries to write and
+/// once to write them, so that the output does not need to be seekable.
+pub fn write_matrix_market_sym_to_bufwrite<'a, N, I, M, W>(
+    writer: &mut W,
+    mat: M,
+    sym: SymmetryMode,
+) -> Result<(), io::Error>
+where
+    I: 'a + SpIndex + fmt::Display,
+    N: 'a + PrimitiveKind + Copy + MatrixMarketDisplay,
+    M: IntoIterator<Item = (&'a N, (I, I))> + SparseMat + Clone,
+    W: Write + ?Sized,
+{
+    write_matrix_market_with_format_to_bufwrite(
+        writer,
+        mat,
+        sym,
+        ValueFormat::Shortest,
+    )
+}
+
+/// Write a sparse matrix into the matrix market format, using any
+/// `io::Write` as output, with the given symmetry and value formatting.
+///
+/// This is the most general Matrix Market writer, see
+/// `write_matrix_market_sym_to_bufwrite` for more details.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::{SymmetryMode, ValueFormat};
+/// use sprs::CsMat;
+/// let mat = CsMat::new((1, 2), vec![0, 2], vec![0, 1], vec![1. / 3., 2.]);
+/// let mut buffer = Vec::new();
+/// sprs::io::write_matrix_market_with_format_to_bufwrite(
+///     &mut buffer,
+///     &mat,
+///     SymmetryMode::General,
+///     ValueFormat::Scientific(3),
+/// )
+/// .unwrap();
+/// let written = String::from_utf8(buffer).unwrap();
+/// assert!(written.ends_with("1 1 3.333e-1\n1 2 2.000e0\n"));
+/// ```
+pub fn write_matrix_market_with_format_to_bufwrite<'a, N, I, M, W>(
+    writer: &mut W,
+    mat: M,
+    sym: SymmetryMode,
+    format: ValueFormat,
+) -> Result<(), io::Error>
+where
+    I: 'a + SpIndex + fmt::Display,
+    N: 'a + PrimitiveKind + Copy + MatrixMarketDisplay,
+    M: IntoIterator<Item = (&'a N, (I, I))> + SparseMat + Clone,
+    W: Write + ?Sized,
+{
+    let entries = match sym {
+        SymmetryMode::General => mat.nnz(),
+        _ => mat
+            .clone()
+            .into_iter()
+            .filter(|&(_, (r, c))| is_written(sym, r, c))
+            .count(),
+    };
+    write_matrix_market_impl(writer, mat, sym, entries, format)
+}
+
+/// Whether the entry at (row, col) should be written for the given symmetry
+fn is_written<I: SpIndex>(sym: SymmetryMode, row: I, col: I) -> bool {
+    match sym {
+        SymmetryMode::General => true,
+        SymmetryMode::SkewSymmetric => row < col,
+        _ => row <= col,
+    }
+}
+
+fn write_matrix_market_impl<'a, N, I, M, W>(
+    writer: &mut W,
+    mat: M,
+    sym: SymmetryMode,
+    entries: usize,
+    format: ValueFormat,
+) -> Result<(), io::Error>
+where
+    I: 'a + SpIndex + fmt::Display,
+    N: 'a + PrimitiveKind + Copy + MatrixMarketDisplay,
+    M: IntoIterator<Item = (&'a N, (I, I))> + SparseMat,
+    W: Write + ?Sized,
+{
+    let (rows, cols) = (mat.rows(), mat.cols());
 
     // header
     let data_type = match N::num_kind() {
@@ -351,81 +1649,48 @@ where
     )?;
     writeln!(writer, "% written by sprs")?;
 
-    // We cannot know in advance how many entries will be written since
-    // this is affected by the symmetry mode. However, we do know that it
-    // can't be greater than the current nnz. Thus, the text size required
-    // to store the number of entries can only decrease. We record the position
-    // where we wrote the header and will later rewrite the number of entries,
-    // replacing possible extra digits by spaces.
-    let dim_header_pos = writer.seek(SeekFrom::Current(0))?;
     // dimensions and nnz
-    writeln!(writer, "{} {} {}", rows, cols, nnz)?;
+    writeln!(writer, "{} {} {}", rows, cols, entries)?;
 
     // entries
-    let mut entries = 0;
-    match sym {
-        SymmetryMode::General => {
-            for (val, (row, col)) in mat.into_iter() {
-                writeln!(
-                    writer,
-                    "{} {} {}",
-                    row.index() + 1,
-                    col.index() + 1,
-                    val
-                )?;
-                entries += 1;
-            }
-        }
-        SymmetryMode::SkewSymmetric => {
-            for (val, (row, col)) in
-                mat.into_iter().filter(|&(_, (r, c))| r < c)
-            {
-                writeln!(
-                    writer,
-                    "{} {} {}",
-                    row.index() + 1,
-                    col.index() + 1,
-                    val
-                )?;
-                entries += 1;
-            }
-        }
-        _ => {
-            for (val, (row, col)) in
-                mat.into_iter().filter(|&(_, (r, c))| r <= c)
-            {
-                writeln!(
-                    writer,
-                    "{} {} {}",
-                    row.index() + 1,
-                    col.index() + 1,
-                    val
-                )?;
-                entries += 1;
-            }
+    let mut written = 0;
+    for (val, (row, col)) in mat.into_iter() {
+        if !is_written(sym, row, col) {
+            continue;
         }
-    };
-    assert!(entries <= nnz);
-    writer.seek(SeekFrom::Start(dim_header_pos))?;
-    write!(writer, "{} {} {}", rows, cols, entries)?;
-    let dim_header_size = format!("{} {} {}", rows, cols, nnz).len();
-    let new_size = format!("{} {} {}", rows, cols, entries).len();
-    if new_size < dim_header_size {
-        let nb_spaces = dim_header_size - new_size;
-        for _ in 0..nb_spaces {
-            writer.write_all(b" ")?;
+        let (row, col, val) =
+            (row.index() + 1, col.index() + 1, MmDisplay(val));
+        match format {
+            ValueFormat::Shortest => {
+                writeln!(writer, "{} {} {}", row, col, val)?
+            }
+            ValueFormat::Fixed(prec) => {
+                writeln!(writer, "{} {} {:.*}", row, col, prec, val)?
+            }
+            ValueFormat::Scientific(prec) => {
+                writeln!(writer, "{} {} {:.*e}", row, col, prec, val)?
+            }
         }
+        written += 1;
     }
+    assert_eq!(written, entries);
     Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::{
-        read_matrix_market, read_matrix_market_from_bufread,
-        write_matrix_market, write_matrix_market_sym, IoError, SymmetryMode,
+        read_matrix_market, read_matrix_market_array,
+        read_matrix_market_from_bufread, read_matrix_market_with_fill,
+        write_matrix_market, write_matrix_market_sym,
+        write_matrix_market_sym_to_bufwrite, write_matrix_market_to_bufwrite,
+        write_matrix_market_with_format_to_bufwrite, IoError,
+        MatrixMarketError, MatrixMarketErrorKind, MatrixMarketReader,
+        SymmetryMode, ValueFormat,
     };
     use crate::CsMat;
+    use ndarray::arr2;
+    use num_complex::{Complex32, Complex64};
     use tempdir::TempDir;
     #[test]
     fn simple_matrix_market_read() {
@@ -481,18 +1746,208 @@ mod test {
         assert_eq!(mat.data(), &[1., 1., 1., 6., 2., -2., 3., 1.]);
     }
 
+    /// Build the expected error of a bad Matrix Market file
+    fn bad_file(
+        line: usize,
+        kind: MatrixMarketErrorKind,
+        token: Option<&str>,
+    ) -> IoError {
+        IoError::BadMatrixMarketFile(MatrixMarketError {
+            line,
+            token: token.map(str::to_string),
+            kind,
+        })
+    }
+
+    /// Build the expected error of an unsupported Matrix Market file
+    fn unsupported(line: usize, kind: MatrixMarketErrorKind) -> IoError {
+        IoError::UnsupportedMatrixMarketFormat(MatrixMarketError {
+            line,
+            token: None,
+            kind,
+        })
+    }
+
     #[test]
     fn matrix_market_read_fail_too_many_in_entry() {
         let path = "data/matrix_market/bad_files/too_many_elems_in_entry.mm";
         let res = read_matrix_market::<f64, i32, _>(path);
-        assert_eq!(res.unwrap_err(), IoError::BadMatrixMarketFile);
+        let kind = MatrixMarketErrorKind::TrailingField;
+        assert_eq!(res.unwrap_err(), bad_file(5, kind, Some("4")));
     }
 
     #[test]
     fn matrix_market_read_fail_not_enough_entries() {
         let path = "data/matrix_market/bad_files/not_enough_entries.mm";
         let res = read_matrix_market::<f64, i32, _>(path);
-        assert_eq!(res.unwrap_err(), IoError::BadMatrixMarketFile);
+        let kind = MatrixMarketErrorKind::NotEnoughEntries;
+        assert_eq!(res.unwrap_err(), bad_file(33, kind, None));
+    }
+
+    #[test]
+    fn matrix_market_read_fail_too_many_entries() {
+        let path = "data/matrix_market/bad_files/too_many_entries.mm";
+        let res = read_matrix_market::<f64, i32, _>(path);
+        let kind = MatrixMarketErrorKind::TooManyEntries;
+        assert_eq!(res.unwrap_err(), bad_file(5, kind, Some("1")));
+    }
+
+    #[test]
+    fn matrix_market_read_fail_index_out_of_bounds() {
+        let path = "data/matrix_market/bad_files/index_out_of_bounds.mm";
+        let err = read_matrix_market::<f64, i32, _>(path).unwrap_err();
+        let kind = MatrixMarketErrorKind::IndexOutOfBounds;
+        assert_eq!(err, bad_file(5, kind, Some("4")));
+        assert_eq!(
+            err.to_string(),
+            "Bad matrix market file, line 5: \
+             index out of bounds of the declared shape `4`."
+        );
+    }
+
+    #[test]
+    fn matrix_market_read_fail_skew_symmetric_diagonal() {
+        let path = "data/matrix_market/bad_files/skew_symmetric_diagonal.mm";
+        let res = read_matrix_market::<i32, i32, _>(path);
+        let kind = MatrixMarketErrorKind::DiagonalInSkewSymmetric;
+        assert_eq!(res.unwrap_err(), bad_file(4, kind, None));
+    }
+
+    #[test]
+    fn matrix_market_read_fail_bad_header() {
+        let path = "data/matrix_market/bad_files/bad_header.mm";
+        let res = read_matrix_market::<f64, i32, _>(path);
+        let kind = MatrixMarketErrorKind::BadHeaderField;
+        assert_eq!(res.unwrap_err(), bad_file(1, kind, Some("double")));
+
+        let mut reader = std::io::Cursor::new("%%MatrixMarket matrix\n");
+        let res = read_matrix_market_from_bufread::<f64, i32, _>(&mut reader);
+        let kind = MatrixMarketErrorKind::MissingField;
+        assert_eq!(res.unwrap_err(), bad_file(1, kind, None));
+
+        let mut reader = std::io::Cursor::new(
+            "%%MatrixMarket matrix coordinate real symmetric\n2 3 0\n",
+        );
+        let res = read_matrix_market_from_bufread::<f64, i32, _>(&mut reader);
+        let kind = MatrixMarketErrorKind::NonSquareSymmetric;
+        assert_eq!(res.unwrap_err(), bad_file(2, kind, None));
+    }
+
+    #[test]
+    fn stream_matrix_market_entries() {
+        for path in &[
+            "data/matrix_market/simple.mm",
+            "data/matrix_market/symmetric.mm",
+            "data/matrix_market/array.mm",
+            "data/matrix_market/array_symmetric.mm",
+            "data/matrix_market/skew_symmetric.mm",
+            "data/matrix_market/array_skew_symmetric.mm",
+        ] {
+            let reader =
+                MatrixMarketReader::<f64, usize, _>::open(path).unwrap();
+            let mat = read_matrix_market::<f64, usize, _>(path).unwrap();
+            let entries: Vec<_> = reader.map(Result::unwrap).collect();
+            let expected: Vec<_> =
+                mat.triplet_iter().map(|(&v, loc)| (v, loc)).collect();
+            assert_eq!(entries, expected);
+        }
+
+        let path = "data/matrix_market/hermitian.mm";
+        let reader = MatrixMarketReader::<Complex64, u16, _>::open(path)
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter(|&(_, (row, col))| row < col);
+        let upper: Vec<_> = reader.collect();
+        assert_eq!(
+            upper,
+            vec![
+                (Complex64::new(1., 1.), (0, 1)),
+                (Complex64::new(0., -3.), (1, 2)),
+            ]
+        );
+
+        let path = "data/matrix_market/pattern.mm";
+        let reader = MatrixMarketReader::<i32, usize, _>::open(path).unwrap();
+        assert_eq!(reader.shape(), (4, 4));
+        assert_eq!(reader.nb_entries(), 4);
+        assert_eq!(reader.symmetry(), SymmetryMode::Symmetric);
+        let sum: i32 = reader.with_fill(1).map(|e| e.unwrap().0).sum();
+        assert_eq!(sum, 7);
+    }
+
+    #[test]
+    fn stream_matrix_market_errors() {
+        let path = "data/matrix_market/bad_files/index_out_of_bounds.mm";
+        let mut reader =
+            MatrixMarketReader::<f64, usize, _>::open(path).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), (1.5, (0, 0)));
+        let kind = MatrixMarketErrorKind::IndexOutOfBounds;
+        assert_eq!(
+            reader.next().unwrap().unwrap_err(),
+            bad_file(5, kind, Some("4"))
+        );
+        assert!(reader.next().is_none());
+
+        let path = "data/matrix_market/bad_files/too_many_entries.mm";
+        let reader = MatrixMarketReader::<f64, usize, _>::open(path).unwrap();
+        let entries: Vec<_> = reader.collect();
+        assert_eq!(entries.len(), 3);
+        let kind = MatrixMarketErrorKind::TooManyEntries;
+        assert_eq!(
+            *entries[2].as_ref().unwrap_err(),
+            bad_file(5, kind, Some("1"))
+        );
+    }
+
+    #[test]
+    fn stream_matrix_market_row_blocks() {
+        let mat = CsMat::new(
+            (5, 4),
+            vec![0, 2, 2, 3, 5, 6],
+            vec![0, 3, 1, 0, 2, 3],
+            vec![1, 2, 3, 4, 5, 6],
+        );
+        let mut buffer = Vec::new();
+        write_matrix_market_to_bufwrite(&mut buffer, &mat).unwrap();
+        for block_rows in 1..7 {
+            let reader =
+                MatrixMarketReader::<i32, usize, _>::new(&buffer[..]).unwrap();
+            let mut next_row = 0;
+            for block in reader.row_blocks(block_rows) {
+                let (start, block) = block.unwrap();
+                assert_eq!(start, next_row);
+                next_row += block.rows();
+                assert_eq!(block, mat.select_rows(start..next_row));
+            }
+            assert_eq!(next_row, 5);
+        }
+
+        // duplicates are summed, and columns need not be sorted
+        let text = "%%MatrixMarket matrix coordinate integer general\n\
+                    3 3 4\n\
+                    1 3 1\n\
+                    1 1 2\n\
+                    1 3 3\n\
+                    3 2 4\n";
+        let reader =
+            MatrixMarketReader::<i32, usize, _>::new(text.as_bytes()).unwrap();
+        let blocks: Vec<_> = reader.row_blocks(2).map(Result::unwrap).collect();
+        let expected =
+            CsMat::new((2, 3), vec![0, 2, 2], vec![0, 2], vec![2, 4]);
+        assert_eq!(blocks[0], (0, expected));
+        let expected = CsMat::new((1, 3), vec![0, 1], vec![1], vec![4]);
+        assert_eq!(blocks[1], (2, expected));
+
+        // the rows of simple.mm are not sorted
+        let path = "data/matrix_market/simple.mm";
+        let reader = MatrixMarketReader::<f64, usize, _>::open(path).unwrap();
+        let mut blocks = reader.row_blocks(10);
+        let kind = MatrixMarketErrorKind::UnsortedRows;
+        assert_eq!(
+            blocks.next().unwrap().unwrap_err(),
+            bad_file(29, kind, None)
+        );
+        assert!(blocks.next().is_none());
     }
 
     #[test]
@@ -542,8 +1997,7 @@ mod test {
     }
 
     #[test]
-    /// Test whether the seek and replace strategy in the symmetric write
-    /// works.
+    /// Test whether the entry count of the symmetric write is correct.
     fn tricky_symmetric_matrix_market() {
         // design a 5x5 symmetric matrix such that the number
         // of nonzeros has more digits than the number of symmetric entries
@@ -569,18 +2023,32 @@ mod test {
 
     #[test]
     fn skew_symmetric_matrix_market() {
-        let mat = CsMat::new(
-            (5, 5),
-            vec![0, 2, 4, 6, 8, 10],
-            vec![1, 4, 0, 2, 1, 3, 2, 4, 0, 3],
-            vec![2, 1, 2, 3, 3, 5, 5, 4, 1, 4],
-        );
+        let path = "data/matrix_market/skew_symmetric.mm";
+        let mat = read_matrix_market::<f64, usize, _>(path).unwrap();
+        let expected = arr2(&[
+            [0., -1.5, 2., 0.],
+            [1.5, 0., 0., 0.],
+            [-2., 0., 0., -4.25],
+            [0., 0., 4.25, 0.],
+        ]);
+        assert_eq!(mat.to_csr().to_dense(), expected);
+
         let tmp_dir = TempDir::new("sprs-tmp").unwrap();
         let save_path = tmp_dir.path().join("skew_symmetric.mm");
+        let mat = mat.to_csr();
         write_matrix_market_sym(&save_path, &mat, SymmetryMode::SkewSymmetric)
             .unwrap();
-        let mat2 = read_matrix_market::<i32, usize, _>(&save_path).unwrap();
+        let mat2 = read_matrix_market::<f64, usize, _>(&save_path).unwrap();
         assert_eq!(mat, mat2.to_csr());
+
+        let path = "data/matrix_market/array_skew_symmetric.mm";
+        let dense = read_matrix_market_array::<i32, _>(path).unwrap();
+        assert_eq!(dense, arr2(&[[0, -1, 2], [1, 0, -3], [-2, 3, 0]]));
+
+        // the mirrored entries cannot be represented by unsigned types
+        let res = read_matrix_market_array::<u32, _>(path);
+        let kind = MatrixMarketErrorKind::BadValue;
+        assert_eq!(res.unwrap_err(), bad_file(3, kind, Some("1")));
     }
 
     #[test]
@@ -598,4 +2066,224 @@ mod test {
         let mat2 = read_matrix_market::<i32, usize, _>(&save_path).unwrap();
         assert_eq!(mat, mat2.to_csr());
     }
+
+    #[test]
+    fn complex_matrix_market_read() {
+        let path = "data/matrix_market/complex.mm";
+        let mat = read_matrix_market::<Complex64, usize, _>(path).unwrap();
+        assert_eq!(mat.shape(), (3, 4));
+        assert_eq!(mat.row_inds(), &[0, 1, 2, 2]);
+        assert_eq!(mat.col_inds(), &[0, 2, 0, 3]);
+        assert_eq!(
+            mat.data(),
+            &[
+                Complex64::new(1.5, -2.),
+                Complex64::new(0., 1.),
+                Complex64::new(-32.5, 4.5),
+                Complex64::new(2., 0.),
+            ]
+        );
+        // complex values cannot be read into a real matrix
+        let res = read_matrix_market::<f64, usize, _>(path);
+        let kind = MatrixMarketErrorKind::ComplexIntoReal;
+        assert_eq!(res.unwrap_err(), unsupported(4, kind));
+        // but real values can be read into a complex matrix
+        let path = "data/matrix_market/simple_int.mm";
+        let mat = read_matrix_market::<Complex32, usize, _>(path).unwrap();
+        assert_eq!(mat.data()[5], Complex32::new(-2., 0.));
+    }
+
+    #[test]
+    fn hermitian_matrix_market_read() {
+        let path = "data/matrix_market/hermitian.mm";
+        let mat = read_matrix_market::<Complex64, usize, _>(path).unwrap();
+        let expected = CsMat::new(
+            (3, 3),
+            vec![0, 2, 4, 6],
+            vec![0, 1, 0, 2, 1, 2],
+            vec![
+                Complex64::new(2., 0.),
+                Complex64::new(1., 1.),
+                Complex64::new(1., -1.),
+                Complex64::new(0., -3.),
+                Complex64::new(0., 3.),
+                Complex64::new(5., 0.),
+            ],
+        );
+        assert_eq!(mat.to_csr(), expected);
+    }
+
+    #[test]
+    fn read_write_read_complex_matrix_market() {
+        let path = "data/matrix_market/complex.mm";
+        let mat = read_matrix_market::<Complex64, usize, _>(path).unwrap();
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let save_path = tmp_dir.path().join("complex.mm");
+        write_matrix_market(&save_path, &mat).unwrap();
+        let mat2 =
+            read_matrix_market::<Complex64, usize, _>(&save_path).unwrap();
+        assert_eq!(mat, mat2);
+
+        let path = "data/matrix_market/hermitian.mm";
+        let csc = read_matrix_market::<Complex64, usize, _>(path)
+            .unwrap()
+            .to_csc();
+        let save_path = tmp_dir.path().join("hermitian.mm");
+        write_matrix_market_sym(&save_path, &csc, SymmetryMode::Hermitian)
+            .unwrap();
+        let mat2 =
+            read_matrix_market::<Complex64, usize, _>(&save_path).unwrap();
+        assert_eq!(csc, mat2.to_csc());
+    }
+
+    #[test]
+    fn pattern_matrix_market_read() {
+        let path = "data/matrix_market/pattern.mm";
+        let pattern = read_matrix_market::<(), u32, _>(path).unwrap();
+        assert_eq!(pattern.shape(), (4, 4));
+        let structure = pattern.to_csr_structure();
+        assert_eq!(structure.indptr(), &[0, 2, 3, 5, 7]);
+        assert_eq!(structure.indices(), &[1, 2, 0, 0, 3, 2, 3]);
+        let structure = pattern.to_csc_structure();
+        assert_eq!(structure.indptr(), &[0, 2, 3, 5, 7]);
+        assert_eq!(structure.indices(), &[1, 2, 0, 0, 3, 2, 3]);
+
+        let mat = read_matrix_market_with_fill(path, 2_i32).unwrap();
+        let expected = CsMat::new(
+            (4, 4),
+            vec![0, 2, 3, 5, 7],
+            vec![1, 2, 0, 0, 3, 2, 3],
+            vec![2; 7],
+        );
+        assert_eq!(mat.to_csr(), expected);
+
+        // numeric types need a fill value
+        let res = read_matrix_market::<f64, usize, _>(path);
+        let kind = MatrixMarketErrorKind::PatternWithoutFill;
+        assert_eq!(res.unwrap_err(), unsupported(4, kind));
+
+        // the fill value is only used for pattern files, and the structure
+        // of any file can be read
+        let path = "data/matrix_market/simple.mm";
+        let mat = read_matrix_market_with_fill(path, 0.).unwrap();
+        assert_eq!(mat, read_matrix_market::<f64, usize, _>(path).unwrap());
+        let structure = read_matrix_market::<(), usize, _>(path)
+            .unwrap()
+            .to_csc_structure();
+        assert_eq!(structure, mat.to_csc().structure_view().to_owned());
+    }
+
+    #[test]
+    fn array_matrix_market_read() {
+        let path = "data/matrix_market/array.mm";
+        let dense = read_matrix_market_array::<f32, _>(path).unwrap();
+        assert_eq!(dense, arr2(&[[1., 0., 3.], [2., -1., 0.]]));
+        let mat = read_matrix_market::<f32, usize, _>(path).unwrap();
+        assert_eq!(mat.nnz(), 4);
+        assert_eq!(mat.to_csr().to_dense(), dense);
+
+        let path = "data/matrix_market/array_symmetric.mm";
+        let dense = read_matrix_market_array::<i64, _>(path).unwrap();
+        let expected = arr2(&[[1, 2, 0], [2, 4, 5], [0, 5, 6]]);
+        assert_eq!(dense, expected);
+        let mat = read_matrix_market::<i64, usize, _>(path).unwrap();
+        assert_eq!(mat.nnz(), 7);
+        assert_eq!(mat.to_csc().to_dense(), expected);
+
+        // a coordinate file cannot be read as a dense matrix
+        let path = "data/matrix_market/simple.mm";
+        let res = read_matrix_market_array::<f64, _>(path);
+        let kind = MatrixMarketErrorKind::CoordinateIntoArray;
+        assert_eq!(res.unwrap_err(), unsupported(1, kind));
+    }
+
+    #[test]
+    fn write_matrix_market_to_buffer() {
+        let path = "data/matrix_market/simple.mm";
+        let mat = read_matrix_market::<f64, usize, _>(path).unwrap();
+        let mut buffer = Vec::new();
+        write_matrix_market_to_bufwrite(&mut buffer, &mat).unwrap();
+        let mut reader = std::io::Cursor::new(buffer);
+        let mat2 =
+            read_matrix_market_from_bufread::<f64, usize, _>(&mut reader)
+                .unwrap();
+        assert_eq!(mat, mat2);
+
+        let path = "data/matrix_market/symmetric.mm";
+        let csc = read_matrix_market::<f64, usize, _>(path).unwrap().to_csc();
+        let mut buffer = Vec::new();
+        write_matrix_market_sym_to_bufwrite(
+            &mut buffer,
+            &csc,
+            SymmetryMode::Symmetric,
+        )
+        .unwrap();
+        let written = String::from_utf8(buffer).unwrap();
+        let size_line = written.lines().nth(2).unwrap();
+        assert_eq!(size_line, "5 5 6");
+        let mut reader = std::io::Cursor::new(written);
+        let mat2 =
+            read_matrix_market_from_bufread::<f64, usize, _>(&mut reader)
+                .unwrap();
+        assert_eq!(csc, mat2.to_csc());
+    }
+
+    #[test]
+    fn write_matrix_market_value_formats() {
+        let mat = CsMat::new(
+            (2, 2),
+            vec![0, 1, 2],
+            vec![0, 1],
+            vec![1. / 3., -1234.5],
+        );
+        let write = |format| {
+            let mut buffer = Vec::new();
+            write_matrix_market_with_format_to_bufwrite(
+                &mut buffer,
+                &mat,
+                SymmetryMode::General,
+                format,
+            )
+            .unwrap();
+            let written = String::from_utf8(buffer).unwrap();
+            written.lines().skip(3).collect::<Vec<_>>().join("\n")
+        };
+        assert_eq!(
+            write(ValueFormat::Shortest),
+            "1 1 0.3333333333333333\n2 2 -1234.5"
+        );
+        assert_eq!(write(ValueFormat::Fixed(2)), "1 1 0.33\n2 2 -1234.50");
+        assert_eq!(write(ValueFormat::Scientific(1)), "1 1 3.3e-1\n2 2 -1.2e3");
+
+        // integers are not affected by the formatting
+        let mat: CsMat<i32> = CsMat::eye(1);
+        let mut buffer = Vec::new();
+        write_matrix_market_with_format_to_bufwrite(
+            &mut buffer,
+            &mat,
+            SymmetryMode::General,
+            ValueFormat::Scientific(3),
+        )
+        .unwrap();
+        assert!(String::from_utf8(buffer).unwrap().ends_with("1 1 1\n"));
+
+        // complex values format both of their parts
+        let mat = CsMat::new(
+            (1, 1),
+            vec![0, 1],
+            vec![0],
+            vec![Complex64::new(0.5, -2.)],
+        );
+        let mut buffer = Vec::new();
+        write_matrix_market_with_format_to_bufwrite(
+            &mut buffer,
+            &mat,
+            SymmetryMode::General,
+            ValueFormat::Fixed(1),
+        )
+        .unwrap();
+        assert!(String::from_utf8(buffer)
+            .unwrap()
+            .ends_with("1 1 0.5 -2.0\n"));
+    }
 }
@@ -0,0 +1,530 @@
+//! A compact binary format for compressed matrices, which can be memory
+//! mapped
+//!
+//! A file is made of a 64 bytes header followed by the `indptr`, `indices`
+//! and `data` arrays of the matrix, each starting at an offset multiple of
+//! 64 bytes. All values are stored in little endian. The header contains:
+//!
+//! | offset | size | content                                               |
+//! |--------|------|-------------------------------------------------------|
+//! | 0      | 8    | the magic bytes `SPRSMAT` followed by a zero byte     |
+//! | 8      | 4    | the format version as a `u32`, currently 1            |
+//! | 12     | 1    | the storage, 0 for CSR and 1 for CSC                  |
+//! | 13     | 2    | the type of `indptr`, see below                       |
+//! | 15     | 2    | the type of `indices`                                 |
+//! | 17     | 2    | the type of `data`                                    |
+//! | 24     | 8    | the number of rows as a `u64`                         |
+//! | 32     | 8    | the number of columns as a `u64`                      |
+//! | 40     | 8    | the number of non-zero entries as a `u64`             |
+//!
+//! with zero padding between the fields. A type is stored as a kind, `i`
+//! for signed integers, `u` for unsigned integers, `f` for floating point
+//! numbers and `c` for complex numbers, followed by the size in bytes of
+//! its values, eg `f8` for `f64`. The real and imaginary parts of complex
+//! values are stored consecutively.
+//!
+//! Since the arrays are stored aligned and in their in-memory layout,
+//! `MmapCsMat` can map a file in memory and expose it as a `CsMatViewI`
+//! without copying it, the operating system reading the parts of the
+//! matrix as they are accessed.
+//!
+//! This module requires the `mmap` feature to be activated.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
+use std::path::Path;
+
+use memmap2::Mmap;
+use num_complex::Complex;
+
+use super::IoError;
+use crate::indexing::SpIndex;
+use crate::sparse::{CompressedStorage, CsMatBase, CsMatViewI};
+
+const MAGIC: &[u8; 8] = b"SPRSMAT\0";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 64;
+const ALIGNMENT: usize = 64;
+
+/// Scalar types that can be stored in the binary format.
+///
+/// This is implemented for the primitive numeric types and for
+/// `num_complex::Complex<f32>` and `Complex<f64>`.
+///
+/// # Safety
+///
+/// Implementors must be plain data types, for which any sequence of
+/// `mem::size_of::<Self>()` bytes is a valid value, aligned on at most 64
+/// bytes, and whose in-memory representation on little endian targets is
+/// the one written by `write_le`.
+pub unsafe trait BinaryElement: Copy {
+    /// The kind and byte size identifying this type in the file header.
+    fn binary_type() -> [u8; 2];
+
+    /// Write the little endian representation of this value.
+    fn write_le<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+macro_rules! binary_prim_impl {
+    ($prim: ty, $kind: expr) => {
+        unsafe impl BinaryElement for $prim {
+            fn binary_type() -> [u8; 2] {
+                [$kind, mem::size_of::<$prim>() as u8]
+            }
+
+            fn write_le<W: Write + ?Sized>(
+                &self,
+                writer: &mut W,
+            ) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+        }
+    };
+}
+
+binary_prim_impl!(i8, b'i');
+binary_prim_impl!(u8, b'u');
+binary_prim_impl!(i16, b'i');
+binary_prim_impl!(u16, b'u');
+binary_prim_impl!(i32, b'i');
+binary_prim_impl!(u32, b'u');
+binary_prim_impl!(i64, b'i');
+binary_prim_impl!(u64, b'u');
+binary_prim_impl!(isize, b'i');
+binary_prim_impl!(usize, b'u');
+binary_prim_impl!(f32, b'f');
+binary_prim_impl!(f64, b'f');
+
+macro_rules! binary_complex_impl {
+    ($prim: ty) => {
+        unsafe impl BinaryElement for Complex<$prim> {
+            fn binary_type() -> [u8; 2] {
+                [b'c', mem::size_of::<Complex<$prim>>() as u8]
+            }
+
+            fn write_le<W: Write + ?Sized>(
+                &self,
+                writer: &mut W,
+            ) -> io::Result<()> {
+                writer.write_all(&self.re.to_le_bytes())?;
+                writer.write_all(&self.im.to_le_bytes())
+            }
+        }
+    };
+}
+
+binary_complex_impl!(f32);
+binary_complex_impl!(f64);
+
+fn align(offset: usize) -> usize {
+    (offset + ALIGNMENT - 1) / ALIGNMENT * ALIGNMENT
+}
+
+/// The layout of a binary file, as described by its header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Layout {
+    storage: CompressedStorage,
+    shape: (usize, usize),
+    nnz: usize,
+    indices_offset: usize,
+    data_offset: usize,
+    len: usize,
+}
+
+impl Layout {
+    fn new<N, I, Iptr>(
+        storage: CompressedStorage,
+        shape: (usize, usize),
+        nnz: usize,
+    ) -> Option<Self> {
+        let outer = match storage {
+            CompressedStorage::CSR => shape.0,
+            CompressedStorage::CSC => shape.1,
+        };
+        let indptr_len = outer.checked_add(1)?;
+        let indptr_end = indptr_len
+            .checked_mul(mem::size_of::<Iptr>())?
+            .checked_add(HEADER_SIZE)?;
+        let indices_offset = align(indptr_end);
+        let indices_end = nnz
+            .checked_mul(mem::size_of::<I>())?
+            .checked_add(indices_offset)?;
+        let data_offset = align(indices_end);
+        let len = nnz
+            .checked_mul(mem::size_of::<N>())?
+            .checked_add(data_offset)?;
+        Some(Layout {
+            storage,
+            shape,
+            nnz,
+            indices_offset,
+            data_offset,
+            len,
+        })
+    }
+
+    fn header<N, I, Iptr>(&self) -> [u8; HEADER_SIZE]
+    where
+        N: BinaryElement,
+        I: BinaryElement,
+        Iptr: BinaryElement,
+    {
+        let mut header = [0; HEADER_SIZE];
+        header[..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12] = match self.storage {
+            CompressedStorage::CSR => 0,
+            CompressedStorage::CSC => 1,
+        };
+        header[13..15].copy_from_slice(&Iptr::binary_type());
+        header[15..17].copy_from_slice(&I::binary_type());
+        header[17..19].copy_from_slice(&N::binary_type());
+        header[24..32].copy_from_slice(&(self.shape.0 as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&(self.shape.1 as u64).to_le_bytes());
+        header[40..48].copy_from_slice(&(self.nnz as u64).to_le_bytes());
+        header
+    }
+
+    /// Read the layout of a file from its header, checking that it stores
+    /// the requested types.
+    fn parse<N, I, Iptr>(bytes: &[u8]) -> Result<Self, IoError>
+    where
+        N: BinaryElement,
+        I: BinaryElement,
+        Iptr: BinaryElement,
+    {
+        if bytes.len() < HEADER_SIZE || &bytes[..8] != MAGIC {
+            return Err(IoError::BadBinaryFile("not a sprs binary file"));
+        }
+        let read_u64 = |pos: usize| {
+            let mut field = [0; 8];
+            field.copy_from_slice(&bytes[pos..pos + 8]);
+            let val = u64::from_le_bytes(field);
+            if val > std::usize::MAX as u64 {
+                Err(IoError::BadBinaryFile("size too large for this target"))
+            } else {
+                Ok(val as usize)
+            }
+        };
+        if bytes[8..12] != VERSION.to_le_bytes() {
+            return Err(IoError::BadBinaryFile("unsupported format version"));
+        }
+        let storage = match bytes[12] {
+            0 => CompressedStorage::CSR,
+            1 => CompressedStorage::CSC,
+            _ => return Err(IoError::BadBinaryFile("invalid storage")),
+        };
+        if bytes[13..15] != Iptr::binary_type() {
+            return Err(IoError::BadBinaryFile("mismatched indptr type"));
+        }
+        if bytes[15..17] != I::binary_type() {
+            return Err(IoError::BadBinaryFile("mismatched indices type"));
+        }
+        if bytes[17..19] != N::binary_type() {
+            return Err(IoError::BadBinaryFile("mismatched data type"));
+        }
+        let shape = (read_u64(24)?, read_u64(32)?);
+        let nnz = read_u64(40)?;
+        let layout = Layout::new::<N, I, Iptr>(storage, shape, nnz)
+            .ok_or(IoError::BadBinaryFile("size too large for this target"))?;
+        if bytes.len() < layout.len {
+            return Err(IoError::BadBinaryFile("truncated file"));
+        }
+        Ok(layout)
+    }
+}
+
+/// Write a compressed matrix into the sprs binary format.
+///
+/// See the module documentation for a description of the format.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sprs::io::binary::{write_binary, MmapCsMat};
+/// use sprs::CsMat;
+/// let eye: CsMat<f64> = CsMat::eye(5);
+/// write_binary("/tmp/eye.spm", &eye).unwrap();
+/// let mapped = MmapCsMat::<f64, usize>::open("/tmp/eye.spm").unwrap();
+/// assert_eq!(mapped.view(), eye.view());
+/// ```
+pub fn write_binary<N, I, IptrS, IS, DS, Iptr, P>(
+    path: P,
+    mat: &CsMatBase<N, I, IptrS, IS, DS, Iptr>,
+) -> Result<(), io::Error>
+where
+    N: BinaryElement,
+    I: SpIndex + BinaryElement,
+    Iptr: SpIndex + BinaryElement,
+    IptrS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    P: AsRef<Path>,
+{
+    let f = File::create(path)?;
+    let mut writer = io::BufWriter::new(f);
+    write_binary_to_bufwrite(&mut writer, mat)?;
+    writer.flush()
+}
+
+/// Write a compressed matrix into the sprs binary format, using any
+/// `io::Write` as output.
+///
+/// See the module documentation for a description of the format.
+pub fn write_binary_to_bufwrite<N, I, IptrS, IS, DS, Iptr, W>(
+    writer: &mut W,
+    mat: &CsMatBase<N, I, IptrS, IS, DS, Iptr>,
+) -> Result<(), io::Error>
+where
+    N: BinaryElement,
+    I: SpIndex + BinaryElement,
+    Iptr: SpIndex + BinaryElement,
+    IptrS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    W: Write + ?Sized,
+{
+    // the first index pointer may be non zero for views of a larger matrix
+    let offset = mat.indptr()[0];
+    let range = offset.index()..mat.indptr()[mat.outer_dims()].index();
+    let layout =
+        Layout::new::<N, I, Iptr>(mat.storage(), mat.shape(), range.len())
+            .expect("matrix size overflow");
+    writer.write_all(&layout.header::<N, I, Iptr>())?;
+    let padding = [0; ALIGNMENT];
+    let mut pos = HEADER_SIZE;
+    for &ptr in mat.indptr() {
+        (ptr - offset).write_le(writer)?;
+    }
+    pos += mem::size_of_val(mat.indptr());
+    writer.write_all(&padding[..layout.indices_offset - pos])?;
+    for ind in &mat.indices()[range.clone()] {
+        ind.write_le(writer)?;
+    }
+    pos = layout.indices_offset + layout.nnz * mem::size_of::<I>();
+    writer.write_all(&padding[..layout.data_offset - pos])?;
+    for val in &mat.data()[range] {
+        val.write_le(writer)?;
+    }
+    Ok(())
+}
+
+/// A compressed matrix stored in a memory mapped file in the sprs binary
+/// format.
+///
+/// The matrix is accessed through `view`, without being read into memory
+/// beforehand.
+#[derive(Debug)]
+pub struct MmapCsMat<N, I, Iptr = I> {
+    mmap: Mmap,
+    layout: Layout,
+    _types: PhantomData<(N, I, Iptr)>,
+}
+
+impl<N, I, Iptr> MmapCsMat<N, I, Iptr>
+where
+    N: BinaryElement,
+    I: SpIndex + BinaryElement,
+    Iptr: SpIndex + BinaryElement,
+{
+    /// Map a file written by `write_binary`, checking the validity of
+    /// the stored matrix.
+    ///
+    /// The check reads the whole `indptr` and `indices` arrays, see
+    /// `open_unchecked` to only read the header.
+    ///
+    /// # Errors
+    ///
+    /// - if the file cannot be mapped, notably on big endian targets,
+    /// - if the header is invalid or its types do not match `N`, `I` and
+    ///   `Iptr`,
+    /// - if the file does not contain a valid compressed matrix.
+    ///
+    /// The file should not be modified while it is mapped, see the
+    /// documentation of `memmap2::Mmap`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        // Safety: the structure of the matrix is checked before returning
+        let mat = unsafe { Self::open_unchecked(path)? };
+        let layout = mat.layout;
+        CsMatViewI::new_view(
+            layout.storage,
+            layout.shape,
+            mat.indptr(),
+            mat.indices(),
+            mat.data(),
+        )
+        .map_err(|_| IoError::BadBinaryFile("invalid compressed structure"))?;
+        Ok(mat)
+    }
+
+    /// Map a file written by `write_binary`, only checking its header.
+    ///
+    /// # Safety
+    ///
+    /// The file must contain a valid compressed matrix, as algorithms are
+    /// free to assume the properties checked by `open` to perform unchecked
+    /// accesses.
+    pub unsafe fn open_unchecked<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, IoError> {
+        if cfg!(target_endian = "big") {
+            return Err(IoError::BadBinaryFile(
+                "memory mapping requires a little endian target",
+            ));
+        }
+        let f = File::open(path)?;
+        let mmap = Mmap::map(&f)?;
+        let layout = Layout::parse::<N, I, Iptr>(&mmap)?;
+        let mat: Self = MmapCsMat {
+            mmap,
+            layout,
+            _types: PhantomData,
+        };
+        let outer = mat.indptr().len() - 1;
+        if mat.indptr()[outer].try_index() != Some(layout.nnz) {
+            return Err(IoError::BadBinaryFile(
+                "last index pointer does not match the number of entries",
+            ));
+        }
+        Ok(mat)
+    }
+
+    /// Get a slice of `len` elements of type `T` starting at `offset`.
+    fn section<T>(&self, offset: usize, len: usize) -> &[T] {
+        // Safety: the header has been checked to describe a layout inside
+        // the file, the sections are aligned on 64 bytes from the start of
+        // the mapping which is page aligned, and `T` is a plain data type.
+        unsafe {
+            let ptr = self.mmap.as_ptr().add(offset) as *const T;
+            std::slice::from_raw_parts(ptr, len)
+        }
+    }
+
+    fn indptr(&self) -> &[Iptr] {
+        let outer = match self.layout.storage {
+            CompressedStorage::CSR => self.layout.shape.0,
+            CompressedStorage::CSC => self.layout.shape.1,
+        };
+        self.section(HEADER_SIZE, outer + 1)
+    }
+
+    fn indices(&self) -> &[I] {
+        self.section(self.layout.indices_offset, self.layout.nnz)
+    }
+
+    fn data(&self) -> &[N] {
+        self.section(self.layout.data_offset, self.layout.nnz)
+    }
+
+    /// A view of the mapped matrix.
+    pub fn view(&self) -> CsMatViewI<'_, N, I, Iptr> {
+        // Safety: the structure has been checked by `open`, or guaranteed
+        // by the caller of `open_unchecked`, and the last index pointer
+        // has been checked to be the length of `indices` and `data`.
+        unsafe {
+            CsMatViewI::new_view_raw(
+                self.layout.storage,
+                self.layout.shape,
+                self.indptr().as_ptr(),
+                self.indices().as_ptr(),
+                self.data().as_ptr(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_binary, write_binary_to_bufwrite, MmapCsMat};
+    use crate::io::IoError;
+    use crate::sparse::{CsMat, CsMatI};
+    use num_complex::Complex64;
+    use std::fs;
+    use tempdir::TempDir;
+
+    #[test]
+    fn write_map_binary() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let path = tmp_dir.path().join("mat.spm");
+        let mat = CsMat::new(
+            (3, 4),
+            vec![0, 2, 3, 5],
+            vec![0, 2, 1, 0, 3],
+            vec![1., 2., 3., 4., -1.5],
+        );
+        write_binary(&path, &mat).unwrap();
+        let mapped = MmapCsMat::<f64, usize>::open(&path).unwrap();
+        assert_eq!(mapped.view(), mat.view());
+
+        let csc: CsMatI<Complex64, u16, u32> = CsMatI::new_csc(
+            (2, 3),
+            vec![0, 1, 1, 3],
+            vec![1, 0, 1],
+            vec![
+                Complex64::new(1., 2.),
+                Complex64::new(3., 4.),
+                Complex64::new(5., 6.),
+            ],
+        );
+        write_binary(&path, &csc).unwrap();
+        let mapped = MmapCsMat::<Complex64, u16, u32>::open(&path).unwrap();
+        assert_eq!(mapped.view(), csc.view());
+        // the sections are aligned
+        let len = fs::metadata(&path).unwrap().len();
+        assert_eq!(len, 64 + 64 + 64 + 3 * 16);
+
+        // views can start in the middle of the data
+        let view = mat.view().middle_outer_views(1, 2);
+        write_binary(&path, &view).unwrap();
+        let mapped = MmapCsMat::<f64, usize>::open(&path).unwrap();
+        let expected = CsMat::new(
+            (2, 4),
+            vec![0, 1, 3],
+            vec![1, 0, 3],
+            vec![3., 4., -1.5],
+        );
+        assert_eq!(mapped.view(), expected.view());
+    }
+
+    #[test]
+    fn map_bad_files() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let path = tmp_dir.path().join("mat.spm");
+        let mat: CsMatI<f32, u32> = CsMatI::eye(3);
+        write_binary(&path, &mat).unwrap();
+        let res = MmapCsMat::<f64, u32>::open(&path);
+        assert_eq!(
+            res.unwrap_err(),
+            IoError::BadBinaryFile("mismatched data type")
+        );
+        let res = MmapCsMat::<f32, u64>::open(&path);
+        assert_eq!(
+            res.unwrap_err(),
+            IoError::BadBinaryFile("mismatched indptr type")
+        );
+
+        let mut bytes = Vec::new();
+        write_binary_to_bufwrite(&mut bytes, &mat).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let res = MmapCsMat::<f32, u32>::open(&path);
+        assert_eq!(res.unwrap_err(), IoError::BadBinaryFile("truncated file"));
+
+        // out of bounds index
+        bytes[128] = 3;
+        fs::write(&path, &bytes).unwrap();
+        let res = MmapCsMat::<f32, u32>::open(&path);
+        assert_eq!(
+            res.unwrap_err(),
+            IoError::BadBinaryFile("invalid compressed structure")
+        );
+
+        let res = MmapCsMat::<f32, u32>::open("data/matrix_market/simple.mm");
+        assert_eq!(
+            res.unwrap_err(),
+            IoError::BadBinaryFile("not a sprs binary file")
+        );
+    }
+}
@@ -0,0 +1,741 @@
+//! Reading and writing of graphs as adjacency matrices
+//!
+//! Two common graph file formats are supported:
+//!
+//! - edge lists, with one edge `u v` or weighted edge `u v w` per line,
+//!   using 0-based vertex indices. Lines starting with `#` or `%` are
+//!   comments.
+//! - the METIS graph format, whose header line `n m [fmt [ncon]]` gives the
+//!   number of vertices and of undirected edges, followed by one line per
+//!   vertex listing its 1-based neighbours, optionally with edge weights.
+//!   Lines starting with `%` are comments.
+//!
+//! A graph is represented by its square adjacency matrix in CSR storage,
+//! the entry at `(u, v)` holding the weight of the edge from `u` to `v`.
+//! Unweighted graphs can be read into pattern matrices, using `()` as the
+//! scalar type, or into numeric matrices, in which case all edges have
+//! weight one.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+use super::{parse_number, IoError, LineError, MatrixMarketRead};
+use crate::indexing::SpIndex;
+use crate::sparse::{CsMatBase, CsMatI};
+
+/// Whether the edges of a graph have a direction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EdgeDirection {
+    /// An edge `u v` only connects `u` to `v`.
+    Directed,
+    /// An edge `u v` connects `u` to `v` and `v` to `u`. The adjacency
+    /// matrix of an undirected graph is symmetric.
+    Undirected,
+}
+
+fn graph_error(
+    line: usize,
+    reason: &'static str,
+    token: Option<&str>,
+) -> IoError {
+    IoError::BadGraphFile(LineError::new(line, reason, token))
+}
+
+/// The weight of the edges of an unweighted graph
+fn unit_weight<N: MatrixMarketRead>(line: usize) -> Result<N, IoError> {
+    N::from_mm_pattern()
+        .or_else(|| N::from_mm_integer(1))
+        .ok_or_else(|| graph_error(line, "unweighted edges", None))
+}
+
+/// Build the CSR adjacency matrix of a graph from its edges, sorted by
+/// source then destination vertex.
+fn adjacency_matrix<N, I>(
+    vertices: usize,
+    edges: Vec<(usize, usize, N)>,
+    line: usize,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    I: SpIndex,
+{
+    let too_large = || {
+        let reason = "graph too large for the index type";
+        graph_error(line, reason, Some(&vertices.to_string()))
+    };
+    I::try_from_usize(vertices).ok_or_else(too_large)?;
+    I::try_from_usize(edges.len()).ok_or_else(too_large)?;
+    let mut indptr = vec![I::zero(); vertices + 1];
+    let mut indices = Vec::with_capacity(edges.len());
+    let mut data = Vec::with_capacity(edges.len());
+    for (u, v, w) in edges {
+        indptr[u + 1] += I::one();
+        indices.push(I::from_usize(v));
+        data.push(w);
+    }
+    for u in 0..vertices {
+        let prev = indptr[u];
+        indptr[u + 1] += prev;
+    }
+    Ok(CsMatI::new_trusted(
+        crate::CSR,
+        (vertices, vertices),
+        indptr,
+        indices,
+        data,
+    ))
+}
+
+/// Read a graph file in the edge list format, returning its adjacency
+/// matrix.
+///
+/// Each line describes an edge between two vertices given by their 0-based
+/// index, optionally followed by its weight. The number of vertices is one
+/// more than the largest vertex index. Edges of undirected graphs are
+/// stored in both directions in the adjacency matrix. If an edge is listed
+/// several times, including in both directions for undirected graphs, only
+/// its first occurrence is kept.
+///
+/// # Errors
+///
+/// - if a vertex index or a weight cannot be parsed, or a weight cannot be
+///   represented by the scalar type,
+/// - if a line has less than two or more than three fields,
+/// - if the index type cannot represent the adjacency matrix.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::graph::{read_edge_list, EdgeDirection};
+/// let path = "data/graph/simple.edges";
+/// let graph =
+///     read_edge_list::<f64, usize, _>(path, EdgeDirection::Undirected)
+///         .unwrap();
+/// assert_eq!(graph.shape(), (5, 5));
+/// assert_eq!(graph.get(0, 1), Some(&2.5));
+/// assert_eq!(graph.get(1, 0), Some(&2.5));
+/// ```
+pub fn read_edge_list<N, I, P>(
+    path: P,
+    direction: EdgeDirection,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    N: MatrixMarketRead,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let f = File::open(path)?;
+    read_edge_list_from_bufread(io::BufReader::new(f), direction)
+}
+
+/// Read a graph in the edge list format from an `io::BufRead`.
+///
+/// See `read_edge_list` for more details.
+pub fn read_edge_list_from_bufread<N, I, R>(
+    mut reader: R,
+    direction: EdgeDirection,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    N: MatrixMarketRead,
+    I: SpIndex,
+    R: io::BufRead,
+{
+    let mut edges = Vec::new();
+    let mut vertices = 0;
+    let mut line = String::new();
+    let mut line_nb = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_nb += 1;
+        let content = line.trim();
+        if content.is_empty() || content.starts_with(&['#', '%'][..]) {
+            continue;
+        }
+        let mut tokens = content.split_whitespace();
+        let mut vertex = || {
+            let token = tokens.next().ok_or_else(|| {
+                graph_error(line_nb, "missing vertex", Some(content))
+            })?;
+            token.parse::<usize>().map_err(|_| {
+                graph_error(line_nb, "invalid vertex", Some(token))
+            })
+        };
+        let (u, v) = (vertex()?, vertex()?);
+        let weight: N = match tokens.next() {
+            Some(token) => parse_number(token).ok_or_else(|| {
+                graph_error(line_nb, "invalid weight", Some(token))
+            })?,
+            None => unit_weight(line_nb)?,
+        };
+        if tokens.next().is_some() {
+            let reason = "too many fields";
+            return Err(graph_error(line_nb, reason, Some(content)));
+        }
+        vertices = vertices.max(u + 1).max(v + 1);
+        if direction == EdgeDirection::Undirected && u != v {
+            edges.push((v, u, weight.clone()));
+        }
+        edges.push((u, v, weight));
+    }
+    // the sort is stable, so the first occurrence of an edge is kept
+    edges.sort_by_key(|&(u, v, _)| (u, v));
+    edges.dedup_by_key(|&mut (u, v, _)| (u, v));
+    adjacency_matrix(vertices, edges, line_nb)
+}
+
+/// Read a graph file in the METIS format, returning its adjacency matrix.
+///
+/// METIS graphs are undirected, so their adjacency matrix is symmetric.
+/// Vertex sizes and weights are read and discarded. Edge weights are read
+/// if the header declares them, otherwise all edges have unit weight.
+///
+/// # Errors
+///
+/// - if the header cannot be parsed, or declares an unknown format,
+/// - if a vertex or a weight cannot be parsed, or a weight cannot be
+///   represented by the scalar type,
+/// - if the file does not have one line per vertex,
+/// - if a vertex is connected to itself, or twice to the same vertex,
+/// - if the number of edges does not match the header, or the adjacency
+///   lists are not symmetric,
+/// - if the index type cannot represent the adjacency matrix.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::graph::read_metis;
+/// let graph = read_metis::<i32, usize, _>("data/graph/simple.graph").unwrap();
+/// assert_eq!(graph.shape(), (7, 7));
+/// assert_eq!(graph.nnz(), 22);
+/// assert_eq!(graph.get(3, 6), Some(&5));
+/// ```
+pub fn read_metis<N, I, P>(path: P) -> Result<CsMatI<N, I>, IoError>
+where
+    N: MatrixMarketRead,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let f = File::open(path)?;
+    read_metis_from_bufread(io::BufReader::new(f))
+}
+
+/// Read a graph in the METIS format from an `io::BufRead`.
+///
+/// See `read_metis` for more details.
+pub fn read_metis_from_bufread<N, I, R>(
+    reader: R,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    N: MatrixMarketRead,
+    I: SpIndex,
+    R: io::BufRead,
+{
+    let mut lines = MetisLines {
+        reader,
+        line: String::new(),
+        line_nb: 0,
+    };
+
+    // header
+    loop {
+        if !lines.next_line()? {
+            return Err(graph_error(lines.line_nb, "missing header", None));
+        }
+        if !lines.line.trim().is_empty() {
+            break;
+        }
+    }
+    let header_nb = lines.line_nb;
+    let (vertices, nb_edges, skipped, has_edge_weights) = {
+        let header = lines.line.trim();
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        if fields.len() < 2 || fields.len() > 4 {
+            let reason = "header should have 2 to 4 fields";
+            return Err(graph_error(header_nb, reason, Some(header)));
+        }
+        let parse_count = |token: &str| {
+            token.parse::<usize>().map_err(|_| {
+                graph_error(header_nb, "invalid header field", Some(token))
+            })
+        };
+        let fmt = fields.get(2).copied().unwrap_or("0");
+        if fmt.len() > 3 || fmt.chars().any(|c| c != '0' && c != '1') {
+            return Err(graph_error(header_nb, "unknown format", Some(fmt)));
+        }
+        // the digits of the format flag, from right to left, tell whether
+        // there are edge weights, vertex weights and vertex sizes
+        let flag = |digit| fmt.bytes().rev().nth(digit) == Some(b'1');
+        let ncon = match fields.get(3) {
+            Some(token) if flag(1) => parse_count(token)?,
+            _ => usize::from(flag(1)),
+        };
+        (
+            parse_count(fields[0])?,
+            parse_count(fields[1])?,
+            usize::from(flag(2)) + ncon,
+            flag(0),
+        )
+    };
+
+    // adjacency lists
+    let mut edges = Vec::new();
+    let mut vertex_lines = Vec::with_capacity(vertices);
+    for u in 0..vertices {
+        if !lines.next_line()? {
+            let reason = "missing vertex lines";
+            return Err(graph_error(lines.line_nb, reason, None));
+        }
+        let line_nb = lines.line_nb;
+        vertex_lines.push(line_nb);
+        let mut tokens = lines.line.split_whitespace();
+        for _ in 0..skipped {
+            let token = tokens.next().ok_or_else(|| {
+                graph_error(line_nb, "missing vertex weight", None)
+            })?;
+            token.parse::<i64>().map_err(|_| {
+                graph_error(line_nb, "invalid vertex weight", Some(token))
+            })?;
+        }
+        let start = edges.len();
+        while let Some(token) = tokens.next() {
+            let v = token
+                .parse::<usize>()
+                .ok()
+                .filter(|&v| v >= 1 && v <= vertices)
+                .ok_or_else(|| {
+                    graph_error(line_nb, "invalid vertex", Some(token))
+                })?
+                - 1;
+            if v == u {
+                return Err(graph_error(line_nb, "self loop", Some(token)));
+            }
+            let weight = if has_edge_weights {
+                let token = tokens.next().ok_or_else(|| {
+                    graph_error(line_nb, "missing weight", Some(token))
+                })?;
+                parse_number(token).ok_or_else(|| {
+                    graph_error(line_nb, "invalid weight", Some(token))
+                })?
+            } else {
+                unit_weight(line_nb)?
+            };
+            edges.push((u, v, weight));
+        }
+        let adjacent = &mut edges[start..];
+        adjacent.sort_by_key(|&(_, v, _)| v);
+        if adjacent.windows(2).any(|w| w[0].1 == w[1].1) {
+            let line = lines.line.trim();
+            return Err(graph_error(line_nb, "repeated edge", Some(line)));
+        }
+    }
+    while lines.next_line()? {
+        if !lines.line.trim().is_empty() {
+            let line = lines.line.trim();
+            let reason = "too many vertex lines";
+            return Err(graph_error(lines.line_nb, reason, Some(line)));
+        }
+    }
+    if edges.len() != 2 * nb_edges {
+        let reason = "edge count does not match the header";
+        return Err(graph_error(
+            header_nb,
+            reason,
+            Some(&nb_edges.to_string()),
+        ));
+    }
+
+    let mat: CsMatI<N, I> = adjacency_matrix(vertices, edges, lines.line_nb)?;
+    for (_, (u, v)) in mat.iter() {
+        let (u, v) = (u.index(), v.index());
+        if mat.nnz_index(v, u).is_none() {
+            let reason = "edge missing from the adjacency list";
+            let token = (u + 1).to_string();
+            return Err(graph_error(vertex_lines[v], reason, Some(&token)));
+        }
+    }
+    Ok(mat)
+}
+
+/// Lines of a METIS file, skipping comments
+struct MetisLines<R> {
+    reader: R,
+    line: String,
+    line_nb: usize,
+}
+
+impl<R: io::BufRead> MetisLines<R> {
+    /// Read the next line that is not a comment, returning `false` at the
+    /// end of the input. Blank lines are kept, as they describe vertices
+    /// without neighbours.
+    fn next_line(&mut self) -> Result<bool, IoError> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(false);
+            }
+            self.line_nb += 1;
+            if !self.line.starts_with('%') {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// Write the adjacency matrix of a graph into the edge list format.
+///
+/// For undirected graphs, only the edges `u v` with `u <= v` are written,
+/// the adjacency matrix being expected to be symmetric. Edge weights are
+/// written only if `weighted` is true.
+///
+/// # Panics
+///
+/// - if the matrix is not square.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sprs::io::graph::{write_edge_list, EdgeDirection};
+/// use sprs::CsMat;
+/// let graph = CsMat::new((3, 3), vec![0, 1, 2, 2], vec![1, 2], vec![1, 2]);
+/// write_edge_list("/tmp/graph.edges", &graph, EdgeDirection::Directed, true)
+///     .unwrap();
+/// ```
+pub fn write_edge_list<N, I, IptrS, IS, DS, Iptr, P>(
+    path: P,
+    mat: &CsMatBase<N, I, IptrS, IS, DS, Iptr>,
+    direction: EdgeDirection,
+    weighted: bool,
+) -> Result<(), io::Error>
+where
+    N: fmt::Display,
+    I: SpIndex,
+    Iptr: SpIndex,
+    IptrS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    P: AsRef<Path>,
+{
+    let f = File::create(path)?;
+    let mut writer = io::BufWriter::new(f);
+    write_edge_list_to_bufwrite(&mut writer, mat, direction, weighted)?;
+    writer.flush()
+}
+
+/// Write the adjacency matrix of a graph into the edge list format, using
+/// any `io::Write` as output.
+///
+/// See `write_edge_list` for more details.
+pub fn write_edge_list_to_bufwrite<N, I, IptrS, IS, DS, Iptr, W>(
+    writer: &mut W,
+    mat: &CsMatBase<N, I, IptrS, IS, DS, Iptr>,
+    direction: EdgeDirection,
+    weighted: bool,
+) -> Result<(), io::Error>
+where
+    N: fmt::Display,
+    I: SpIndex,
+    Iptr: SpIndex,
+    IptrS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    W: Write + ?Sized,
+{
+    assert_eq!(mat.rows(), mat.cols(), "adjacency matrices are square");
+    writeln!(writer, "# written by sprs")?;
+    for (w, (u, v)) in mat.iter() {
+        let (u, v) = (u.index(), v.index());
+        if direction == EdgeDirection::Undirected && u > v {
+            continue;
+        }
+        if weighted {
+            writeln!(writer, "{} {} {}", u, v, w)?;
+        } else {
+            writeln!(writer, "{} {}", u, v)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the adjacency matrix of an undirected graph into the METIS format.
+///
+/// The adjacency matrix is expected to be symmetric, which is not checked,
+/// so its outer dimension is used to write the adjacency lists whatever its
+/// storage. Diagonal entries are ignored, as METIS does not allow self
+/// loops. Edge weights are written only if `weighted` is true, note that
+/// METIS only accepts positive integer weights.
+///
+/// # Panics
+///
+/// - if the matrix is not square.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::graph::write_metis_to_bufwrite;
+/// use sprs::CsMat;
+/// let graph = CsMat::new((3, 3), vec![0, 1, 3, 4], vec![1, 0, 2, 1], vec![1; 4]);
+/// let mut buffer = Vec::new();
+/// write_metis_to_bufwrite(&mut buffer, &graph, false).unwrap();
+/// let expected = "% written by sprs\n3 2\n2\n1 3\n2\n";
+/// assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+/// ```
+pub fn write_metis<N, I, IptrS, IS, DS, Iptr, P>(
+    path: P,
+    mat: &CsMatBase<N, I, IptrS, IS, DS, Iptr>,
+    weighted: bool,
+) -> Result<(), io::Error>
+where
+    N: fmt::Display,
+    I: SpIndex,
+    Iptr: SpIndex,
+    IptrS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    P: AsRef<Path>,
+{
+    let f = File::create(path)?;
+    let mut writer = io::BufWriter::new(f);
+    write_metis_to_bufwrite(&mut writer, mat, weighted)?;
+    writer.flush()
+}
+
+/// Write the adjacency matrix of an undirected graph into the METIS format,
+/// using any `io::Write` as output.
+///
+/// See `write_metis` for more details.
+pub fn write_metis_to_bufwrite<N, I, IptrS, IS, DS, Iptr, W>(
+    writer: &mut W,
+    mat: &CsMatBase<N, I, IptrS, IS, DS, Iptr>,
+    weighted: bool,
+) -> Result<(), io::Error>
+where
+    N: fmt::Display,
+    I: SpIndex,
+    Iptr: SpIndex,
+    IptrS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    W: Write + ?Sized,
+{
+    assert_eq!(mat.rows(), mat.cols(), "adjacency matrices are square");
+    let off_diagonal = mat
+        .outer_iterator()
+        .enumerate()
+        .map(|(u, adj)| adj.indices().iter().filter(|v| v.index() != u).count())
+        .sum::<usize>();
+    writeln!(writer, "% written by sprs")?;
+    if weighted {
+        writeln!(writer, "{} {} 1", mat.rows(), off_diagonal / 2)?;
+    } else {
+        writeln!(writer, "{} {}", mat.rows(), off_diagonal / 2)?;
+    }
+    for (u, adj) in mat.outer_iterator().enumerate() {
+        let mut sep = "";
+        for (v, w) in adj.iter().filter(|&(v, _)| v != u) {
+            if weighted {
+                write!(writer, "{}{} {}", sep, v + 1, w)?;
+            } else {
+                write!(writer, "{}{}", sep, v + 1)?;
+            }
+            sep = " ";
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        read_edge_list, read_edge_list_from_bufread, read_metis,
+        read_metis_from_bufread, write_edge_list, write_edge_list_to_bufwrite,
+        write_metis, EdgeDirection,
+    };
+    use crate::io::{IoError, LineError};
+    use crate::sparse::{CsMat, CsMatI};
+    use tempdir::TempDir;
+
+    fn graph_error(line: usize, reason: &'static str, token: &str) -> IoError {
+        IoError::BadGraphFile(LineError {
+            line,
+            token: Some(token.to_string()),
+            reason,
+        })
+    }
+
+    #[test]
+    fn read_edge_lists() {
+        let path = "data/graph/simple.edges";
+        let graph: CsMat<f64> =
+            read_edge_list(path, EdgeDirection::Undirected).unwrap();
+        let expected = CsMat::new(
+            (5, 5),
+            vec![0, 2, 4, 5, 6, 7],
+            vec![1, 4, 0, 2, 1, 3, 0],
+            vec![2.5, -1., 2.5, 1., 1., 4., -1.],
+        );
+        assert_eq!(graph, expected);
+
+        let graph: CsMatI<(), u16> =
+            read_edge_list(path, EdgeDirection::Directed).unwrap();
+        assert_eq!(graph.indptr(), &[0, 2, 3, 4, 5, 5]);
+        assert_eq!(graph.indices(), &[1, 4, 2, 1, 3]);
+
+        // unweighted edges have a unit weight
+        let graph: CsMat<i32> = read_edge_list_from_bufread(
+            "0 2\n2 0\n".as_bytes(),
+            EdgeDirection::Directed,
+        )
+        .unwrap();
+        assert_eq!(
+            graph.to_dense(),
+            ndarray::arr2(&[[0, 0, 1], [0, 0, 0], [1, 0, 0]])
+        );
+    }
+
+    #[test]
+    fn read_bad_edge_lists() {
+        let read = |text: &str| {
+            read_edge_list_from_bufread::<f64, usize, _>(
+                text.as_bytes(),
+                EdgeDirection::Directed,
+            )
+            .unwrap_err()
+        };
+        assert_eq!(read("0 1\n-1 2\n"), graph_error(2, "invalid vertex", "-1"));
+        assert_eq!(read("0\n"), graph_error(1, "missing vertex", "0"));
+        assert_eq!(read("0 1 x\n"), graph_error(1, "invalid weight", "x"));
+        assert_eq!(
+            read("0 1 1 1\n"),
+            graph_error(1, "too many fields", "0 1 1 1")
+        );
+        let res = read_edge_list_from_bufread::<f64, i16, _>(
+            "0 40000\n".as_bytes(),
+            EdgeDirection::Directed,
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            graph_error(1, "graph too large for the index type", "40001")
+        );
+    }
+
+    #[test]
+    fn read_metis_graphs() {
+        let graph: CsMat<i32> = read_metis("data/graph/simple.graph").unwrap();
+        assert_eq!(graph.shape(), (7, 7));
+        assert_eq!(
+            graph.indices(),
+            &[
+                1, 2, 4, 0, 2, 3, 0, 1, 3, 4, 1, 2, 5, 6, 0, 2, 5, 3, 4, 6, 3,
+                5
+            ]
+        );
+        assert_eq!(graph.outer_view(3).unwrap().data(), &[1, 2, 2, 5]);
+        assert_eq!(graph, graph.transpose_view().to_csr());
+
+        // vertex sizes and weights are skipped
+        let text = "% comment\n4 2 110 2\n1 5 2 2\n1 1 1 1 3\n2 0 7 2\n1 3 3\n";
+        let graph: CsMat<()> =
+            read_metis_from_bufread(text.as_bytes()).unwrap();
+        assert_eq!(graph.indptr(), &[0, 1, 3, 4, 4]);
+        assert_eq!(graph.indices(), &[1, 0, 2, 1]);
+
+        // isolated vertices have blank lines
+        let text = "3 1\n\n3\n2\n";
+        let graph: CsMat<f32> =
+            read_metis_from_bufread(text.as_bytes()).unwrap();
+        assert_eq!(graph.indptr(), &[0, 0, 1, 2]);
+        assert_eq!(graph.data(), &[1., 1.]);
+    }
+
+    #[test]
+    fn read_bad_metis_graphs() {
+        let read = |text: &str| {
+            read_metis_from_bufread::<f64, usize, _>(text.as_bytes())
+                .unwrap_err()
+        };
+        assert_eq!(read("3 1 2\n"), graph_error(1, "unknown format", "2"));
+        assert_eq!(
+            read("3\n"),
+            graph_error(1, "header should have 2 to 4 fields", "3")
+        );
+        assert_eq!(read("2 1\n2\n2\n"), graph_error(3, "self loop", "2"));
+        assert_eq!(read("2 1\n2\n3\n"), graph_error(3, "invalid vertex", "3"));
+        assert_eq!(
+            read("2 1\n2 2\n1\n"),
+            graph_error(2, "repeated edge", "2 2")
+        );
+        assert_eq!(
+            read("2 1 1\n2\n1 1\n"),
+            graph_error(2, "missing weight", "2")
+        );
+        assert_eq!(
+            read("2 2\n2\n1\n"),
+            graph_error(1, "edge count does not match the header", "2")
+        );
+        assert_eq!(
+            read("2 1\n2\n1\n1\n"),
+            graph_error(4, "too many vertex lines", "1")
+        );
+        assert_eq!(
+            read("3 1\n2\n\n1\n"),
+            graph_error(3, "edge missing from the adjacency list", "1")
+        );
+        assert_eq!(
+            read("3 1\n2\n1\n"),
+            IoError::BadGraphFile(LineError {
+                line: 3,
+                token: None,
+                reason: "missing vertex lines",
+            })
+        );
+    }
+
+    #[test]
+    fn write_read_graphs() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let graph: CsMat<f64> = read_edge_list(
+            "data/graph/simple.edges",
+            EdgeDirection::Undirected,
+        )
+        .unwrap();
+        let path = tmp_dir.path().join("graph.edges");
+        write_edge_list(&path, &graph, EdgeDirection::Undirected, true)
+            .unwrap();
+        let graph2 = read_edge_list(&path, EdgeDirection::Undirected).unwrap();
+        assert_eq!(graph, graph2);
+
+        let graph: CsMat<i32> = read_metis("data/graph/simple.graph").unwrap();
+        let path = tmp_dir.path().join("simple.graph");
+        write_metis(&path, &graph, true).unwrap();
+        assert_eq!(graph, read_metis(&path).unwrap());
+        write_metis(&path, &graph, false).unwrap();
+        let pattern: CsMat<()> = read_metis(&path).unwrap();
+        assert_eq!(pattern.indices(), graph.indices());
+
+        // edges are written in storage order
+        let mut buffer = Vec::new();
+        let graph = CsMat::new_csc(
+            (3, 3),
+            vec![0, 1, 2, 3],
+            vec![2, 0, 0],
+            vec![1, 2, 3],
+        );
+        write_edge_list_to_bufwrite(
+            &mut buffer,
+            &graph,
+            EdgeDirection::Directed,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "# written by sprs\n2 0\n0 1\n0 2\n"
+        );
+    }
+}
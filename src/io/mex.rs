@@ -0,0 +1,406 @@
+//! Reading and writing of labelled matrices in the MEX format
+//!
+//! The MEX format, popularized by 10x Genomics, stores a sparse matrix as
+//! a directory containing three files:
+//!
+//! - `matrix.mtx`, the matrix in the Matrix Market format, with features as
+//!   rows and barcodes as columns,
+//! - `barcodes.tsv`, containing one barcode (column label) per line,
+//! - `features.tsv`, containing one tab separated feature description
+//!   (row label) per line, made of an identifier, a name and optionally
+//!   a feature type. Older bundles name this file `genes.tsv` and omit the
+//!   feature type.
+//!
+//! Each of these files can be gzip-compressed, in which case its name
+//! carries an additional `.gz` extension. Reading and writing compressed
+//! files requires the `gzip` feature to be activated.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "gzip")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature = "gzip")]
+use flate2::write::GzEncoder;
+use num_traits::Num;
+
+use super::{read_matrix_market_from_bufread, write_matrix_market_to_bufwrite};
+use super::{IoError, MatrixMarketDisplay, MatrixMarketRead};
+use crate::indexing::SpIndex;
+use crate::num_kinds::PrimitiveKind;
+use crate::sparse::{CompressedStorage, CsMatI};
+
+/// Description of a feature, ie the label of a row of a MEX matrix.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Feature {
+    /// Unique identifier of the feature, eg an Ensembl gene id.
+    pub id: String,
+    /// Human readable name of the feature, eg a gene symbol.
+    pub name: String,
+    /// Type of the feature, eg "Gene Expression". Absent from legacy
+    /// `genes.tsv` files.
+    pub feature_type: Option<String>,
+}
+
+/// A sparse matrix labelled by features (rows) and barcodes (columns).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MexMatrix<N, I: SpIndex> {
+    /// The matrix, of shape `(features.len(), barcodes.len())`.
+    pub matrix: CsMatI<N, I>,
+    /// The row labels.
+    pub features: Vec<Feature>,
+    /// The column labels.
+    pub barcodes: Vec<String>,
+}
+
+/// Compression of the files written by `write_mex`.
+///
+/// Writing gzip-compressed files requires the `gzip` feature, `write_mex`
+/// returning an error otherwise.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Compression {
+    Uncompressed,
+    Gzip,
+}
+
+const MATRIX_FILES: &[&str] = &["matrix.mtx.gz", "matrix.mtx"];
+const BARCODES_FILES: &[&str] = &["barcodes.tsv.gz", "barcodes.tsv"];
+const FEATURES_FILES: &[&str] = &[
+    "features.tsv.gz",
+    "features.tsv",
+    "genes.tsv.gz",
+    "genes.tsv",
+];
+
+/// Find the first of the candidate file names present in `dir`.
+fn find_file(dir: &Path, candidates: &[&str]) -> Result<PathBuf, io::Error> {
+    candidates
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "no {} file in {}",
+                    candidates[0].trim_end_matches(".gz"),
+                    dir.display()
+                ),
+            )
+        })
+}
+
+/// Open a file for reading, transparently decompressing it if its
+/// extension is `.gz`.
+fn open_reader(path: &Path) -> Result<Box<dyn BufRead>, io::Error> {
+    let f = File::open(path)?;
+    if path.extension() == Some(OsStr::new("gz")) {
+        gzip_reader(f)
+    } else {
+        Ok(Box::new(io::BufReader::new(f)))
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_reader(f: File) -> Result<Box<dyn BufRead>, io::Error> {
+    Ok(Box::new(io::BufReader::new(MultiGzDecoder::new(f))))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_reader(_f: File) -> Result<Box<dyn BufRead>, io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "reading compressed files requires the gzip feature",
+    ))
+}
+
+/// Create the file `name` in `dir`, and write its content using `write`.
+fn write_file<F>(
+    dir: &Path,
+    name: &str,
+    compression: Compression,
+    write: F,
+) -> Result<(), io::Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+{
+    match compression {
+        Compression::Uncompressed => {
+            let f = File::create(dir.join(name))?;
+            let mut writer = io::BufWriter::new(f);
+            write(&mut writer)?;
+            writer.flush()
+        }
+        Compression::Gzip => {
+            write_gzip(&dir.join(format!("{}.gz", name)), write)
+        }
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn write_gzip<F>(path: &Path, write: F) -> Result<(), io::Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+{
+    let f = File::create(path)?;
+    let encoder = GzEncoder::new(f, flate2::Compression::default());
+    let mut writer = io::BufWriter::new(encoder);
+    write(&mut writer)?;
+    writer.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "gzip"))]
+fn write_gzip<F>(_path: &Path, _write: F) -> Result<(), io::Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+{
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "writing compressed files requires the gzip feature",
+    ))
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, io::Error> {
+    let mut lines = Vec::new();
+    for line in open_reader(path)?.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            lines.push(line);
+        }
+    }
+    Ok(lines)
+}
+
+fn parse_feature(line: &str) -> Result<Feature, IoError> {
+    let mut fields = line.trim_end_matches('\r').split('\t');
+    let id = fields.next().ok_or(IoError::MismatchedLabels)?.to_string();
+    let name = fields.next().unwrap_or(&id).to_string();
+    let feature_type = fields.next().map(str::to_string);
+    Ok(Feature {
+        id,
+        name,
+        feature_type,
+    })
+}
+
+/// Read a MEX bundle from the directory `dir`, returning the matrix in the
+/// requested storage along with its row and column labels.
+///
+/// Compressed and uncompressed files are both supported, as well as legacy
+/// bundles using a `genes.tsv` file.
+///
+/// # Errors
+///
+/// - if one of the files is missing or cannot be read,
+/// - if the matrix file is not a valid Matrix Market file,
+/// - if the number of labels does not match the shape of the matrix.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::mex::read_mex;
+/// use sprs::CSC;
+/// let mex = read_mex::<u32, u32, _>("data/mex/simple", CSC).unwrap();
+/// assert_eq!(mex.matrix.shape(), (4, 3));
+/// assert_eq!(mex.barcodes[0], "AAACCTGA-1");
+/// assert_eq!(mex.features[1].name, "GENE2");
+/// ```
+pub fn read_mex<N, I, P>(
+    dir: P,
+    storage: CompressedStorage,
+) -> Result<MexMatrix<N, I>, IoError>
+where
+    N: MatrixMarketRead + Num,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let mut reader = open_reader(&find_file(dir, MATRIX_FILES)?)?;
+    let triplets = read_matrix_market_from_bufread::<N, I, _>(&mut reader)?;
+    let features = read_lines(&find_file(dir, FEATURES_FILES)?)?
+        .iter()
+        .map(|line| parse_feature(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    let barcodes = read_lines(&find_file(dir, BARCODES_FILES)?)?;
+    if features.len() != triplets.rows() || barcodes.len() != triplets.cols() {
+        return Err(IoError::MismatchedLabels);
+    }
+    let matrix = match storage {
+        CompressedStorage::CSR => triplets.to_csr(),
+        CompressedStorage::CSC => triplets.to_csc(),
+    };
+    Ok(MexMatrix {
+        matrix,
+        features,
+        barcodes,
+    })
+}
+
+/// Write a MEX bundle into the directory `dir`, which must exist.
+///
+/// The features are written to `features.tsv`, with a feature type column
+/// if all features have a type. If `compression` is `Compression::Gzip`,
+/// which requires the `gzip` feature, all files are compressed and their
+/// names are suffixed with `.gz`.
+///
+/// # Errors
+///
+/// - if the number of labels does not match the shape of the matrix,
+/// - if `compression` is `Compression::Gzip` and the `gzip` feature is not
+///   activated,
+/// - if any of the files cannot be written.
+pub fn write_mex<N, I, P>(
+    dir: P,
+    mex: &MexMatrix<N, I>,
+    compression: Compression,
+) -> Result<(), IoError>
+where
+    N: PrimitiveKind + Copy + MatrixMarketDisplay,
+    I: SpIndex + fmt::Display,
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let (rows, cols) = mex.matrix.shape();
+    if mex.features.len() != rows || mex.barcodes.len() != cols {
+        return Err(IoError::MismatchedLabels);
+    }
+    write_file(dir, "matrix.mtx", compression, |writer| {
+        write_matrix_market_to_bufwrite(writer, &mex.matrix)
+    })?;
+    let with_types = mex.features.iter().all(|f| f.feature_type.is_some());
+    write_file(dir, "features.tsv", compression, |writer| {
+        for feature in &mex.features {
+            write!(writer, "{}\t{}", feature.id, feature.name)?;
+            match feature.feature_type {
+                Some(ref feature_type) if with_types => {
+                    writeln!(writer, "\t{}", feature_type)?
+                }
+                _ => writeln!(writer)?,
+            }
+        }
+        Ok(())
+    })?;
+    write_file(dir, "barcodes.tsv", compression, |writer| {
+        for barcode in &mex.barcodes {
+            writeln!(writer, "{}", barcode)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_mex, write_mex, Compression, Feature};
+    use crate::io::IoError;
+    use crate::sparse::CsMat;
+    use crate::{CSC, CSR};
+    use tempdir::TempDir;
+
+    #[test]
+    fn read_simple_mex() {
+        let mex = read_mex::<i32, usize, _>("data/mex/simple", CSR).unwrap();
+        let expected = CsMat::new(
+            (4, 3),
+            vec![0, 2, 3, 3, 5],
+            vec![0, 2, 1, 0, 2],
+            vec![1, 3, 7, 2, 5],
+        );
+        assert_eq!(mex.matrix, expected);
+        assert_eq!(
+            mex.barcodes,
+            vec!["AAACCTGA-1", "AAACGGGC-1", "AAAGTAGT-1"]
+        );
+        assert_eq!(mex.features.len(), 4);
+        assert_eq!(
+            mex.features[0],
+            Feature {
+                id: "ENSG00000001".to_string(),
+                name: "GENE1".to_string(),
+                feature_type: Some("Gene Expression".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn write_read_mex_compressed() {
+        let mex = read_mex::<i32, usize, _>("data/mex/simple", CSC).unwrap();
+        assert!(mex.matrix.is_csc());
+        for &compression in &[Compression::Uncompressed, Compression::Gzip] {
+            let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+            write_mex(tmp_dir.path(), &mex, compression).unwrap();
+            let gz = compression == Compression::Gzip;
+            assert_eq!(tmp_dir.path().join("matrix.mtx.gz").is_file(), gz);
+            let mex2 = read_mex::<i32, usize, _>(tmp_dir.path(), CSC).unwrap();
+            assert_eq!(mex, mex2);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "gzip"))]
+    fn compressed_without_gzip() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        for name in &["matrix.mtx", "features.tsv", "barcodes.tsv"] {
+            let src = std::path::Path::new("data/mex/simple").join(name);
+            let dest = tmp_dir.path().join(format!("{}.gz", name));
+            std::fs::copy(src, dest).unwrap();
+        }
+        let res = read_mex::<f64, usize, _>(tmp_dir.path(), CSR);
+        match res {
+            Err(IoError::Io(err)) => {
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput)
+            }
+            _ => panic!("expected an unsupported compression error"),
+        }
+
+        let mex = read_mex::<i32, usize, _>("data/mex/simple", CSC).unwrap();
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let res = write_mex(tmp_dir.path(), &mex, Compression::Gzip);
+        match res {
+            Err(IoError::Io(err)) => {
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput)
+            }
+            _ => panic!("expected an unsupported compression error"),
+        }
+        assert!(!tmp_dir.path().join("matrix.mtx.gz").exists());
+    }
+
+    #[test]
+    fn write_legacy_features() {
+        let mut mex = read_mex::<f64, u32, _>("data/mex/simple", CSR).unwrap();
+        for feature in &mut mex.features {
+            feature.feature_type = None;
+        }
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        write_mex(tmp_dir.path(), &mex, Compression::Uncompressed).unwrap();
+        let mex2 = read_mex::<f64, u32, _>(tmp_dir.path(), CSR).unwrap();
+        assert_eq!(mex, mex2);
+    }
+
+    #[test]
+    fn mismatched_labels() {
+        let mut mex =
+            read_mex::<f64, usize, _>("data/mex/simple", CSR).unwrap();
+        mex.barcodes.pop();
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let res = write_mex(tmp_dir.path(), &mex, Compression::Uncompressed);
+        assert_eq!(res.unwrap_err(), IoError::MismatchedLabels);
+    }
+
+    #[test]
+    fn missing_files() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let res = read_mex::<f64, usize, _>(tmp_dir.path(), CSR);
+        match res {
+            Err(IoError::Io(err)) => {
+                assert_eq!(err.kind(), std::io::ErrorKind::NotFound)
+            }
+            _ => panic!("expected a missing file error"),
+        }
+    }
+}
@@ -0,0 +1,763 @@
+//! Reading and writing of sparse matrices in the SciPy `.npz` format
+//!
+//! The `scipy.sparse.save_npz` function stores a sparse matrix as a zip
+//! archive of NumPy `.npy` arrays:
+//!
+//! - `format`, a byte string giving the storage: `csr`, `csc` or `coo`,
+//! - `shape`, the number of rows and columns of the matrix,
+//! - `data`, the values of the non-zero entries,
+//! - `indptr` and `indices` for compressed matrices, or `row` and `col` for
+//!   triplet matrices.
+//!
+//! The arrays are written with the NumPy type matching the Rust scalar type,
+//! so that a matrix read by `scipy.sparse.load_npz` has the same value and
+//! index types as the written one.
+//!
+//! This module requires the `npz` feature to be activated.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+use num_complex::Complex;
+use num_traits::Num;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::IoError;
+use crate::indexing::SpIndex;
+use crate::sparse::{CsMatBase, CsMatI, TriMatBase, TriMatI};
+
+/// Description of a failure to read an `.npz` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NpzError {
+    /// The name of the array which could not be read
+    pub array: String,
+    /// The reason of the failure
+    pub reason: &'static str,
+}
+
+impl fmt::Display for NpzError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "array `{}`: {}", self.array, self.reason)
+    }
+}
+
+fn npz_error(array: &str, reason: &'static str) -> IoError {
+    IoError::BadNpzFile(NpzError {
+        array: array.to_string(),
+        reason,
+    })
+}
+
+/// Scalar types that can be stored in NumPy arrays.
+///
+/// This is implemented for the primitive numeric types and for
+/// `num_complex::Complex<f32>` and `Complex<f64>`. Reading converts the
+/// stored values when they can be represented in the target type, eg
+/// integer arrays can be read into floating point matrices, but floating
+/// point arrays cannot be read into integer matrices.
+pub trait NpyElement: Sized {
+    /// The NumPy type description of this scalar type, eg `<f8`.
+    fn npy_descr() -> &'static str;
+
+    /// Append the little endian representation of this value to `bytes`.
+    fn npy_write(&self, bytes: &mut Vec<u8>);
+
+    /// Convert a value of an integer or boolean array.
+    fn from_npy_integer(val: i128) -> Option<Self>;
+
+    /// Convert a value of a floating point array.
+    fn from_npy_float(val: f64) -> Option<Self>;
+
+    /// Convert a value of a complex array.
+    fn from_npy_complex(re: f64, im: f64) -> Option<Self>;
+}
+
+macro_rules! npy_int_impl {
+    ($prim: ty, $stored: ty, $descr: expr) => {
+        impl NpyElement for $prim {
+            fn npy_descr() -> &'static str {
+                $descr
+            }
+
+            fn npy_write(&self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&(*self as $stored).to_le_bytes());
+            }
+
+            fn from_npy_integer(val: i128) -> Option<Self> {
+                <$prim>::try_from(val).ok()
+            }
+
+            fn from_npy_float(_val: f64) -> Option<Self> {
+                None
+            }
+
+            fn from_npy_complex(_re: f64, _im: f64) -> Option<Self> {
+                None
+            }
+        }
+    };
+}
+
+npy_int_impl!(i8, i8, "|i1");
+npy_int_impl!(u8, u8, "|u1");
+npy_int_impl!(i16, i16, "<i2");
+npy_int_impl!(u16, u16, "<u2");
+npy_int_impl!(i32, i32, "<i4");
+npy_int_impl!(u32, u32, "<u4");
+npy_int_impl!(i64, i64, "<i8");
+npy_int_impl!(u64, u64, "<u8");
+npy_int_impl!(isize, i64, "<i8");
+npy_int_impl!(usize, u64, "<u8");
+
+macro_rules! npy_float_impl {
+    ($prim: ty, $descr: expr) => {
+        impl NpyElement for $prim {
+            fn npy_descr() -> &'static str {
+                $descr
+            }
+
+            fn npy_write(&self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn from_npy_integer(val: i128) -> Option<Self> {
+                Some(val as $prim)
+            }
+
+            fn from_npy_float(val: f64) -> Option<Self> {
+                Some(val as $prim)
+            }
+
+            fn from_npy_complex(_re: f64, _im: f64) -> Option<Self> {
+                None
+            }
+        }
+    };
+}
+
+npy_float_impl!(f32, "<f4");
+npy_float_impl!(f64, "<f8");
+
+macro_rules! npy_complex_impl {
+    ($prim: ty, $descr: expr) => {
+        impl NpyElement for Complex<$prim> {
+            fn npy_descr() -> &'static str {
+                $descr
+            }
+
+            fn npy_write(&self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.re.to_le_bytes());
+                bytes.extend_from_slice(&self.im.to_le_bytes());
+            }
+
+            fn from_npy_integer(val: i128) -> Option<Self> {
+                Some(Complex::new(val as $prim, 0.))
+            }
+
+            fn from_npy_float(val: f64) -> Option<Self> {
+                Some(Complex::new(val as $prim, 0.))
+            }
+
+            fn from_npy_complex(re: f64, im: f64) -> Option<Self> {
+                Some(Complex::new(re as $prim, im as $prim))
+            }
+        }
+    };
+}
+
+npy_complex_impl!(f32, "<c8");
+npy_complex_impl!(f64, "<c16");
+
+/// The type of the elements of a NumPy array, as described by strings
+/// such as `<f8` or `|S3`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Dtype {
+    big_endian: bool,
+    kind: char,
+    size: usize,
+}
+
+impl Dtype {
+    fn parse(descr: &str) -> Option<Self> {
+        let mut chars = descr.chars();
+        let big_endian = match chars.next()? {
+            '>' | '!' => true,
+            '<' | '|' => false,
+            '=' => cfg!(target_endian = "big"),
+            _ => return None,
+        };
+        let kind = chars.next()?;
+        let size = chars.as_str().parse().ok()?;
+        let valid = match kind {
+            'b' => size == 1,
+            'i' | 'u' => [1, 2, 4, 8].contains(&size),
+            'f' => size == 4 || size == 8,
+            'c' => size == 8 || size == 16,
+            'S' => true,
+            'U' => true,
+            _ => false,
+        };
+        if !valid {
+            return None;
+        }
+        Some(Dtype {
+            big_endian,
+            kind,
+            size: if kind == 'U' { 4 * size } else { size },
+        })
+    }
+
+    /// Read an unsigned integer of `bytes.len()` bytes at most 8.
+    fn read_bits(&self, bytes: &[u8]) -> u64 {
+        let mut bits = 0;
+        for pos in 0..bytes.len() {
+            let byte = match self.big_endian {
+                true => bytes[pos],
+                false => bytes[bytes.len() - 1 - pos],
+            };
+            bits = (bits << 8) | u64::from(byte);
+        }
+        bits
+    }
+
+    fn read_float(&self, bytes: &[u8]) -> f64 {
+        let bits = self.read_bits(bytes);
+        match bytes.len() {
+            4 => f64::from(f32::from_bits(bits as u32)),
+            _ => f64::from_bits(bits),
+        }
+    }
+}
+
+/// A NumPy array read from an `.npy` file, of at most one dimension.
+struct NpyArray {
+    dtype: Dtype,
+    len: usize,
+    data: Vec<u8>,
+}
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Find the value of `key` in the dictionary header of an `.npy` file.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{}':", key))? + key.len() + 3;
+    let value = header[start..].trim_start();
+    let end = match value.chars().next()? {
+        '\'' => value[1..].find('\'')? + 2,
+        '(' => value.find(')')? + 1,
+        _ => value.find(&[',', '}'][..])?,
+    };
+    Some(value[..end].trim())
+}
+
+impl NpyArray {
+    fn parse(name: &str, mut data: Vec<u8>) -> Result<Self, IoError> {
+        if data.len() < 10 || &data[..6] != NPY_MAGIC {
+            return Err(npz_error(name, "not an npy file"));
+        }
+        let (header_start, header_len) = match data[6] {
+            1 => (10, usize::from(u16::from_le_bytes([data[8], data[9]]))),
+            2 | 3 if data.len() >= 12 => {
+                let len = [data[8], data[9], data[10], data[11]];
+                (12, u32::from_le_bytes(len) as usize)
+            }
+            _ => return Err(npz_error(name, "unsupported npy version")),
+        };
+        let header_end = header_start + header_len;
+        let header = data
+            .get(header_start..header_end)
+            .and_then(|header| std::str::from_utf8(header).ok())
+            .ok_or_else(|| npz_error(name, "invalid npy header"))?;
+        let dtype = header_value(header, "descr")
+            .map(|descr| descr.trim_matches('\''))
+            .and_then(Dtype::parse)
+            .ok_or_else(|| npz_error(name, "unsupported array type"))?;
+        let shape = header_value(header, "shape")
+            .ok_or_else(|| npz_error(name, "invalid npy header"))?;
+        let dims = shape
+            .trim_matches(|c| c == '(' || c == ')')
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| dim.trim_end_matches('L').parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| npz_error(name, "invalid npy header"))?;
+        if dims.len() > 1 {
+            return Err(npz_error(name, "array has more than one dimension"));
+        }
+        let len = dims.first().cloned().unwrap_or(1);
+        let size = len
+            .checked_mul(dtype.size)
+            .ok_or_else(|| npz_error(name, "array too large"))?;
+        if data.len() - header_end.min(data.len()) < size {
+            return Err(npz_error(name, "array data is truncated"));
+        }
+        data.drain(..header_end);
+        data.truncate(size);
+        Ok(NpyArray { dtype, len, data })
+    }
+
+    /// Convert the numeric values of this array, failing if one of them
+    /// cannot be represented in the target type.
+    fn values<T: NpyElement>(&self, name: &str) -> Result<Vec<T>, IoError> {
+        let dtype = self.dtype;
+        let mut values = Vec::with_capacity(self.len);
+        for bytes in self.data.chunks(dtype.size) {
+            let val = match dtype.kind {
+                'b' | 'u' => T::from_npy_integer(dtype.read_bits(bytes).into()),
+                'i' => {
+                    // sign extend the value
+                    let shift = 64 - 8 * dtype.size;
+                    let bits = dtype.read_bits(bytes) << shift;
+                    T::from_npy_integer(((bits as i64) >> shift).into())
+                }
+                'f' => T::from_npy_float(dtype.read_float(bytes)),
+                'c' => {
+                    let (re, im) = bytes.split_at(dtype.size / 2);
+                    T::from_npy_complex(
+                        dtype.read_float(re),
+                        dtype.read_float(im),
+                    )
+                }
+                _ => return Err(npz_error(name, "array is not numeric")),
+            };
+            values.push(val.ok_or_else(|| {
+                npz_error(
+                    name,
+                    "value cannot be represented by the scalar type",
+                )
+            })?);
+        }
+        Ok(values)
+    }
+
+    /// Read the content of a string array.
+    fn string(&self, name: &str) -> Result<String, IoError> {
+        let dtype = self.dtype;
+        let chars: Option<String> = match dtype.kind {
+            'S' => self
+                .data
+                .iter()
+                .map(|&byte| Some(char::from(byte)))
+                .collect(),
+            'U' => self
+                .data
+                .chunks(4)
+                .map(|bytes| std::char::from_u32(dtype.read_bits(bytes) as u32))
+                .collect(),
+            _ => None,
+        };
+        chars
+            .map(|string| string.trim_end_matches('\0').to_string())
+            .ok_or_else(|| npz_error(name, "array is not a string"))
+    }
+}
+
+/// Serialize a one dimensional array, or a scalar if `len` is `None`, into
+/// the `.npy` format.
+fn npy_bytes(descr: &str, len: Option<usize>, data: &[u8]) -> Vec<u8> {
+    let shape = match len {
+        Some(len) => format!("({},)", len),
+        None => "()".to_string(),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // the data is aligned on 64 bytes, and the header ends with a newline
+    let padding = 63 - (NPY_MAGIC.len() + 4 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    let mut bytes = Vec::with_capacity(10 + header.len() + data.len());
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+fn elements_bytes<T: NpyElement>(elements: &[T]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for element in elements {
+        element.npy_write(&mut bytes);
+    }
+    bytes
+}
+
+/// The arrays of an `.npz` archive, read on demand.
+struct NpzArchive<R> {
+    archive: ZipArchive<R>,
+}
+
+impl<R: Read + Seek> NpzArchive<R> {
+    fn array(&mut self, name: &str) -> Result<NpyArray, IoError> {
+        let mut data = Vec::new();
+        match self.archive.by_name(&format!("{}.npy", name)) {
+            Ok(mut file) => file.read_to_end(&mut data)?,
+            Err(zip::result::ZipError::FileNotFound) => {
+                return Err(npz_error(name, "missing array"))
+            }
+            Err(err) => return Err(IoError::Io(err.into())),
+        };
+        NpyArray::parse(name, data)
+    }
+
+    fn values<T: NpyElement>(&mut self, name: &str) -> Result<Vec<T>, IoError> {
+        self.array(name)?.values(name)
+    }
+
+    fn indices<I: SpIndex + NpyElement>(
+        &mut self,
+        name: &str,
+        dim: usize,
+    ) -> Result<Vec<I>, IoError> {
+        let indices = self.values::<I>(name)?;
+        if indices
+            .iter()
+            .any(|ind| ind.try_index().filter(|&i| i < dim).is_none())
+        {
+            return Err(npz_error(name, "index out of bounds of the shape"));
+        }
+        Ok(indices)
+    }
+}
+
+/// The content of an `.npz` file, as stored.
+enum NpzMatrix<N, I: SpIndex> {
+    Compressed(CsMatI<N, I>),
+    Triplets(TriMatI<N, I>),
+}
+
+fn read_npz_impl<N, I, P>(path: P) -> Result<NpzMatrix<N, I>, IoError>
+where
+    N: NpyElement + Copy,
+    I: SpIndex + NpyElement,
+    P: AsRef<Path>,
+{
+    let f = io::BufReader::new(File::open(path)?);
+    let archive = ZipArchive::new(f).map_err(io::Error::from)?;
+    let mut npz = NpzArchive { archive };
+    let format = npz.array("format")?.string("format")?;
+    let shape = npz.values::<usize>("shape")?;
+    let shape = match shape[..] {
+        [rows, cols] => (rows, cols),
+        _ => return Err(npz_error("shape", "shape is not two dimensional")),
+    };
+    let data = npz.values::<N>("data")?;
+    match &format[..] {
+        "csr" | "csc" => {
+            let (outer, inner) = match &format[..] {
+                "csr" => shape,
+                _ => (shape.1, shape.0),
+            };
+            let indptr = npz.values::<I>("indptr")?;
+            let indices = npz.indices::<I>("indices", inner)?;
+            if indptr.len() != outer + 1 {
+                let reason = "length does not match the shape";
+                return Err(npz_error("indptr", reason));
+            }
+            let mat = match &format[..] {
+                "csr" => CsMatI::try_new(shape, indptr, indices, data),
+                _ => CsMatI::try_new_csc(shape, indptr, indices, data),
+            };
+            mat.map(NpzMatrix::Compressed).map_err(|_| {
+                npz_error("indices", "invalid compressed structure")
+            })
+        }
+        "coo" => {
+            let row_inds = npz.indices::<I>("row", shape.0)?;
+            let col_inds = npz.indices::<I>("col", shape.1)?;
+            if row_inds.len() != data.len() || col_inds.len() != data.len() {
+                let reason = "length does not match the data";
+                return Err(npz_error("row", reason));
+            }
+            let triplets =
+                TriMatI::from_triplets(shape, row_inds, col_inds, data);
+            Ok(NpzMatrix::Triplets(triplets))
+        }
+        _ => Err(npz_error("format", "unsupported sparse format")),
+    }
+}
+
+/// Read a sparse matrix saved by `scipy.sparse.save_npz`.
+///
+/// CSR and CSC matrices are returned in the same storage, with their
+/// indices sorted. COO matrices are converted to CSR storage, summing
+/// duplicate entries.
+///
+/// # Errors
+///
+/// - if the file is not a valid zip archive,
+/// - if an array is missing, or cannot be converted to the index or scalar
+///   types, see `NpyElement`,
+/// - if the matrix is not in the csr, csc or coo format,
+/// - if the arrays do not describe a valid matrix, eg if a compressed
+///   matrix has duplicate entries.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::npz::read_npz;
+/// let mat = read_npz::<f64, i32, _>("data/npz/simple_csr.npz").unwrap();
+/// assert!(mat.is_csr());
+/// assert_eq!(mat.shape(), (3, 4));
+/// assert_eq!(mat.get(2, 3), Some(&-1.5));
+/// ```
+pub fn read_npz<N, I, P>(path: P) -> Result<CsMatI<N, I>, IoError>
+where
+    N: NpyElement + Num + Copy,
+    I: SpIndex + NpyElement,
+    P: AsRef<Path>,
+{
+    match read_npz_impl(path)? {
+        NpzMatrix::Compressed(mat) => Ok(mat),
+        NpzMatrix::Triplets(triplets) => Ok(triplets.to_csr()),
+    }
+}
+
+/// Read a sparse matrix saved by `scipy.sparse.save_npz` as a triplet
+/// matrix.
+///
+/// The entries of COO matrices are read as stored, including duplicate
+/// entries. The entries of CSR and CSC matrices are returned in storage
+/// order.
+///
+/// See `read_npz` for the possible errors.
+pub fn read_npz_triplets<N, I, P>(path: P) -> Result<TriMatI<N, I>, IoError>
+where
+    N: NpyElement + Copy,
+    I: SpIndex + NpyElement,
+    P: AsRef<Path>,
+{
+    match read_npz_impl::<N, I, P>(path)? {
+        NpzMatrix::Compressed(mat) => {
+            let mut triplets = TriMatI::with_capacity(mat.shape(), mat.nnz());
+            for (&val, (row, col)) in mat.iter() {
+                triplets.add_triplet(row.index(), col.index(), val);
+            }
+            Ok(triplets)
+        }
+        NpzMatrix::Triplets(triplets) => Ok(triplets),
+    }
+}
+
+/// Write the arrays of a sparse matrix into a compressed `.npz` archive.
+fn write_npz_arrays<W: Write + Seek>(
+    writer: W,
+    format: &str,
+    shape: (usize, usize),
+    arrays: &[(&str, &str, Vec<u8>)],
+) -> Result<(), io::Error> {
+    let mut zip = ZipWriter::new(writer);
+    let options =
+        FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("format.npy", options)?;
+    let descr = format!("|S{}", format.len());
+    zip.write_all(&npy_bytes(&descr, None, format.as_bytes()))?;
+    zip.start_file("shape.npy", options)?;
+    let shape = elements_bytes(&[shape.0 as i64, shape.1 as i64]);
+    zip.write_all(&npy_bytes(i64::npy_descr(), Some(2), &shape))?;
+    for (name, descr, bytes) in arrays {
+        zip.start_file(format!("{}.npy", name), options)?;
+        let len = bytes.len() / Dtype::parse(descr).map_or(1, |d| d.size);
+        zip.write_all(&npy_bytes(descr, Some(len), bytes))?;
+    }
+    zip.finish()?.flush()
+}
+
+/// Write a compressed matrix into the `.npz` format of
+/// `scipy.sparse.save_npz`, in the csr or csc format according to its
+/// storage.
+///
+/// The arrays are stored with the NumPy types matching the scalar and index
+/// types of the matrix.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sprs::io::npz::write_npz;
+/// use sprs::CsMat;
+/// let eye: CsMat<f64> = CsMat::eye(5);
+/// write_npz("/tmp/eye.npz", &eye).unwrap();
+/// ```
+pub fn write_npz<N, I, IptrS, IS, DS, Iptr, P>(
+    path: P,
+    mat: &CsMatBase<N, I, IptrS, IS, DS, Iptr>,
+) -> Result<(), io::Error>
+where
+    N: NpyElement,
+    I: SpIndex + NpyElement,
+    Iptr: SpIndex + NpyElement,
+    IptrS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    P: AsRef<Path>,
+{
+    let format = if mat.is_csr() { "csr" } else { "csc" };
+    // the first index pointer may be non zero for views of a larger matrix
+    let offset = mat.indptr()[0];
+    let range = offset.index()..mat.indptr()[mat.outer_dims()].index();
+    let indptr: Vec<Iptr> =
+        mat.indptr().iter().map(|&ptr| ptr - offset).collect();
+    let arrays = [
+        (
+            "indices",
+            I::npy_descr(),
+            elements_bytes(&mat.indices()[range.clone()]),
+        ),
+        ("indptr", Iptr::npy_descr(), elements_bytes(&indptr)),
+        ("data", N::npy_descr(), elements_bytes(&mat.data()[range])),
+    ];
+    let f = io::BufWriter::new(File::create(path)?);
+    write_npz_arrays(f, format, mat.shape(), &arrays)
+}
+
+/// Write a triplet matrix into the `.npz` format of `scipy.sparse.save_npz`,
+/// in the coo format.
+///
+/// The arrays are stored with the NumPy types matching the scalar and index
+/// types of the matrix.
+pub fn write_npz_triplets<N, I, IS, DS, P>(
+    path: P,
+    mat: &TriMatBase<IS, DS>,
+) -> Result<(), io::Error>
+where
+    N: NpyElement,
+    I: SpIndex + NpyElement,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+    P: AsRef<Path>,
+{
+    let arrays = [
+        ("row", I::npy_descr(), elements_bytes(mat.row_inds())),
+        ("col", I::npy_descr(), elements_bytes(mat.col_inds())),
+        ("data", N::npy_descr(), elements_bytes(mat.data())),
+    ];
+    let f = io::BufWriter::new(File::create(path)?);
+    write_npz_arrays(f, "coo", (mat.rows(), mat.cols()), &arrays)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        npy_bytes, read_npz, read_npz_triplets, write_npz, write_npz_triplets,
+        NpyArray, NpzError,
+    };
+    use crate::io::IoError;
+    use crate::sparse::{CsMat, CsMatI, TriMatI};
+    use num_complex::Complex32;
+    use tempdir::TempDir;
+
+    fn simple() -> CsMat<f64> {
+        CsMat::new(
+            (3, 4),
+            vec![0, 2, 3, 5],
+            vec![0, 2, 1, 0, 3],
+            vec![1., 2., 3., 4., -1.5],
+        )
+    }
+
+    #[test]
+    fn read_scipy_files() {
+        let expected = simple();
+        let mat = read_npz::<f64, usize, _>("data/npz/simple_csr.npz").unwrap();
+        assert_eq!(mat, expected);
+        // the values are converted to the requested types
+        let mat = read_npz::<f32, u16, _>("data/npz/simple_csr.npz").unwrap();
+        assert_eq!(mat.data(), &[1., 2., 3., 4., -1.5]);
+
+        // big endian values, unsorted indices and unicode format
+        let mat = read_npz::<f64, usize, _>("data/npz/simple_csc.npz").unwrap();
+        assert!(mat.is_csc());
+        assert_eq!(mat.to_csr(), expected);
+
+        // coo matrices can have duplicate entries
+        let path = "data/npz/simple_coo.npz";
+        let triplets = read_npz_triplets::<f64, i32, _>(path).unwrap();
+        assert_eq!(triplets.nnz(), 6);
+        let mat = read_npz::<f64, usize, _>(path).unwrap();
+        assert_eq!(mat, expected);
+    }
+
+    #[test]
+    fn read_errors() {
+        let error = |array: &str, reason| {
+            IoError::BadNpzFile(NpzError {
+                array: array.to_string(),
+                reason,
+            })
+        };
+        let path = "data/npz/simple_csr.npz";
+        assert_eq!(
+            read_npz::<i32, usize, _>(path).unwrap_err(),
+            error("data", "value cannot be represented by the scalar type")
+        );
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let path = tmp_dir.path().join("wide.npz");
+        let mat = CsMat::new((1, 40000), vec![0, 1], vec![39999], vec![1.]);
+        write_npz(&path, &mat).unwrap();
+        assert_eq!(
+            read_npz::<f64, i16, _>(&path).unwrap_err(),
+            error("indices", "value cannot be represented by the scalar type")
+        );
+        let res = read_npz::<f64, usize, _>("data/matrix_market/simple.mm");
+        match res {
+            Err(IoError::Io(_)) => (),
+            _ => panic!("expected an invalid archive error"),
+        }
+        // a crafted header whose data size overflows
+        let bytes = npy_bytes("<f8", Some(std::usize::MAX / 4), &[]);
+        match NpyArray::parse("data", bytes) {
+            Err(err) => assert_eq!(err, error("data", "array too large")),
+            Ok(_) => panic!("expected an array too large error"),
+        }
+    }
+
+    #[test]
+    fn write_read_npz() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let path = tmp_dir.path().join("simple.npz");
+        let mat = simple();
+        write_npz(&path, &mat).unwrap();
+        assert_eq!(read_npz::<f64, usize, _>(&path).unwrap(), mat);
+        write_npz(&path, &mat.to_csc()).unwrap();
+        assert_eq!(read_npz::<f64, usize, _>(&path).unwrap(), mat.to_csc());
+
+        // views can start in the middle of the data
+        let view = mat.view().middle_outer_views(1, 2);
+        write_npz(&path, &view).unwrap();
+        let expected = CsMat::new(
+            (2, 4),
+            vec![0, 1, 3],
+            vec![1, 0, 3],
+            vec![3., 4., -1.5],
+        );
+        assert_eq!(read_npz::<f64, usize, _>(&path).unwrap(), expected);
+
+        let mat: CsMatI<Complex32, i32> = CsMatI::new(
+            (2, 2),
+            vec![0, 1, 2],
+            vec![1, 0],
+            vec![Complex32::new(1., -2.), Complex32::new(0.5, 3.)],
+        );
+        write_npz(&path, &mat).unwrap();
+        assert_eq!(read_npz::<Complex32, i32, _>(&path).unwrap(), mat);
+
+        let triplets = TriMatI::<u8, u32>::from_triplets(
+            (2, 3),
+            vec![0, 1, 0],
+            vec![2, 0, 2],
+            vec![1, 2, 3],
+        );
+        write_npz_triplets(&path, &triplets).unwrap();
+        assert_eq!(read_npz_triplets(&path).unwrap(), triplets);
+        let mat = read_npz::<u8, u32, _>(&path).unwrap();
+        assert_eq!(mat.get(0, 2), Some(&4));
+    }
+}
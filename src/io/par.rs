@@ -0,0 +1,526 @@
+//! Parallel reading of Matrix Market files
+//!
+//! The body of the file is read in large batches, which are split into
+//! pieces ending on line boundaries. The pieces are parsed in parallel, and
+//! the compressed matrix is built directly from the parsed entries, without
+//! going through a triplet matrix.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use num_traits::Num;
+use rayon::prelude::*;
+
+use super::{
+    collect_triplets, parse_entry, sum_duplicates, Format, Header, IoError,
+    MatrixMarketError, MatrixMarketErrorKind, MatrixMarketRead,
+    MatrixMarketReader,
+};
+use crate::indexing::SpIndex;
+use crate::sparse::{CompressedStorage, CsMatI};
+
+/// Size in bytes of the batches of the input loaded in memory
+const BATCH_SIZE: usize = 1 << 26;
+
+/// Approximate size in bytes of the pieces of a batch parsed by a task
+const PIECE_SIZE: usize = 1 << 20;
+
+/// Read a sparse matrix file in the Matrix Market format into a compressed
+/// matrix with the requested storage, parsing the entries in parallel.
+///
+/// The result is identical to reading the file with `read_matrix_market`
+/// and converting the triplet matrix into the requested storage: entries of
+/// non-general matrices are mirrored, duplicate entries are summed and
+/// errors report the same line. Array files are read serially, as the
+/// location of their entries depends on their rank in the file. See
+/// `read_matrix_market_par_with_fill` to read pattern files.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::{read_matrix_market, read_matrix_market_par};
+/// use sprs::CSR;
+/// let path = "data/matrix_market/simple.mm";
+/// let mat = read_matrix_market_par::<f64, usize, _>(path, CSR).unwrap();
+/// let serial = read_matrix_market::<f64, usize, _>(path).unwrap();
+/// assert_eq!(mat, serial.to_csr());
+/// ```
+pub fn read_matrix_market_par<N, I, P>(
+    mm_file: P,
+    storage: CompressedStorage,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead + Num + Send + Sync,
+    P: AsRef<Path>,
+{
+    let mm_file = mm_file.as_ref();
+    let f = File::open(mm_file)?;
+    let mut reader = io::BufReader::new(f);
+    read_matrix_market_par_from_bufread(&mut reader, storage)
+}
+
+/// Read a sparse matrix in the Matrix Market format from an `io::BufRead`
+/// into a compressed matrix with the requested storage, parsing the entries
+/// in parallel.
+///
+/// See `read_matrix_market_par` for more details.
+pub fn read_matrix_market_par_from_bufread<N, I, R>(
+    reader: &mut R,
+    storage: CompressedStorage,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead + Num + Send + Sync,
+    R: io::BufRead,
+{
+    read_par_impl(reader, storage, None, BATCH_SIZE, PIECE_SIZE)
+}
+
+/// Read a sparse matrix file in the Matrix Market format into a compressed
+/// matrix with the requested storage, parsing the entries in parallel and
+/// giving the value `fill` to all the entries of a pattern file.
+///
+/// Files with another data type are read as in `read_matrix_market_par`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::read_matrix_market_par_with_fill;
+/// use sprs::CSC;
+/// let path = "data/matrix_market/pattern.mm";
+/// let mat =
+///     read_matrix_market_par_with_fill::<f64, usize, _>(path, CSC, 1.)
+///         .unwrap();
+/// assert_eq!(mat.data(), &[1.; 7]);
+/// ```
+pub fn read_matrix_market_par_with_fill<N, I, P>(
+    mm_file: P,
+    storage: CompressedStorage,
+    fill: N,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead + Num + Send + Sync,
+    P: AsRef<Path>,
+{
+    let mm_file = mm_file.as_ref();
+    let f = File::open(mm_file)?;
+    let mut reader = io::BufReader::new(f);
+    read_matrix_market_par_with_fill_from_bufread(&mut reader, storage, fill)
+}
+
+/// Read a sparse matrix in the Matrix Market format from an `io::BufRead`
+/// into a compressed matrix with the requested storage, parsing the entries
+/// in parallel and giving the value `fill` to all the entries of a pattern
+/// file.
+///
+/// See `read_matrix_market_par_with_fill` for more details.
+pub fn read_matrix_market_par_with_fill_from_bufread<N, I, R>(
+    reader: &mut R,
+    storage: CompressedStorage,
+    fill: N,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead + Num + Send + Sync,
+    R: io::BufRead,
+{
+    read_par_impl(reader, storage, Some(fill), BATCH_SIZE, PIECE_SIZE)
+}
+
+fn read_par_impl<N, I, R>(
+    reader: &mut R,
+    storage: CompressedStorage,
+    pattern_fill: Option<N>,
+    batch_size: usize,
+    piece_size: usize,
+) -> Result<CsMatI<N, I>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead + Num + Send + Sync,
+    R: io::BufRead,
+{
+    let mut stream = MatrixMarketReader::<N, I, _>::new(reader)?;
+    stream.pattern_fill = pattern_fill;
+    if stream.header.format == Format::Array {
+        let triplets = collect_triplets(stream)?;
+        return Ok(triplets.triplet_iter().into_cs(storage));
+    }
+    let MatrixMarketReader {
+        mut lines,
+        header,
+        shape: (rows, cols),
+        entries,
+        pattern_fill,
+        ..
+    } = stream;
+    let mut line_nb = lines.line_nb;
+    let mut nb_entries = 0;
+    let mut batch = Vec::new();
+    let mut chunks = Vec::new();
+    loop {
+        read_batch(&mut lines.reader, &mut batch, batch_size)?;
+        if batch.is_empty() {
+            break;
+        }
+        let text = std::str::from_utf8(&batch).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })?;
+        let pieces = split_pieces(text, piece_size);
+        // locate the first line and entry of each piece
+        let counts: Vec<_> =
+            pieces.par_iter().map(|p| count_lines(p)).collect();
+        let mut tasks = Vec::with_capacity(pieces.len());
+        for (&piece, (piece_lines, piece_entries)) in pieces.iter().zip(counts)
+        {
+            tasks.push((piece, line_nb, nb_entries));
+            line_nb += piece_lines;
+            nb_entries += piece_entries;
+        }
+        let parsed: Vec<_> = tasks
+            .into_par_iter()
+            .map(|(piece, first_line, first_entry)| {
+                let location = (first_line, first_entry, entries);
+                let fill = pattern_fill.as_ref();
+                parse_piece(piece, location, &header, (rows, cols), fill)
+            })
+            .collect();
+        // the first error in file order is reported, as in the serial path
+        for chunk in parsed {
+            chunks.push(chunk?);
+        }
+    }
+    if nb_entries < entries {
+        let kind = MatrixMarketErrorKind::NotEnoughEntries;
+        return Err(MatrixMarketError::new(line_nb, kind, None).into());
+    }
+    Ok(build_compressed(storage, (rows, cols), chunks))
+}
+
+/// Read about `batch_size` bytes from `reader` into `batch`, ending on a
+/// line boundary unless the end of the input is reached.
+fn read_batch<R: io::BufRead>(
+    reader: &mut R,
+    batch: &mut Vec<u8>,
+    batch_size: usize,
+) -> Result<(), io::Error> {
+    batch.clear();
+    while batch.len() < batch_size {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(());
+        }
+        let len = available.len().min(batch_size - batch.len());
+        batch.extend_from_slice(&available[..len]);
+        reader.consume(len);
+    }
+    if batch.last() != Some(&b'\n') {
+        reader.read_until(b'\n', batch)?;
+    }
+    Ok(())
+}
+
+/// Split `text` into pieces of about `piece_size` bytes ending on line
+/// boundaries.
+fn split_pieces(text: &str, piece_size: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.len() > piece_size {
+        let bytes = &rest.as_bytes()[piece_size..];
+        match bytes.iter().position(|&b| b == b'\n') {
+            Some(pos) => {
+                let (piece, tail) = rest.split_at(piece_size + pos + 1);
+                pieces.push(piece);
+                rest = tail;
+            }
+            None => break,
+        }
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Count the lines and the entries, ie the non-empty lines, of a piece.
+fn count_lines(piece: &str) -> (usize, usize) {
+    piece.lines().fold((0, 0), |(lines, entries), line| {
+        let is_entry = line.split_whitespace().next().is_some();
+        (lines + 1, entries + is_entry as usize)
+    })
+}
+
+/// Parse the entries of a piece, located by the number of lines and
+/// entries preceding it, returning the entries with their mirrors for
+/// non-general matrices. Pattern entries take the value `pattern_fill`.
+fn parse_piece<N, I>(
+    piece: &str,
+    (first_line, first_entry, entries): (usize, usize, usize),
+    header: &Header,
+    shape: (usize, usize),
+    pattern_fill: Option<&N>,
+) -> Result<Vec<(I, I, N)>, IoError>
+where
+    I: SpIndex,
+    N: MatrixMarketRead,
+{
+    let mut parsed = Vec::with_capacity(piece.len() / 8);
+    let mut entry_nb = first_entry;
+    for (line, line_nb) in piece.lines().zip(first_line + 1..) {
+        let first_token = line.split_whitespace().next();
+        if first_token.is_none() {
+            continue;
+        }
+        let located = |(kind, token)| -> IoError {
+            MatrixMarketError::new(line_nb, kind, token).into()
+        };
+        if entry_nb == entries {
+            let kind = MatrixMarketErrorKind::TooManyEntries;
+            return Err(located((kind, first_token)));
+        }
+        entry_nb += 1;
+        let (row, col, val, mirrored) =
+            parse_entry(line, header, shape, None, pattern_fill)
+                .map_err(located)?;
+        let (row, col) = (I::from_usize(row), I::from_usize(col));
+        parsed.push((row, col, val));
+        if let Some(mirrored) = mirrored {
+            parsed.push((col, row, mirrored));
+        }
+    }
+    Ok(parsed)
+}
+
+/// Build a compressed matrix from the parsed entries, summing duplicates.
+fn build_compressed<N, I>(
+    storage: CompressedStorage,
+    (rows, cols): (usize, usize),
+    chunks: Vec<Vec<(I, I, N)>>,
+) -> CsMatI<N, I>
+where
+    I: SpIndex,
+    N: Num + Clone + Send + Sync,
+{
+    let (outer_dims, inner_of) = match storage {
+        CompressedStorage::CSR => (rows, false),
+        CompressedStorage::CSC => (cols, true),
+    };
+    let split = |row: I, col: I| if inner_of { (col, row) } else { (row, col) };
+    let mut offsets = vec![0; outer_dims + 1];
+    for &(row, col, _) in chunks.iter().flatten() {
+        offsets[split(row, col).0.index() + 1] += 1;
+    }
+    for outer in 0..outer_dims {
+        offsets[outer + 1] += offsets[outer];
+    }
+    // scatter the entries into their outer dimension, in file order
+    let mut entries = vec![(I::zero(), N::zero()); offsets[outer_dims]];
+    let mut next = offsets.clone();
+    for (row, col, val) in chunks.into_iter().flatten() {
+        let (outer, inner) = split(row, col);
+        entries[next[outer.index()]] = (inner, val);
+        next[outer.index()] += 1;
+    }
+    // sort each outer dimension and sum its duplicates in parallel
+    let mut slices = Vec::with_capacity(outer_dims);
+    let mut rest = &mut entries[..];
+    for outer in 0..outer_dims {
+        let len = offsets[outer + 1] - offsets[outer];
+        let (slice, tail) = std::mem::take(&mut rest).split_at_mut(len);
+        slices.push(slice);
+        rest = tail;
+    }
+    let lens: Vec<usize> = slices.into_par_iter().map(sum_duplicates).collect();
+
+    let nnz = lens.iter().sum();
+    let mut indptr = Vec::with_capacity(outer_dims + 1);
+    let mut indices = Vec::with_capacity(nnz);
+    let mut data = Vec::with_capacity(nnz);
+    indptr.push(I::zero());
+    let mut entries = entries.into_iter();
+    for (outer, &len) in lens.iter().enumerate() {
+        let stored = offsets[outer + 1] - offsets[outer];
+        // the summed duplicates are left after the distinct entries
+        for (pos, (inner, val)) in entries.by_ref().take(stored).enumerate() {
+            if pos < len {
+                indices.push(inner);
+                data.push(val);
+            }
+        }
+        indptr.push(I::from_usize(indices.len()));
+    }
+    CsMatI::new_trusted(storage, (rows, cols), indptr, indices, data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        read_matrix_market_par, read_matrix_market_par_with_fill, read_par_impl,
+    };
+    use crate::io::{
+        read_matrix_market, read_matrix_market_from_bufread,
+        read_matrix_market_with_fill, write_matrix_market_to_bufwrite, IoError,
+    };
+    use crate::sparse::CsMat;
+    use crate::{CSC, CSR};
+    use num_complex::Complex64;
+
+    /// Compare the parallel and serial readers, with pieces small enough
+    /// to exercise the splitting of the input.
+    fn check_same_as_serial(text: &str) {
+        let serial = read_matrix_market_from_bufread::<f64, u32, _>(
+            &mut text.as_bytes(),
+        );
+        for &(batch_size, piece_size) in &[(1 << 20, 1 << 10), (64, 8), (1, 1)]
+        {
+            for &storage in &[CSR, CSC] {
+                let par = read_par_impl::<f64, u32, _>(
+                    &mut text.as_bytes(),
+                    storage,
+                    None,
+                    batch_size,
+                    piece_size,
+                );
+                match (&serial, par) {
+                    (Ok(serial), Ok(par)) => {
+                        assert_eq!(par, serial.triplet_iter().into_cs(storage))
+                    }
+                    (Err(serial), Err(par)) => assert_eq!(*serial, par),
+                    (serial, par) => panic!("{:?} != {:?}", serial, par),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn par_read_same_as_serial() {
+        for path in &[
+            "data/matrix_market/simple.mm",
+            "data/matrix_market/simple_int.mm",
+            "data/matrix_market/symmetric.mm",
+            "data/matrix_market/array.mm",
+            "data/matrix_market/array_symmetric.mm",
+            "data/matrix_market/skew_symmetric.mm",
+            "data/matrix_market/array_skew_symmetric.mm",
+        ] {
+            check_same_as_serial(&std::fs::read_to_string(path).unwrap());
+        }
+    }
+
+    #[test]
+    fn par_read_bad_files() {
+        for path in &[
+            "data/matrix_market/bad_files/too_many_elems_in_entry.mm",
+            "data/matrix_market/bad_files/not_enough_entries.mm",
+            "data/matrix_market/bad_files/too_many_entries.mm",
+            "data/matrix_market/bad_files/index_out_of_bounds.mm",
+            "data/matrix_market/bad_files/skew_symmetric_diagonal.mm",
+            "data/matrix_market/bad_files/bad_header.mm",
+            "data/matrix_market/pattern.mm",
+        ] {
+            check_same_as_serial(&std::fs::read_to_string(path).unwrap());
+        }
+    }
+
+    #[test]
+    fn par_read_duplicates_and_blank_lines() {
+        let text = "%%MatrixMarket matrix coordinate real symmetric\n\
+                    3 3 5\n\
+                    \n\
+                    2 1 1.5\n\
+                    3 3 2\n   \n\
+                    2 1 0.5\n\
+                    1 1 -1\n\
+                    \n\
+                    3 1 4\n\n";
+        check_same_as_serial(text);
+        let mat = read_par_impl::<f64, usize, _>(
+            &mut text.as_bytes(),
+            CSR,
+            None,
+            4,
+            2,
+        )
+        .unwrap();
+        let expected = CsMat::new(
+            (3, 3),
+            vec![0, 3, 4, 6],
+            vec![0, 1, 2, 0, 0, 2],
+            vec![-1., 2., 4., 2., 4., 2.],
+        );
+        assert_eq!(mat, expected);
+    }
+
+    #[test]
+    fn par_read_large() {
+        let (rows, cols) = (300, 200);
+        let mut indptr = vec![0];
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        for row in 0..rows {
+            for col in (row % 7..cols).step_by(5 + row % 3) {
+                indices.push(col);
+                data.push((row * cols + col) as f64 / 7.);
+            }
+            indptr.push(indices.len());
+        }
+        let mat = CsMat::new((rows, cols), indptr, indices, data);
+        let mut buffer = Vec::new();
+        write_matrix_market_to_bufwrite(&mut buffer, &mat).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        check_same_as_serial(&text);
+        let par = read_par_impl::<f64, usize, _>(
+            &mut text.as_bytes(),
+            CSR,
+            None,
+            512,
+            64,
+        )
+        .unwrap();
+        assert_eq!(par, mat);
+    }
+
+    #[test]
+    fn par_read_complex() {
+        let path = "data/matrix_market/hermitian.mm";
+        let par =
+            read_matrix_market_par::<Complex64, usize, _>(path, CSC).unwrap();
+        let serial = read_matrix_market::<Complex64, usize, _>(path).unwrap();
+        assert_eq!(par, serial.to_csc());
+
+        let path = "data/matrix_market/complex.mm";
+        let res = read_matrix_market_par::<f64, usize, _>(path, CSR);
+        match res {
+            Err(IoError::UnsupportedMatrixMarketFormat(err)) => {
+                assert_eq!(err.line, 4)
+            }
+            _ => panic!("complex values cannot be read into a real matrix"),
+        }
+    }
+
+    #[test]
+    fn par_read_pattern_with_fill() {
+        let path = "data/matrix_market/pattern.mm";
+        for &storage in &[CSR, CSC] {
+            let par = read_matrix_market_par_with_fill::<f64, usize, _>(
+                path, storage, 2.,
+            )
+            .unwrap();
+            let serial =
+                read_matrix_market_with_fill::<f64, usize, _>(path, 2.)
+                    .unwrap();
+            assert_eq!(par, serial.triplet_iter().into_cs(storage));
+        }
+        // the fill value does not affect files of another data type
+        let path = "data/matrix_market/simple.mm";
+        let par =
+            read_matrix_market_par_with_fill::<f64, usize, _>(path, CSR, 2.)
+                .unwrap();
+        assert_eq!(par, read_matrix_market_par(path, CSR).unwrap());
+    }
+}
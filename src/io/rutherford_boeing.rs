@@ -0,0 +1,913 @@
+//! Reading and writing of matrices in the Rutherford-Boeing format
+//!
+//! The Rutherford-Boeing format, and the older Harwell-Boeing format it
+//! derives from, store a sparse matrix in compressed sparse column form,
+//! as fixed-width fields whose layout is described by Fortran format
+//! specifications in the header:
+//!
+//! - a title line, holding a title and a short key identifying the matrix,
+//! - a line giving the number of lines of each section,
+//! - a line giving the matrix type, such as `rua` for a real unsymmetric
+//!   assembled matrix, and its shape and number of entries,
+//! - a line giving the Fortran formats of the sections,
+//! - the column pointers, row indices and values sections, using 1-based
+//!   indices.
+//!
+//! Only the lower triangle of symmetric, hermitian and skew-symmetric
+//! matrices is stored, and pattern matrices have no values section.
+//! Harwell-Boeing files, which have an additional right-hand side section,
+//! can also be read, the right-hand sides being ignored. Matrices in
+//! elemental form are not supported.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use num_complex::Complex;
+
+use super::{
+    parse_number, IoError, LineError, MatrixMarketRead, MmLines, SymmetryMode,
+};
+use crate::errors::SprsError;
+use crate::indexing::SpIndex;
+use crate::sparse::CsMatI;
+use crate::CSC;
+
+/// A matrix read from or written to a Rutherford-Boeing file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RbMatrix<N, I: SpIndex> {
+    /// The matrix, in CSC storage when read. Only the lower triangle is
+    /// stored for non-general symmetries, see `full_matrix`.
+    pub matrix: CsMatI<N, I>,
+    /// The symmetry of the matrix.
+    pub symmetry: SymmetryMode,
+    /// A title describing the matrix, of at most 72 characters.
+    pub title: String,
+    /// A key identifying the matrix, of at most 8 characters.
+    pub key: String,
+}
+
+impl<N, I: SpIndex> RbMatrix<N, I> {
+    /// Wrap a general matrix, with an empty title and key.
+    pub fn new(matrix: CsMatI<N, I>) -> Self {
+        Self {
+            matrix,
+            symmetry: SymmetryMode::General,
+            title: String::new(),
+            key: String::new(),
+        }
+    }
+
+    /// The full matrix, in CSC storage, obtained by mirroring the stored
+    /// triangle of non-general matrices. Mirrored entries are conjugated for
+    /// hermitian matrices and negated for skew-symmetric matrices.
+    ///
+    /// # Errors
+    ///
+    /// - if an entry of a skew-symmetric matrix cannot be negated, for
+    ///   instance because its scalar type is unsigned,
+    /// - if the matrix stores both an entry and its mirrored entry.
+    pub fn full_matrix(&self) -> Result<CsMatI<N, I>, SprsError>
+    where
+        N: MatrixMarketRead + Default,
+    {
+        if self.symmetry == SymmetryMode::General {
+            return Ok(self.matrix.to_csc());
+        }
+        let mut entries = Vec::with_capacity(2 * self.matrix.nnz());
+        for (val, (row, col)) in self.matrix.iter() {
+            let (row, col) = (row.index(), col.index());
+            entries.push((col, row, val.clone()));
+            if row == col {
+                continue;
+            }
+            let mirrored = match self.symmetry {
+                SymmetryMode::Hermitian => val.mm_conj(),
+                SymmetryMode::SkewSymmetric => val.mm_neg().ok_or(
+                    SprsError::IllegalArguments("Entry cannot be negated"),
+                )?,
+                _ => val.clone(),
+            };
+            entries.push((row, col, mirrored));
+        }
+        entries.sort_by_key(|&(col, row, _)| (col, row));
+        let cols = self.matrix.cols();
+        let mut indptr = vec![I::zero(); cols + 1];
+        for &(col, _, _) in &entries {
+            indptr[col + 1] += I::one();
+        }
+        for col in 0..cols {
+            let prev = indptr[col];
+            indptr[col + 1] += prev;
+        }
+        let (indices, data) = entries
+            .into_iter()
+            .map(|(_, row, val)| (I::from_usize(row), val))
+            .unzip();
+        CsMatI::new_checked(CSC, self.matrix.shape(), indptr, indices, data)
+            .map_err(|(_, _, _, err)| err)
+    }
+}
+
+/// The layout of the fields of a section, given by a Fortran format such
+/// as `(8I10)` or `(1P,4E20.12)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct FortranFormat {
+    /// The number of fields per line
+    repeat: usize,
+    /// The width of a field
+    width: usize,
+}
+
+/// Parse a Fortran format, ignoring its field type, precision and scale
+/// factor which do not affect the reading of the fields.
+fn parse_fortran_format(format: &str) -> Option<FortranFormat> {
+    let format: String = format
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let format = format.trim_start_matches('(').trim_end_matches(')');
+    // skip a scale factor such as `1P,`
+    let format = match format.find('P') {
+        Some(pos) => format[pos + 1..].trim_start_matches(','),
+        None => format,
+    };
+    let type_pos = format.find(|c: char| c.is_ascii_alphabetic())?;
+    let repeat = match type_pos {
+        0 => 1,
+        _ => format[..type_pos].parse().ok()?,
+    };
+    let width: String = format[type_pos + 1..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    let width = width.parse().ok()?;
+    if repeat == 0 || width == 0 {
+        return None;
+    }
+    Some(FortranFormat { repeat, width })
+}
+
+/// The type of the values of a matrix, given by the first letter of its
+/// type code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ValueType {
+    Real,
+    Complex,
+    Integer,
+    Pattern,
+}
+
+/// Line reader of a Rutherford-Boeing file, locating errors.
+struct RbLines<R> {
+    lines: MmLines<R>,
+}
+
+impl<R: io::BufRead> RbLines<R> {
+    fn error(&self, reason: &'static str, token: Option<&str>) -> IoError {
+        let err = LineError::new(self.lines.line_nb, reason, token);
+        IoError::BadRutherfordBoeingFile(err)
+    }
+
+    /// Read the next line of the header, as a string without its line
+    /// terminator.
+    fn header_line(&mut self) -> Result<String, IoError> {
+        if !self.lines.next_line()? {
+            return Err(self.error("missing header line", None));
+        }
+        let line = self.lines.line.trim_end_matches(&['\n', '\r'][..]);
+        Ok(line.to_string())
+    }
+
+    /// Read `count` fixed-width fields laid out according to `format`,
+    /// converting them with `parse`.
+    fn read_fields<T, F>(
+        &mut self,
+        format: FortranFormat,
+        count: usize,
+        mut parse: F,
+    ) -> Result<Vec<T>, IoError>
+    where
+        F: FnMut(&str) -> Result<T, &'static str>,
+    {
+        let mut fields = Vec::with_capacity(count);
+        while fields.len() < count {
+            if !self.lines.next_line()? {
+                return Err(self.error("fewer entries than declared", None));
+            }
+            let line = self.lines.line.trim_end_matches(&['\n', '\r'][..]);
+            for pos in 0..format.repeat {
+                let start = pos * format.width;
+                if fields.len() == count || start >= line.len() {
+                    break;
+                }
+                let end = line.len().min(start + format.width);
+                let field = line
+                    .get(start..end)
+                    .ok_or_else(|| self.error("invalid field", None))?
+                    .trim();
+                let val = parse(field)
+                    .map_err(|reason| self.error(reason, Some(field)))?;
+                fields.push(val);
+            }
+        }
+        Ok(fields)
+    }
+}
+
+/// Parse a real field, which can use a Fortran `D` exponent
+fn parse_real(field: &str) -> Option<f64> {
+    field.replace(&['D', 'd'][..], "e").parse().ok()
+}
+
+/// Read a matrix file in the Rutherford-Boeing or Harwell-Boeing format.
+///
+/// The matrix is returned in CSC storage, holding only the lower triangle
+/// of non-general matrices as stored in the file. Pattern files can be read
+/// into a `CsMatI<(), I>`, see `read_rutherford_boeing_with_fill` to read
+/// them into numeric matrices. Complex files can be read into
+/// `num_complex::Complex` matrices, see `MatrixMarketRead` for the
+/// supported scalar types.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::rutherford_boeing::read_rutherford_boeing;
+/// use sprs::io::SymmetryMode;
+/// let path = "data/rutherford_boeing/symmetric.rsa";
+/// let rb = read_rutherford_boeing::<f64, usize, _>(path).unwrap();
+/// assert_eq!(rb.symmetry, SymmetryMode::Symmetric);
+/// assert_eq!(rb.matrix.nnz(), 5);
+/// assert_eq!(rb.full_matrix().unwrap().nnz(), 7);
+/// ```
+pub fn read_rutherford_boeing<N, I, P>(
+    path: P,
+) -> Result<RbMatrix<N, I>, IoError>
+where
+    N: MatrixMarketRead + Copy,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let f = File::open(path)?;
+    read_rutherford_boeing_from_bufread(io::BufReader::new(f))
+}
+
+/// Read a matrix in the Rutherford-Boeing or Harwell-Boeing format from an
+/// `io::BufRead`.
+///
+/// See `read_rutherford_boeing` for more details.
+pub fn read_rutherford_boeing_from_bufread<N, I, R>(
+    reader: R,
+) -> Result<RbMatrix<N, I>, IoError>
+where
+    N: MatrixMarketRead + Copy,
+    I: SpIndex,
+    R: io::BufRead,
+{
+    read_rutherford_boeing_impl(reader, None)
+}
+
+/// Read a matrix file in the Rutherford-Boeing or Harwell-Boeing format,
+/// giving the value `fill` to all the entries of a pattern file.
+///
+/// Files with another value type are read as in `read_rutherford_boeing`.
+pub fn read_rutherford_boeing_with_fill<N, I, P>(
+    path: P,
+    fill: N,
+) -> Result<RbMatrix<N, I>, IoError>
+where
+    N: MatrixMarketRead + Copy,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let f = File::open(path)?;
+    read_rutherford_boeing_impl(io::BufReader::new(f), Some(fill))
+}
+
+fn read_rutherford_boeing_impl<N, I, R>(
+    reader: R,
+    pattern_fill: Option<N>,
+) -> Result<RbMatrix<N, I>, IoError>
+where
+    N: MatrixMarketRead + Copy,
+    I: SpIndex,
+    R: io::BufRead,
+{
+    let mut lines = RbLines {
+        lines: MmLines::new(reader),
+    };
+    // title and key
+    let line = lines.header_line()?;
+    let title = line.get(..72).unwrap_or(&line).trim_end().to_string();
+    let key = line.get(72..).unwrap_or("").trim().to_string();
+
+    // number of lines of each section, Harwell-Boeing files having an
+    // additional count for the right-hand sides
+    let line = lines.header_line()?;
+    let counts: Vec<&str> = line.split_whitespace().collect();
+    let rhs_lines = match counts.len() {
+        4 => 0,
+        5 => counts[4]
+            .parse::<usize>()
+            .map_err(|_| lines.error("invalid line count", Some(counts[4])))?,
+        _ => return Err(lines.error("invalid line counts", None)),
+    };
+
+    // matrix type, shape and number of entries
+    let line = lines.header_line()?;
+    let mxtype = line.get(..3).unwrap_or(&line).to_ascii_lowercase();
+    let mut type_code = mxtype.chars();
+    let value_type = match type_code.next() {
+        Some('r') => ValueType::Real,
+        Some('c') => ValueType::Complex,
+        Some('i') => ValueType::Integer,
+        Some('p') | Some('q') => ValueType::Pattern,
+        _ => return Err(lines.error("invalid matrix type", Some(&mxtype))),
+    };
+    let symmetry = match type_code.next() {
+        Some('u') | Some('r') => SymmetryMode::General,
+        Some('s') => SymmetryMode::Symmetric,
+        Some('h') => SymmetryMode::Hermitian,
+        Some('z') => SymmetryMode::SkewSymmetric,
+        _ => return Err(lines.error("invalid matrix type", Some(&mxtype))),
+    };
+    match type_code.next() {
+        Some('a') => (),
+        Some('e') => {
+            let reason = "elemental matrices are not supported";
+            return Err(lines.error(reason, Some(&mxtype)));
+        }
+        _ => return Err(lines.error("invalid matrix type", Some(&mxtype))),
+    }
+    let mut sizes = Vec::with_capacity(4);
+    for token in line.get(3..).unwrap_or("").split_whitespace() {
+        let size = token
+            .parse::<usize>()
+            .map_err(|_| lines.error("invalid size", Some(token)))?;
+        sizes.push(size);
+    }
+    let (rows, cols, nnz) = match sizes[..] {
+        [rows, cols, nnz] | [rows, cols, nnz, _] => (rows, cols, nnz),
+        _ => return Err(lines.error("invalid sizes", None)),
+    };
+    if symmetry != SymmetryMode::General && rows != cols {
+        let reason = "non-square matrix declared as symmetric";
+        return Err(lines.error(reason, None));
+    }
+
+    // formats of the sections
+    let line = lines.header_line()?;
+    let formats: Vec<&str> = line.split_whitespace().collect();
+    let parse_format = |pos: usize| {
+        let token = formats.get(pos).cloned();
+        token
+            .and_then(parse_fortran_format)
+            .ok_or_else(|| lines.error("invalid Fortran format", token))
+    };
+    let ptr_format = parse_format(0)?;
+    let ind_format = parse_format(1)?;
+    let val_format = match value_type {
+        ValueType::Pattern => None,
+        _ => Some(parse_format(2)?),
+    };
+    if rhs_lines > 0 {
+        // the right-hand side description is not needed
+        lines.header_line()?;
+    }
+
+    // column pointers, which are 1-based and non-decreasing
+    let mut prev = 0;
+    let indptr =
+        lines.read_fields(ptr_format, cols + 1, |field| {
+            match field.parse::<usize>() {
+                Ok(ptr) if ptr >= prev.max(1) && ptr <= nnz + 1 => {
+                    prev = ptr;
+                    Ok(I::from_usize(ptr - 1))
+                }
+                _ => Err("invalid column pointer"),
+            }
+        })?;
+    if indptr[0].index() != 0 || indptr[cols].index() != nnz {
+        let reason = "column pointers do not match the number of entries";
+        return Err(lines.error(reason, None));
+    }
+
+    // row indices, which are 1-based
+    let indices = lines.read_fields(ind_format, nnz, |field| {
+        match field.parse::<usize>() {
+            Ok(ind) if ind >= 1 && ind <= rows => Ok(I::from_usize(ind - 1)),
+            Ok(_) => Err("index out of bounds of the declared shape"),
+            Err(_) => Err("invalid index"),
+        }
+    })?;
+
+    let data = match (value_type, val_format) {
+        (ValueType::Real, Some(format)) => {
+            lines.read_fields(format, nnz, |field| {
+                parse_real(field)
+                    .and_then(N::from_mm_real)
+                    .ok_or("invalid value")
+            })?
+        }
+        (ValueType::Integer, Some(format)) => {
+            lines.read_fields(format, nnz, |field| {
+                parse_number(field).ok_or("invalid value")
+            })?
+        }
+        (ValueType::Complex, Some(format)) => {
+            // the real and imaginary parts are stored as consecutive fields
+            let parts = lines.read_fields(format, 2 * nnz, |field| {
+                parse_real(field).ok_or("invalid value")
+            })?;
+            let data: Option<Vec<N>> = parts
+                .chunks(2)
+                .map(|part| N::from_mm_complex(part[0], part[1]))
+                .collect();
+            data.ok_or_else(|| {
+                lines.error("complex entry read into a real type", None)
+            })?
+        }
+        _ => {
+            let fill =
+                pattern_fill.or_else(N::from_mm_pattern).ok_or_else(|| {
+                    lines.error("pattern entry read without a fill value", None)
+                })?;
+            vec![fill; nnz]
+        }
+    };
+
+    let matrix = CsMatI::try_new_csc((rows, cols), indptr, indices, data)
+        .map_err(|_| lines.error("duplicate entries in a column", None))?;
+    Ok(RbMatrix {
+        matrix,
+        symmetry,
+        title,
+        key,
+    })
+}
+
+/// Scalar types that can be written into a Rutherford-Boeing file.
+///
+/// This is implemented for the primitive numeric types, for
+/// `num_complex::Complex`, and for the unit type which is written as a
+/// pattern matrix.
+pub trait RutherfordBoeingWrite {
+    /// The letter giving the value type in the matrix type code: `r` for
+    /// real, `c` for complex, `i` for integer and `p` for pattern values.
+    fn rb_value_type() -> char;
+
+    /// Append the fields representing this value to `fields`: a single
+    /// field for real and integer values, the real and imaginary parts for
+    /// complex values, and no field for pattern values.
+    fn rb_fields(&self, fields: &mut Vec<String>);
+}
+
+macro_rules! rb_write_int_impl {
+    ($prim: ty) => {
+        impl RutherfordBoeingWrite for $prim {
+            fn rb_value_type() -> char {
+                'i'
+            }
+
+            fn rb_fields(&self, fields: &mut Vec<String>) {
+                fields.push(self.to_string());
+            }
+        }
+    };
+}
+
+rb_write_int_impl!(i8);
+rb_write_int_impl!(u8);
+rb_write_int_impl!(i16);
+rb_write_int_impl!(u16);
+rb_write_int_impl!(i32);
+rb_write_int_impl!(u32);
+rb_write_int_impl!(i64);
+rb_write_int_impl!(u64);
+rb_write_int_impl!(isize);
+rb_write_int_impl!(usize);
+
+macro_rules! rb_write_float_impl {
+    ($prim: ty) => {
+        impl RutherfordBoeingWrite for $prim {
+            fn rb_value_type() -> char {
+                'r'
+            }
+
+            fn rb_fields(&self, fields: &mut Vec<String>) {
+                // the shortest representation reading back to this value
+                fields.push(format!("{:e}", self));
+            }
+        }
+    };
+}
+
+rb_write_float_impl!(f32);
+rb_write_float_impl!(f64);
+
+impl<T: RutherfordBoeingWrite> RutherfordBoeingWrite for Complex<T> {
+    fn rb_value_type() -> char {
+        'c'
+    }
+
+    fn rb_fields(&self, fields: &mut Vec<String>) {
+        self.re.rb_fields(fields);
+        self.im.rb_fields(fields);
+    }
+}
+
+impl RutherfordBoeingWrite for () {
+    fn rb_value_type() -> char {
+        'p'
+    }
+
+    fn rb_fields(&self, _fields: &mut Vec<String>) {}
+}
+
+/// The lines of a section made of `fields`, laid out in lines of at most
+/// 80 characters, along with the Fortran format describing this layout.
+struct Section {
+    format: String,
+    lines: Vec<String>,
+}
+
+impl Section {
+    fn new(fields: &[String], type_code: char) -> Self {
+        // leave room for a separating space
+        let width = fields.iter().map(String::len).max().unwrap_or(1) + 1;
+        let repeat = (80 / width).max(1);
+        let format = match type_code {
+            'I' => format!("({}I{})", repeat, width),
+            _ => format!("({}E{}.{})", repeat, width, width.max(9) - 8),
+        };
+        let lines = fields
+            .chunks(repeat)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|field| format!("{:>width$}", field, width = width))
+                    .collect()
+            })
+            .collect();
+        Self { format, lines }
+    }
+}
+
+/// Write a matrix into the Rutherford-Boeing format.
+///
+/// For non-general symmetries, only the entries of the lower triangle are
+/// written, this function does not check the actual symmetry of the
+/// matrix. The diagonal entries of skew-symmetric matrices are also
+/// ignored. Title and key are truncated to 72 and 8 characters.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sprs::io::rutherford_boeing::{write_rutherford_boeing, RbMatrix};
+/// use sprs::CsMat;
+/// let eye: CsMat<f64> = CsMat::eye(5);
+/// let mut rb = RbMatrix::new(eye);
+/// rb.title = "Identity matrix".to_string();
+/// rb.key = "EYE5".to_string();
+/// write_rutherford_boeing("/tmp/eye5.rua", &rb).unwrap();
+/// ```
+pub fn write_rutherford_boeing<N, I, P>(
+    path: P,
+    rb: &RbMatrix<N, I>,
+) -> Result<(), io::Error>
+where
+    N: RutherfordBoeingWrite + Clone + Default,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let f = File::create(path)?;
+    let mut writer = io::BufWriter::new(f);
+    write_rutherford_boeing_to_bufwrite(&mut writer, rb)?;
+    writer.flush()
+}
+
+/// Write a matrix into the Rutherford-Boeing format, using any `io::Write`
+/// as output.
+///
+/// See `write_rutherford_boeing` for more details.
+pub fn write_rutherford_boeing_to_bufwrite<N, I, W>(
+    writer: &mut W,
+    rb: &RbMatrix<N, I>,
+) -> Result<(), io::Error>
+where
+    N: RutherfordBoeingWrite + Clone + Default,
+    I: SpIndex,
+    W: Write + ?Sized,
+{
+    let converted;
+    let csc = if rb.matrix.is_csc() {
+        rb.matrix.view()
+    } else {
+        converted = rb.matrix.to_csc();
+        converted.view()
+    };
+    let (rows, cols) = csc.shape();
+    let is_written = |row: usize, col: usize| match rb.symmetry {
+        SymmetryMode::General => true,
+        SymmetryMode::SkewSymmetric => row > col,
+        _ => row >= col,
+    };
+    let mut indptr = Vec::with_capacity(cols + 1);
+    let mut indices = Vec::with_capacity(csc.nnz());
+    let mut values = Vec::with_capacity(csc.nnz());
+    indptr.push(1.to_string());
+    for (col, vec) in csc.outer_iterator().enumerate() {
+        for (row, val) in vec.iter() {
+            if is_written(row, col) {
+                indices.push((row + 1).to_string());
+                val.rb_fields(&mut values);
+            }
+        }
+        indptr.push((indices.len() + 1).to_string());
+    }
+    let nnz = indices.len();
+    let value_type = N::rb_value_type();
+    let ptr_section = Section::new(&indptr, 'I');
+    let ind_section = Section::new(&indices, 'I');
+    let val_section = match value_type {
+        'p' => None,
+        'i' => Some(Section::new(&values, 'I')),
+        _ => Some(Section::new(&values, 'E')),
+    };
+    let val_lines = val_section.as_ref().map_or(0, |s| s.lines.len());
+    let (val_format, val_data) = match val_section {
+        Some(Section { format, lines }) => (format, lines),
+        None => (String::new(), Vec::new()),
+    };
+
+    let title: String = rb.title.chars().take(72).collect();
+    let key: String = rb.key.chars().take(8).collect();
+    writeln!(writer, "{:<72}{:<8}", title, key)?;
+    writeln!(
+        writer,
+        "{:>14}{:>14}{:>14}{:>14}",
+        ptr_section.lines.len() + ind_section.lines.len() + val_lines,
+        ptr_section.lines.len(),
+        ind_section.lines.len(),
+        val_lines,
+    )?;
+    let symmetry = match rb.symmetry {
+        SymmetryMode::General if rows == cols => 'u',
+        SymmetryMode::General => 'r',
+        SymmetryMode::Symmetric => 's',
+        SymmetryMode::Hermitian => 'h',
+        SymmetryMode::SkewSymmetric => 'z',
+    };
+    writeln!(
+        writer,
+        "{}{}a{:11}{:>14}{:>14}{:>14}{:>14}",
+        value_type, symmetry, "", rows, cols, nnz, 0
+    )?;
+    writeln!(
+        writer,
+        "{:<16}{:<16}{:<20}",
+        ptr_section.format, ind_section.format, val_format
+    )?;
+    let sections = [ptr_section.lines, ind_section.lines, val_data];
+    for line in sections.iter().flatten() {
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        parse_fortran_format, read_rutherford_boeing,
+        read_rutherford_boeing_from_bufread, read_rutherford_boeing_with_fill,
+        write_rutherford_boeing, write_rutherford_boeing_to_bufwrite,
+        FortranFormat, RbMatrix,
+    };
+    use crate::errors::SprsError;
+    use crate::io::{IoError, LineError, SymmetryMode};
+    use crate::sparse::CsMat;
+    use num_complex::Complex64;
+    use tempdir::TempDir;
+
+    #[test]
+    fn fortran_formats() {
+        let format = |repeat, width| Some(FortranFormat { repeat, width });
+        assert_eq!(parse_fortran_format("(8I10)"), format(8, 10));
+        assert_eq!(parse_fortran_format("(16i5)"), format(16, 5));
+        assert_eq!(parse_fortran_format("(4E20.12)"), format(4, 20));
+        assert_eq!(parse_fortran_format("(1P,3E25.16)"), format(3, 25));
+        assert_eq!(parse_fortran_format("(1P5D16.8)"), format(5, 16));
+        assert_eq!(parse_fortran_format("( 3F 9.2 )"), format(3, 9));
+        assert_eq!(parse_fortran_format("(E15.8E3)"), format(1, 15));
+        assert_eq!(parse_fortran_format("(I)"), None);
+        assert_eq!(parse_fortran_format("garbage"), None);
+    }
+
+    #[test]
+    fn read_unsymmetric() {
+        let path = "data/rutherford_boeing/simple.rua";
+        let rb = read_rutherford_boeing::<f64, usize, _>(path).unwrap();
+        let expected = CsMat::new_csc(
+            (4, 4),
+            vec![0, 2, 3, 5, 6],
+            vec![0, 2, 1, 0, 3, 3],
+            vec![1., -2.5, 3., 4., 5e-3, 6.],
+        );
+        assert_eq!(rb.matrix, expected);
+        assert_eq!(rb.symmetry, SymmetryMode::General);
+        assert_eq!(rb.title, "Simple real unsymmetric test matrix");
+        assert_eq!(rb.key, "SIMPLE");
+
+        // the structure of any matrix can be read
+        let rb = read_rutherford_boeing::<(), u32, _>(path).unwrap();
+        assert_eq!(rb.matrix.indices(), &[0, 2, 1, 0, 3, 3]);
+    }
+
+    #[test]
+    fn read_symmetric_and_pattern() {
+        let path = "data/rutherford_boeing/symmetric.rsa";
+        let rb = read_rutherford_boeing::<f64, usize, _>(path).unwrap();
+        assert_eq!(rb.symmetry, SymmetryMode::Symmetric);
+        assert_eq!(rb.matrix.indptr(), &[0, 2, 4, 5]);
+        assert_eq!(rb.matrix.indices(), &[0, 1, 1, 2, 2]);
+        let expected = CsMat::new_csc(
+            (3, 3),
+            vec![0, 2, 5, 7],
+            vec![0, 1, 0, 1, 2, 1, 2],
+            vec![4., 1., 1., 5., 2., 2., 6.],
+        );
+        assert_eq!(rb.full_matrix().unwrap(), expected);
+
+        let path = "data/rutherford_boeing/pattern.psa";
+        let rb = read_rutherford_boeing::<(), usize, _>(path).unwrap();
+        assert_eq!(rb.matrix.indices(), &[0, 1, 1, 2, 2]);
+        let rb = read_rutherford_boeing_with_fill(path, 1_i32).unwrap();
+        assert_eq!(
+            rb.full_matrix().unwrap(),
+            expected.map(|&v| if v == 0. { 0 } else { 1 })
+        );
+        let res = read_rutherford_boeing::<f64, usize, _>(path);
+        match res {
+            Err(IoError::BadRutherfordBoeingFile(err)) => {
+                assert_eq!(
+                    err.reason,
+                    "pattern entry read without a fill value"
+                )
+            }
+            _ => panic!("a pattern file needs a fill value"),
+        }
+    }
+
+    #[test]
+    fn read_harwell_boeing() {
+        let path = "data/rutherford_boeing/simple_hb.rua";
+        let rb = read_rutherford_boeing::<f64, usize, _>(path).unwrap();
+        let expected =
+            read_rutherford_boeing("data/rutherford_boeing/simple.rua")
+                .unwrap();
+        assert_eq!(rb.matrix, expected.matrix);
+    }
+
+    #[test]
+    fn read_bad_files() {
+        let read = |text: &str| {
+            read_rutherford_boeing_from_bufread::<f64, usize, _>(
+                text.as_bytes(),
+            )
+        };
+        let error = |line, token: Option<&str>, reason| {
+            IoError::BadRutherfordBoeingFile(LineError {
+                line,
+                token: token.map(str::to_string),
+                reason,
+            })
+        };
+        let header = "title\n3 1 1 1\nrua 2 2 2 0\n(3I2) (2I2) (2E10.3)\n";
+        let text = format!("{}1 2 3\n1 3\n1.0 2.0\n", header);
+        assert_eq!(
+            read(&text).unwrap_err(),
+            error(6, Some("3"), "index out of bounds of the declared shape")
+        );
+        let text = format!("{}1 3 2\n1 2\n1.0 2.0\n", header);
+        assert_eq!(
+            read(&text).unwrap_err(),
+            error(5, Some("2"), "invalid column pointer")
+        );
+        let text = format!("{}1 2 3\n1 2\n1.0\n", header);
+        assert_eq!(
+            read(&text).unwrap_err(),
+            error(7, None, "fewer entries than declared")
+        );
+        let text = "title\n3 1 1 1\nrse 2 2 2 0\n(3I2) (2I2) (2E10.3)\n";
+        assert_eq!(
+            read(text).unwrap_err(),
+            error(3, Some("rse"), "elemental matrices are not supported")
+        );
+        let text = "title\n3 1 1 1\nrua 2 2 2 0\n(3I2) (2I) (2E10.3)\n";
+        assert_eq!(
+            read(text).unwrap_err(),
+            error(4, Some("(2I)"), "invalid Fortran format")
+        );
+    }
+
+    #[test]
+    fn write_read_rutherford_boeing() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let path = tmp_dir.path().join("simple.rua");
+        let rb = read_rutherford_boeing::<f64, usize, _>(
+            "data/rutherford_boeing/simple.rua",
+        )
+        .unwrap();
+        write_rutherford_boeing(&path, &rb).unwrap();
+        let rb2 = read_rutherford_boeing(&path).unwrap();
+        assert_eq!(rb, rb2);
+
+        // CSR matrices are converted, and values are written exactly
+        let mat = CsMat::new(
+            (2, 3),
+            vec![0, 2, 3],
+            vec![0, 2, 1],
+            vec![1. / 3., -1e-300, 12345.678],
+        );
+        let mut buffer = Vec::new();
+        write_rutherford_boeing_to_bufwrite(&mut buffer, &RbMatrix::new(mat))
+            .unwrap();
+        let rb =
+            read_rutherford_boeing_from_bufread::<f64, u32, _>(&buffer[..])
+                .unwrap();
+        assert_eq!(rb.matrix.to_dense()[[0, 0]], 1. / 3.);
+        assert_eq!(rb.matrix.to_dense()[[0, 2]], -1e-300);
+        assert_eq!(rb.matrix.to_dense()[[1, 1]], 12345.678);
+        let header = String::from_utf8(buffer).unwrap();
+        assert!(header.lines().nth(2).unwrap().starts_with("rra"));
+    }
+
+    #[test]
+    fn write_read_symmetric_variants() {
+        let mat = CsMat::new_csc(
+            (3, 3),
+            vec![0, 2, 4, 5],
+            vec![0, 2, 1, 2, 2],
+            vec![
+                Complex64::new(1., 0.),
+                Complex64::new(2., -1.),
+                Complex64::new(3., 0.),
+                Complex64::new(0., 4.),
+                Complex64::new(5., 0.),
+            ],
+        );
+        let rb = RbMatrix {
+            matrix: mat.clone(),
+            symmetry: SymmetryMode::Hermitian,
+            title: "Hermitian matrix".to_string(),
+            key: "HERM3".to_string(),
+        };
+        let mut buffer = Vec::new();
+        write_rutherford_boeing_to_bufwrite(&mut buffer, &rb).unwrap();
+        let rb2 = read_rutherford_boeing_from_bufread(&buffer[..]).unwrap();
+        assert_eq!(rb, rb2);
+        let full = rb2.full_matrix().unwrap();
+        assert_eq!(full.get(0, 2), Some(&Complex64::new(2., 1.)));
+        assert_eq!(full.get(2, 0), Some(&Complex64::new(2., -1.)));
+
+        // the upper triangle and the diagonal of skew-symmetric matrices
+        // are not written
+        let mat = CsMat::new_csc(
+            (2, 2),
+            vec![0, 2, 4],
+            vec![0, 1, 0, 1],
+            vec![1, 2, -2, 1],
+        );
+        let mut rb = RbMatrix::new(mat);
+        rb.symmetry = SymmetryMode::SkewSymmetric;
+        let mut buffer = Vec::new();
+        write_rutherford_boeing_to_bufwrite(&mut buffer, &rb).unwrap();
+        let rb2 =
+            read_rutherford_boeing_from_bufread::<i64, usize, _>(&buffer[..])
+                .unwrap();
+        assert_eq!(rb2.matrix.nnz(), 1);
+        let expected =
+            CsMat::new_csc((2, 2), vec![0, 1, 2], vec![1, 0], vec![2, -2]);
+        assert_eq!(rb2.full_matrix().unwrap(), expected);
+        // the mirrored entries cannot be represented by unsigned types
+        let rb2 =
+            read_rutherford_boeing_from_bufread::<u64, usize, _>(&buffer[..])
+                .unwrap();
+        assert_eq!(
+            rb2.full_matrix(),
+            Err(SprsError::IllegalArguments("Entry cannot be negated"))
+        );
+
+        // pattern matrices are written without values
+        let pattern = RbMatrix::new(rb.matrix.map(|_| ()));
+        let mut buffer = Vec::new();
+        write_rutherford_boeing_to_bufwrite(&mut buffer, &pattern).unwrap();
+        let rb2 =
+            read_rutherford_boeing_from_bufread::<(), usize, _>(&buffer[..])
+                .unwrap();
+        assert_eq!(rb2.matrix, pattern.matrix);
+        let written = String::from_utf8(buffer).unwrap();
+        assert_eq!(written.lines().count(), 6);
+    }
+}
@@ -0,0 +1,428 @@
+//! Reading and writing of labelled datasets in the SVMLight format
+//!
+//! The SVMLight format, also used by LibSVM, stores one sample per line, as
+//! a label followed by the non-zero features of the sample:
+//!
+//! ```text
+//! # comment
+//! 1.0 qid:3 1:0.5 4:-2 # trailing comment
+//! -1 2:1.5
+//! ```
+//!
+//! Each line becomes a row of a CSR matrix, the features being indexed by
+//! columns. The optional `qid` field groups samples into queries for
+//! ranking tasks.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::{parse_number, IoError, LineError, MatrixMarketRead};
+use crate::indexing::SpIndex;
+use crate::sparse::CsMatI;
+
+/// A labelled dataset, with one sample per row of the matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvmLightData<N, I: SpIndex> {
+    /// The features of the samples, in CSR storage when read.
+    pub matrix: CsMatI<N, I>,
+    /// The label of each sample.
+    pub labels: Vec<f64>,
+    /// The query identifier of each sample, if the samples have one.
+    pub query_ids: Option<Vec<u64>>,
+}
+
+/// The indexing of the features in an SVMLight file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexBase {
+    /// The first feature has index 0.
+    Zero,
+    /// The first feature has index 1, as specified by the SVMLight format.
+    One,
+    /// Detect the indexing when reading: the features are considered
+    /// zero-based if any of them has index 0, one-based otherwise. One-based
+    /// indices are used when writing.
+    Auto,
+}
+
+fn svmlight_error(line: usize, reason: &'static str, token: &str) -> IoError {
+    IoError::BadSvmLightFile(LineError::new(line, reason, Some(token)))
+}
+
+/// Read a dataset file in the SVMLight format.
+///
+/// The number of columns of the matrix is one more than the largest
+/// feature index. Features of a sample can be listed in any order, but must
+/// not be repeated. Comments, starting with `#`, and blank lines are
+/// ignored.
+///
+/// # Errors
+///
+/// - if a label, feature index or value cannot be parsed, or a value cannot
+///   be represented by the scalar type,
+/// - if a feature index is 0 while reading one-based indices,
+/// - if a feature is repeated in a sample,
+/// - if some samples have a query identifier and others do not,
+/// - if the index type cannot represent the shape of the matrix.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::io::svmlight::{read_svmlight, IndexBase};
+/// let path = "data/svmlight/simple.svm";
+/// let data = read_svmlight::<f64, usize, _>(path, IndexBase::One).unwrap();
+/// assert_eq!(data.matrix.shape(), (4, 5));
+/// assert_eq!(data.labels, vec![1., -1., 1., 2.5]);
+/// assert_eq!(data.matrix.get(0, 3), Some(&-2.));
+/// ```
+pub fn read_svmlight<N, I, P>(
+    path: P,
+    base: IndexBase,
+) -> Result<SvmLightData<N, I>, IoError>
+where
+    N: MatrixMarketRead + Copy,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let f = File::open(path)?;
+    read_svmlight_from_bufread(io::BufReader::new(f), base)
+}
+
+/// Read a dataset in the SVMLight format from an `io::BufRead`.
+///
+/// See `read_svmlight` for more details.
+pub fn read_svmlight_from_bufread<N, I, R>(
+    mut reader: R,
+    base: IndexBase,
+) -> Result<SvmLightData<N, I>, IoError>
+where
+    N: MatrixMarketRead + Copy,
+    I: SpIndex,
+    R: io::BufRead,
+{
+    let mut labels = Vec::new();
+    let mut query_ids = Vec::new();
+    let mut indptr = vec![I::zero()];
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    let mut features: Vec<(usize, N)> = Vec::new();
+    let mut has_zero_index = false;
+    let mut line = String::new();
+    let mut line_nb = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_nb += 1;
+        let content = match line.find('#') {
+            Some(pos) => &line[..pos],
+            None => &line[..],
+        };
+        let mut tokens = content.split_whitespace().peekable();
+        let label = match tokens.next() {
+            Some(label) => label,
+            None => continue,
+        };
+        let label = label
+            .parse::<f64>()
+            .map_err(|_| svmlight_error(line_nb, "invalid label", label))?;
+        labels.push(label);
+        let has_qid = tokens
+            .peek()
+            .map_or(false, |token| token.starts_with("qid:"));
+        if has_qid {
+            let token = tokens.next().unwrap();
+            let qid = token["qid:".len()..]
+                .parse::<u64>()
+                .map_err(|_| svmlight_error(line_nb, "invalid qid", token))?;
+            query_ids.push(qid);
+        }
+        if !query_ids.is_empty() && query_ids.len() != labels.len() {
+            let reason = "qid missing from some samples";
+            return Err(svmlight_error(line_nb, reason, content.trim()));
+        }
+
+        features.clear();
+        for token in tokens {
+            let error = |reason| svmlight_error(line_nb, reason, token);
+            let mut fields = token.splitn(2, ':');
+            let index = fields
+                .next()
+                .and_then(|index| index.parse::<usize>().ok())
+                .ok_or_else(|| error("invalid feature index"))?;
+            let value = fields
+                .next()
+                .and_then(parse_number)
+                .ok_or_else(|| error("invalid feature value"))?;
+            let index = match base {
+                IndexBase::One => index.checked_sub(1).ok_or_else(|| {
+                    error("feature index 0 in one-based file")
+                })?,
+                _ => index,
+            };
+            // the number of columns is one past the largest index
+            if index.checked_add(1).is_none() {
+                return Err(error("feature index too large"));
+            }
+            has_zero_index |= index == 0;
+            features.push((index, value));
+        }
+        features.sort_unstable_by_key(|&(index, _)| index);
+        if features.windows(2).any(|w| w[0].0 == w[1].0) {
+            let reason = "feature repeated in a sample";
+            return Err(svmlight_error(line_nb, reason, content.trim()));
+        }
+        indices.extend(features.iter().map(|&(index, _)| index));
+        data.extend(features.iter().map(|&(_, value)| value));
+        let nnz = I::try_from_usize(indices.len()).ok_or_else(|| {
+            let reason = "too many features for the index type";
+            svmlight_error(line_nb, reason, content.trim())
+        })?;
+        indptr.push(nnz);
+    }
+
+    if base == IndexBase::Auto && !has_zero_index {
+        indices.iter_mut().for_each(|index| *index -= 1);
+    }
+    let cols = indices.iter().max().map_or(0, |&max| max + 1);
+    let indices = indices
+        .into_iter()
+        .map(I::try_from_usize)
+        .collect::<Option<Vec<I>>>()
+        .ok_or_else(|| {
+            let reason = "too many features for the index type";
+            svmlight_error(line_nb, reason, &cols.to_string())
+        })?;
+    let matrix = CsMatI::new_trusted(
+        crate::CSR,
+        (labels.len(), cols),
+        indptr,
+        indices,
+        data,
+    );
+    let query_ids = if query_ids.is_empty() {
+        None
+    } else {
+        Some(query_ids)
+    };
+    Ok(SvmLightData {
+        matrix,
+        labels,
+        query_ids,
+    })
+}
+
+/// Write a dataset into the SVMLight format.
+///
+/// Each row of the matrix is written as a sample, its stored entries
+/// being written as features. Query identifiers are written when present.
+///
+/// # Panics
+///
+/// - if the number of labels or of query identifiers does not match the
+///   number of rows of the matrix.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sprs::io::svmlight::{write_svmlight, IndexBase, SvmLightData};
+/// use sprs::CsMat;
+/// let data = SvmLightData {
+///     matrix: CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 1], vec![1, 2, 3]),
+///     labels: vec![1., -1.],
+///     query_ids: None,
+/// };
+/// write_svmlight("/tmp/data.svm", &data, IndexBase::One).unwrap();
+/// ```
+pub fn write_svmlight<N, I, P>(
+    path: P,
+    data: &SvmLightData<N, I>,
+    base: IndexBase,
+) -> Result<(), io::Error>
+where
+    N: fmt::Display + Clone + Default,
+    I: SpIndex,
+    P: AsRef<Path>,
+{
+    let f = File::create(path)?;
+    let mut writer = io::BufWriter::new(f);
+    write_svmlight_to_bufwrite(&mut writer, data, base)?;
+    writer.flush()
+}
+
+/// Write a dataset into the SVMLight format, using any `io::Write` as
+/// output.
+///
+/// See `write_svmlight` for more details.
+pub fn write_svmlight_to_bufwrite<N, I, W>(
+    writer: &mut W,
+    data: &SvmLightData<N, I>,
+    base: IndexBase,
+) -> Result<(), io::Error>
+where
+    N: fmt::Display + Clone + Default,
+    I: SpIndex,
+    W: Write + ?Sized,
+{
+    let rows = data.matrix.rows();
+    assert_eq!(data.labels.len(), rows, "one label per sample is required");
+    if let Some(ref query_ids) = data.query_ids {
+        assert_eq!(query_ids.len(), rows, "one qid per sample is required");
+    }
+    let offset = match base {
+        IndexBase::Zero => 0,
+        IndexBase::One | IndexBase::Auto => 1,
+    };
+    let converted;
+    let csr = if data.matrix.is_csr() {
+        data.matrix.view()
+    } else {
+        converted = data.matrix.to_csr();
+        converted.view()
+    };
+    for (row, vec) in csr.outer_iterator().enumerate() {
+        write!(writer, "{}", data.labels[row])?;
+        if let Some(ref query_ids) = data.query_ids {
+            write!(writer, " qid:{}", query_ids[row])?;
+        }
+        for (col, val) in vec.iter() {
+            write!(writer, " {}:{}", col + offset, val)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        read_svmlight, read_svmlight_from_bufread, write_svmlight,
+        write_svmlight_to_bufwrite, IndexBase, SvmLightData,
+    };
+    use crate::io::{IoError, LineError};
+    use crate::sparse::CsMat;
+    use tempdir::TempDir;
+
+    #[test]
+    fn read_simple_svmlight() {
+        let path = "data/svmlight/simple.svm";
+        let data =
+            read_svmlight::<f64, usize, _>(path, IndexBase::One).unwrap();
+        let expected = CsMat::new(
+            (4, 5),
+            vec![0, 2, 3, 3, 6],
+            vec![0, 3, 1, 0, 2, 4],
+            vec![0.5, -2., 1.5, 1., 3., 1e-3],
+        );
+        assert_eq!(data.matrix, expected);
+        assert_eq!(data.labels, vec![1., -1., 1., 2.5]);
+        assert_eq!(data.query_ids, None);
+
+        // one-based indices are detected
+        let auto = read_svmlight::<f64, u32, _>(path, IndexBase::Auto).unwrap();
+        assert_eq!(auto.matrix.indices(), &[0, 3, 1, 0, 2, 4]);
+        let zero = read_svmlight::<f64, u32, _>(path, IndexBase::Zero).unwrap();
+        assert_eq!(zero.matrix.indices(), &[1, 4, 2, 1, 3, 5]);
+        assert_eq!(zero.matrix.cols(), 6);
+    }
+
+    #[test]
+    fn read_query_ids() {
+        let text = "3 qid:1 0:1 2:2\n1 qid:1 1:1\n# query 2\n2 qid:2 2:4\n";
+        let data = read_svmlight_from_bufread::<i32, usize, _>(
+            text.as_bytes(),
+            IndexBase::Auto,
+        )
+        .unwrap();
+        assert_eq!(data.query_ids, Some(vec![1, 1, 2]));
+        assert_eq!(data.matrix.indices(), &[0, 2, 1, 2]);
+        assert_eq!(data.matrix.data(), &[1, 2, 1, 4]);
+    }
+
+    #[test]
+    fn read_bad_files() {
+        let read = |text: &str| {
+            read_svmlight_from_bufread::<f64, usize, _>(
+                text.as_bytes(),
+                IndexBase::One,
+            )
+            .unwrap_err()
+        };
+        let error = |line, reason, token: &str| {
+            IoError::BadSvmLightFile(LineError {
+                line,
+                token: Some(token.to_string()),
+                reason,
+            })
+        };
+        assert_eq!(read("1 1:1\nfoo 2:1\n"), error(2, "invalid label", "foo"));
+        assert_eq!(
+            read("1 1:1 0:2\n"),
+            error(1, "feature index 0 in one-based file", "0:2")
+        );
+        assert_eq!(
+            read("1 1:1 2:x\n"),
+            error(1, "invalid feature value", "2:x")
+        );
+        assert_eq!(
+            read("1 1:1 2:1 1:3\n"),
+            error(1, "feature repeated in a sample", "1 1:1 2:1 1:3")
+        );
+        assert_eq!(
+            read("1 qid:1 1:1\n2 1:1\n"),
+            error(2, "qid missing from some samples", "2 1:1")
+        );
+        let res = read_svmlight_from_bufread::<i8, usize, _>(
+            "1 1:300".as_bytes(),
+            IndexBase::One,
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            error(1, "invalid feature value", "1:300")
+        );
+        let token = format!("{}:1", std::usize::MAX);
+        let res = read_svmlight_from_bufread::<f64, usize, _>(
+            format!("1 {}", token).as_bytes(),
+            IndexBase::Zero,
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            error(1, "feature index too large", &token)
+        );
+    }
+
+    #[test]
+    fn write_read_svmlight() {
+        let tmp_dir = TempDir::new("sprs-tmp").unwrap();
+        let path = tmp_dir.path().join("data.svm");
+        let data = read_svmlight::<f64, usize, _>(
+            "data/svmlight/simple.svm",
+            IndexBase::One,
+        )
+        .unwrap();
+        write_svmlight(&path, &data, IndexBase::One).unwrap();
+        let data2 = read_svmlight(&path, IndexBase::One).unwrap();
+        assert_eq!(data, data2);
+
+        // CSC matrices are written by rows
+        let data = SvmLightData {
+            matrix: CsMat::new_csc(
+                (2, 3),
+                vec![0, 1, 2, 3],
+                vec![1, 0, 1],
+                vec![1, 2, 3],
+            ),
+            labels: vec![0., 1.],
+            query_ids: Some(vec![7, 8]),
+        };
+        let mut buffer = Vec::new();
+        write_svmlight_to_bufwrite(&mut buffer, &data, IndexBase::Zero)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "0 qid:7 1:2\n1 qid:8 0:1 2:3\n"
+        );
+    }
+}
@@ -12,10 +12,19 @@ It features a sparse matrix type, [**`CsMat`**](struct.CsMatBase.html), and a sp
 - sparse vector/sparse vector addition, dot product.
 - sparse matrix/dense matrix addition, multiplication.
 - sparse triangular solves.
+- block sparse row, diagonal and sliced ELLPACK
+  ([SELL-C-σ](struct.SellMatI.html)) storage, for faster matrix-vector
+  products on suitable matrices.
 - powerful iteration over the sparse structure, enabling easy extension of the library.
 - matrix construction using the [triplet format](struct.TriMatBase.html),
-  vertical and horizontal stacking, block construction.
+  the [dictionary of keys format](struct.DokMatI.html), vertical and
+  horizontal stacking, block construction.
 - sparse cholesky solver in the separate crate `sprs-ldl`.
+- sparse [LU factorization](linalg/lu/index.html) with partial pivoting,
+  for general square systems.
+- preconditioned [iterative solvers](linalg/iterative/index.html): conjugate
+  gradient, BiCGSTAB and restarted GMRES, with
+  [incomplete LU](linalg/ilu/index.html) preconditioners.
 - fully generic integer type for the storage of indices, enabling compact
   representations.
 - planned interoperability with existing sparse solvers such as SuiteSparse.
@@ -89,15 +98,17 @@ pub use crate::indexing::SpIndex;
 
 pub use crate::sparse::{
     csmat::CsIter, csmat::OuterIterator, csmat::OuterIteratorMut,
-    csmat::OuterIteratorPerm, kronecker::kronecker_product, CsMat, CsMatBase,
-    CsMatI, CsMatVecView, CsMatView, CsMatViewI, CsMatViewMut, CsMatViewMutI,
-    CsStructure, CsStructureI, CsStructureView, CsStructureViewI, CsVec,
-    CsVecBase, CsVecI, CsVecView, CsVecViewI, CsVecViewMut, CsVecViewMutI,
-    SparseMat, TriMat, TriMatBase, TriMatI, TriMatIter, TriMatView,
-    TriMatViewI, TriMatViewMut, TriMatViewMutI,
+    csmat::OuterIteratorPerm, kronecker::kronecker_product, BsrMat, BsrMatI,
+    CsMat, CsMatBase, CsMatI, CsMatVecView, CsMatView, CsMatViewI,
+    CsMatViewMut, CsMatViewMutI, CsStructure, CsStructureI, CsStructureView,
+    CsStructureViewI, CsVec, CsVecBase, CsVecI, CsVecView, CsVecViewI,
+    CsVecViewMut, CsVecViewMutI, DiaMat, DokMat, DokMatI, SellMat, SellMatI,
+    SparseMat, SymCsMat, SymCsMatI, TriMat, TriMatBase, TriMatI, TriMatIter,
+    TriMatView, TriMatViewI, TriMatViewMut, TriMatViewMutI,
 };
 
-pub use crate::sparse::symmetric::is_symmetric;
+pub use crate::sparse::slicing::AxisSelection;
+pub use crate::sparse::symmetric::{is_symmetric, is_upper_triangular};
 
 pub use crate::sparse::permutation::{
     perm_is_valid, transform_mat_papt, PermOwned, PermOwnedI, PermView,
@@ -123,6 +134,7 @@ pub mod vec {
 }
 
 pub use crate::sparse::construct::{bmat, hstack, vstack};
+pub use crate::sparse::dia::diags;
 
 pub use crate::sparse::to_dense::assign_to_dense;
 
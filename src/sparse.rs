@@ -1,10 +1,11 @@
 use crate::array_backend::Array2;
 use crate::errors::SprsError;
 use crate::indexing::SpIndex;
+use std::collections::HashMap;
 use std::ops::Deref;
 
 #[cfg(feature = "serde")]
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
 pub use self::csmat::CompressedStorage;
 
@@ -81,7 +82,7 @@ pub use self::csmat::CompressedStorage;
 /// [`bmat`]: fn.bmat.html
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct CsMatBase<N, I, IptrStorage, IndStorage, DataStorage, Iptr = I>
 where
     I: SpIndex,
@@ -147,7 +148,7 @@ pub type CsStructure = CsStructureI<usize>;
 /// [`CsVecViewMutI`]: type.CsVecViewMutI.html
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct CsVecBase<IStorage, DStorage> {
     dim: usize,
     indices: IStorage,
@@ -206,6 +207,7 @@ pub type CsVec<N> = CsVecI<N, usize>;
 /// [`TriMatViewI`]: type.TriMatViewI.html
 /// [`TriMatViewMutI`]: type.TriMatViewMutI.html
 #[derive(PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct TriMatBase<IStorage, DStorage> {
     rows: usize,
     cols: usize,
@@ -222,6 +224,163 @@ pub type TriMat<N> = TriMatI<N, usize>;
 pub type TriMatView<'a, N> = TriMatViewI<'a, N, usize>;
 pub type TriMatViewMut<'a, N> = TriMatViewMutI<'a, N, usize>;
 
+/// Sparse matrix in the block compressed sparse row format.
+///
+/// A `BsrMatI` partitions a matrix into dense blocks of a fixed shape
+/// `(r, c)`, and stores the non-zero blocks with a compressed sparse row
+/// structure over the grid of blocks: the block row `i` holds the blocks
+/// whose block column indices are `indices[indptr[i]..indptr[i + 1]]`,
+/// sorted in increasing order. The values of the `k`-th block are stored in
+/// row major order in `data[k * r * c..(k + 1) * r * c]`.
+///
+/// This format is well suited to matrices arising from discretizations with
+/// several unknowns per node, such as finite elements, as it stores a single
+/// index per block instead of one per non-zero entry, and enables efficient
+/// products with dense vectors and matrices.
+///
+/// A `BsrMatI` can be created from a compressed matrix with
+/// [`CsMatBase::to_bsr`], and converted back with [`to_csr`].
+///
+/// [`CsMatBase::to_bsr`]: struct.CsMatBase.html#method.to_bsr
+/// [`to_csr`]: struct.BsrMatI.html#method.to_csr
+///
+/// The type alias [`BsrMat`] uses `usize` as the index type.
+///
+/// [`BsrMat`]: type.BsrMat.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BsrMatI<N, I> {
+    nrows: usize,
+    ncols: usize,
+    block_shape: (usize, usize),
+    indptr: Vec<I>,
+    indices: Vec<I>,
+    data: Vec<N>,
+}
+
+pub type BsrMat<N> = BsrMatI<N, usize>;
+
+/// Symmetric sparse matrix, storing only its upper triangle.
+///
+/// A `SymCsMatI` wraps a compressed matrix whose non-zero entries all lie on
+/// or above the diagonal, and behaves as the symmetric matrix having this
+/// upper triangle, which halves the memory used by the off-diagonal entries.
+/// It can be created from a full symmetric matrix with
+/// [`from_full`], and converted back with [`to_full`].
+///
+/// [`from_full`]: struct.SymCsMatI.html#method.from_full
+/// [`to_full`]: struct.SymCsMatI.html#method.to_full
+///
+/// The type alias [`SymCsMat`] uses `usize` as the index type.
+///
+/// [`SymCsMat`]: type.SymCsMat.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymCsMatI<N, I, Iptr = I>
+where
+    I: SpIndex,
+    Iptr: SpIndex,
+{
+    upper: CsMatI<N, I, Iptr>,
+}
+
+pub type SymCsMat<N> = SymCsMatI<N, usize>;
+
+/// Sparse matrix in the sliced ELLPACK format, with row sorting
+/// (SELL-C-σ).
+///
+/// The rows of a `SellMatI` are grouped into chunks of `C` consecutive
+/// rows, each chunk being stored as a dense column major array of `C` rows
+/// whose width is the largest number of non-zeros of its rows. Shorter rows
+/// are padded with zeros. Before being grouped into chunks, the rows are
+/// sorted by decreasing number of non-zeros inside windows of `σ`
+/// consecutive rows, which reduces the padding.
+///
+/// This layout enables a matrix-vector product whose inner loop processes
+/// `C` rows with contiguous memory accesses, which suits SIMD units, and
+/// whose chunks can be processed in parallel. It is best suited to matrices
+/// whose rows have similar numbers of non-zeros.
+///
+/// A `SellMatI` can be created from a compressed matrix with
+/// [`CsMatBase::to_sell`], and converted back with [`to_csr`].
+///
+/// [`CsMatBase::to_sell`]: struct.CsMatBase.html#method.to_sell
+/// [`to_csr`]: struct.SellMatI.html#method.to_csr
+///
+/// The type alias [`SellMat`] uses `usize` as the index type.
+///
+/// [`SellMat`]: type.SellMat.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SellMatI<N, I> {
+    nrows: usize,
+    ncols: usize,
+    chunk_size: usize,
+    sigma: usize,
+    /// The original row of each slot of the chunks
+    perm: Vec<I>,
+    /// The number of non-zeros of each slot, padding slots being empty
+    slot_lens: Vec<I>,
+    /// The start of each chunk in `indices` and `data`
+    chunk_ptr: Vec<usize>,
+    indices: Vec<I>,
+    data: Vec<N>,
+}
+
+pub type SellMat<N> = SellMatI<N, usize>;
+
+/// Sparse matrix in the diagonal format.
+///
+/// A `DiaMat` stores a few diagonals of a matrix, each identified by its
+/// offset from the main diagonal: the diagonal of offset `k` holds the
+/// entries at locations `(i, i + k)`, so positive offsets are above the
+/// main diagonal and negative offsets below. Each diagonal is stored as a
+/// dense array of `cols` values, the entry at `(i, j)` being stored at
+/// position `j` of its diagonal, as in SciPy's `dia_matrix`. The values
+/// whose column has no row on the diagonal are ignored.
+///
+/// This format is well suited to banded matrices, such as finite
+/// difference stencils on regular grids, and enables fast products with
+/// dense vectors. It can be converted to the compressed format using
+/// [`to_csr`]. The [`diags`] function builds a compressed matrix directly
+/// from a list of diagonals.
+///
+/// [`to_csr`]: struct.DiaMat.html#method.to_csr
+/// [`diags`]: fn.diags.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiaMat<N> {
+    nrows: usize,
+    ncols: usize,
+    offsets: Vec<isize>,
+    data: Vec<N>,
+}
+
+/// Sparse matrix in the dictionary of keys format.
+///
+/// A `DokMatI` stores its non-zero entries in a hash map indexed by their
+/// `(row, col)` location, so that entries can be read, set, removed or
+/// accumulated in constant time, in any order. Contrary to the [triplet
+/// format](struct.TriMatBase.html), setting an entry overwrites its previous
+/// value instead of adding a duplicate entry.
+///
+/// This format is useful for building a matrix by random access updates, but
+/// cannot be used for arithmetic operations. A matrix in the dictionary of
+/// keys format can be converted to the compressed format using the methods
+/// [`to_csr`] and [`to_csc`], or to the triplet format using [`to_trimat`].
+///
+/// [`to_csr`]: struct.DokMatI.html#method.to_csr
+/// [`to_csc`]: struct.DokMatI.html#method.to_csc
+/// [`to_trimat`]: struct.DokMatI.html#method.to_trimat
+///
+/// The type alias [`DokMat`] uses `usize` as the index type.
+///
+/// [`DokMat`]: type.DokMat.html
+#[derive(Clone, Debug)]
+pub struct DokMatI<N, I> {
+    rows: usize,
+    cols: usize,
+    entries: HashMap<(I, I), N>,
+}
+
+pub type DokMat<N> = DokMatI<N, usize>;
+
 /// An iterator over elements of a sparse matrix, in the triplet format
 ///
 /// The dataypes RI, CI, and DI are iterators yielding the row, column and
@@ -242,12 +401,13 @@ pub struct TriMatIter<RI, CI, DI> {
 
 mod prelude {
     pub use super::{
-        CsMat, CsMatBase, CsMatI, CsMatVecView, CsMatVecView_, CsMatView,
-        CsMatViewI, CsMatViewMut, CsMatViewMutI, CsStructure, CsStructureI,
-        CsStructureView, CsStructureViewI, CsVec, CsVecBase, CsVecI, CsVecView,
-        CsVecViewI, CsVecViewMut, CsVecViewMutI, SparseMat, TriMat, TriMatBase,
-        TriMatI, TriMatIter, TriMatView, TriMatViewI, TriMatViewMut,
-        TriMatViewMutI,
+        BsrMat, BsrMatI, CsMat, CsMatBase, CsMatI, CsMatVecView, CsMatVecView_,
+        CsMatView, CsMatViewI, CsMatViewMut, CsMatViewMutI, CsStructure,
+        CsStructureI, CsStructureView, CsStructureViewI, CsVec, CsVecBase,
+        CsVecI, CsVecView, CsVecViewI, CsVecViewMut, CsVecViewMutI, DiaMat,
+        DokMat, DokMatI, SellMat, SellMatI, SparseMat, SymCsMat, SymCsMatI,
+        TriMat, TriMatBase, TriMatI, TriMatIter, TriMatView, TriMatViewI,
+        TriMatViewMut, TriMatViewMutI,
     };
 }
 
@@ -407,13 +567,21 @@ pub(crate) mod utils {
 }
 
 pub mod binop;
+pub mod bsr;
 pub mod compressed;
 pub mod construct;
 pub mod csmat;
+pub mod dia;
+pub mod dok;
 pub mod kronecker;
 pub mod linalg;
 pub mod permutation;
 pub mod prod;
+pub mod reductions;
+pub mod sell;
+#[cfg(feature = "serde")]
+mod serde_traits;
+pub mod slicing;
 pub mod smmp;
 pub mod special_mats;
 pub mod symmetric;
@@ -0,0 +1,576 @@
+//! Block compressed sparse row matrices
+//!
+//! A block sparse matrix stores dense blocks of a fixed shape on a
+//! compressed sparse row structure. Compared to the element wise compressed
+//! storage, a single index is stored per block, and products can operate on
+//! contiguous dense blocks.
+
+use std::ops::{Deref, Mul};
+
+use ndarray::linalg::Dot;
+use ndarray::{Array, ArrayBase, ArrayView2, ArrayViewMut2, Ix1, Ix2};
+use num_traits::Num;
+
+use crate::errors::SprsError;
+use crate::indexing::SpIndex;
+use crate::sparse::prelude::*;
+use crate::sparse::utils;
+use crate::Shape;
+
+/// Whether the matrix shape is a multiple of the block shape, which must not
+/// have a zero dimension
+fn divides(block_shape: Shape, shape: Shape) -> bool {
+    shape.0.checked_rem(block_shape.0) == Some(0)
+        && shape.1.checked_rem(block_shape.1) == Some(0)
+}
+
+impl<N, I: SpIndex> BsrMatI<N, I> {
+    /// Try to create a block sparse matrix of shape `shape`, made of blocks
+    /// of shape `block_shape`, from its raw components.
+    ///
+    /// `indptr` and `indices` describe the compressed sparse row structure
+    /// of the non-zero blocks, whose block column indices should be sorted
+    /// in each block row. `data` holds the values of the blocks, in row
+    /// major order.
+    ///
+    /// # Errors
+    ///
+    /// - if the block shape has a zero dimension, or does not divide the
+    ///   shape of the matrix,
+    /// - if the block structure is invalid, see `CsMatI::try_new`, except
+    ///   that unsorted indices are an error,
+    /// - if `data` does not hold exactly one block per index.
+    pub fn try_new(
+        shape: Shape,
+        block_shape: Shape,
+        indptr: Vec<I>,
+        indices: Vec<I>,
+        data: Vec<N>,
+    ) -> Result<Self, SprsError> {
+        if !divides(block_shape, shape) {
+            return Err(SprsError::IllegalArguments(
+                "Block shape does not divide the matrix shape",
+            ));
+        }
+        let (r, c) = block_shape;
+        utils::check_compressed_structure(
+            shape.1 / c,
+            shape.0 / r,
+            &indptr,
+            &indices,
+        )?;
+        if data.len() != indices.len() * r * c {
+            return Err(SprsError::IllegalArguments(
+                "Data length does not match the number of blocks",
+            ));
+        }
+        Ok(Self {
+            nrows: shape.0,
+            ncols: shape.1,
+            block_shape,
+            indptr,
+            indices,
+            data,
+        })
+    }
+
+    /// Create a block sparse matrix from its raw components.
+    ///
+    /// # Panics
+    ///
+    /// - if the components do not describe a valid matrix, see `try_new`.
+    pub fn new(
+        shape: Shape,
+        block_shape: Shape,
+        indptr: Vec<I>,
+        indices: Vec<I>,
+        data: Vec<N>,
+    ) -> Self {
+        Self::try_new(shape, block_shape, indptr, indices, data).unwrap()
+    }
+
+    /// The number of rows of the matrix
+    pub fn rows(&self) -> usize {
+        self.nrows
+    }
+
+    /// The number of cols of the matrix
+    pub fn cols(&self) -> usize {
+        self.ncols
+    }
+
+    /// The shape of the matrix, as a `(rows, cols)` tuple
+    pub fn shape(&self) -> Shape {
+        (self.nrows, self.ncols)
+    }
+
+    /// The shape of the blocks of the matrix
+    pub fn block_shape(&self) -> Shape {
+        self.block_shape
+    }
+
+    /// The number of block rows and block columns of the matrix
+    pub fn block_dims(&self) -> Shape {
+        let (r, c) = self.block_shape;
+        (self.nrows / r, self.ncols / c)
+    }
+
+    /// The number of non-zero blocks
+    pub fn nnz_blocks(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// The number of stored values, including the explicit zeros of the
+    /// blocks
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The block index pointer, giving the range of the blocks of each
+    /// block row
+    pub fn indptr(&self) -> &[I] {
+        &self.indptr[..]
+    }
+
+    /// The block column indices of the non-zero blocks
+    pub fn indices(&self) -> &[I] {
+        &self.indices[..]
+    }
+
+    /// The values of the non-zero blocks, each block being stored in row
+    /// major order
+    pub fn data(&self) -> &[N] {
+        &self.data[..]
+    }
+
+    /// The position of the block at block row `i` and block column `j`
+    /// in the storage, if it is a non-zero block
+    fn block_position(&self, i: usize, j: usize) -> Option<usize> {
+        if i >= self.block_dims().0 {
+            return None;
+        }
+        let start = self.indptr[i].index();
+        let stop = self.indptr[i + 1].index();
+        self.indices[start..stop]
+            .binary_search_by(|&col| col.index().cmp(&j))
+            .ok()
+            .map(|pos| start + pos)
+    }
+
+    /// Access the block located at block row `i` and block column `j`, if
+    /// it is a non-zero block
+    pub fn block(&self, i: usize, j: usize) -> Option<ArrayView2<'_, N>> {
+        let k = self.block_position(i, j)?;
+        let (r, c) = self.block_shape;
+        let block = &self.data[k * r * c..(k + 1) * r * c];
+        Some(ArrayView2::from_shape((r, c), block).unwrap())
+    }
+
+    /// Mutable access to the block located at block row `i` and block
+    /// column `j`, if it is a non-zero block
+    pub fn block_mut(
+        &mut self,
+        i: usize,
+        j: usize,
+    ) -> Option<ArrayViewMut2<'_, N>> {
+        let k = self.block_position(i, j)?;
+        let (r, c) = self.block_shape;
+        let block = &mut self.data[k * r * c..(k + 1) * r * c];
+        Some(ArrayViewMut2::from_shape((r, c), block).unwrap())
+    }
+
+    /// Access the element located at `row` and `col`, if it belongs to a
+    /// non-zero block
+    pub fn get(&self, row: usize, col: usize) -> Option<&N> {
+        let (r, c) = self.block_shape;
+        if col >= self.ncols {
+            return None;
+        }
+        let k = self.block_position(row / r, col / c)?;
+        self.data.get(k * r * c + (row % r) * c + col % c)
+    }
+
+    /// Create a CSR matrix from this matrix.
+    ///
+    /// All the values of the non-zero blocks are stored, including their
+    /// explicit zeros.
+    pub fn to_csr(&self) -> CsMatI<N, I>
+    where
+        N: Clone,
+    {
+        let (r, c) = self.block_shape;
+        let mut indptr = Vec::with_capacity(self.nrows + 1);
+        let mut indices = Vec::with_capacity(self.nnz());
+        let mut data = Vec::with_capacity(self.nnz());
+        indptr.push(I::zero());
+        for window in self.indptr.windows(2) {
+            let blocks = window[0].index()..window[1].index();
+            for row in 0..r {
+                for k in blocks.clone() {
+                    let col = self.indices[k].index() * c;
+                    let start = k * r * c + row * c;
+                    indices.extend((col..col + c).map(I::from_usize));
+                    data.extend_from_slice(&self.data[start..start + c]);
+                }
+                indptr.push(I::from_usize(indices.len()));
+            }
+        }
+        CsMatI::new_trusted(crate::CSR, self.shape(), indptr, indices, data)
+    }
+
+    /// Create the transpose of this matrix, whose blocks are the transposes
+    /// of the blocks of this matrix
+    pub fn transpose(&self) -> Self
+    where
+        N: Clone,
+    {
+        let (r, c) = self.block_shape;
+        let (block_rows, block_cols) = self.block_dims();
+        let nnzb = self.nnz_blocks();
+        // count the blocks of each block column, then place them
+        let mut indptr = vec![0; block_cols + 1];
+        for &j in &self.indices {
+            indptr[j.index() + 1] += 1;
+        }
+        for j in 0..block_cols {
+            indptr[j + 1] += indptr[j];
+        }
+        let mut next = indptr.clone();
+        let mut indices = vec![I::zero(); nnzb];
+        let mut positions = vec![0; nnzb];
+        for i in 0..block_rows {
+            for k in self.indptr[i].index()..self.indptr[i + 1].index() {
+                let j = self.indices[k].index();
+                indices[next[j]] = I::from_usize(i);
+                positions[next[j]] = k;
+                next[j] += 1;
+            }
+        }
+        let mut data = Vec::with_capacity(self.nnz());
+        for k in positions {
+            let block = &self.data[k * r * c..(k + 1) * r * c];
+            for col in 0..c {
+                data.extend(block.iter().skip(col).step_by(c).cloned());
+            }
+        }
+        Self {
+            nrows: self.ncols,
+            ncols: self.nrows,
+            block_shape: (c, r),
+            indptr: indptr.into_iter().map(I::from_usize).collect(),
+            indices,
+            data,
+        }
+    }
+
+    /// Iterate over the non-zero blocks with their block row and block
+    /// column indices
+    fn blocks(&self) -> impl Iterator<Item = (usize, usize, &[N])> {
+        let (r, c) = self.block_shape;
+        self.indptr
+            .windows(2)
+            .enumerate()
+            .flat_map(move |(i, window)| {
+                (window[0].index()..window[1].index()).map(move |k| {
+                    let block = &self.data[k * r * c..(k + 1) * r * c];
+                    (i, self.indices[k].index(), block)
+                })
+            })
+    }
+}
+
+impl<N, I, IpS, IS, DS, Iptr> CsMatBase<N, I, IpS, IS, DS, Iptr>
+where
+    I: SpIndex,
+    Iptr: SpIndex,
+    IpS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+{
+    /// Create a block sparse matrix with blocks of shape `block_shape`
+    /// from this matrix.
+    ///
+    /// Every block holding a non-zero entry of this matrix is stored, its
+    /// other values being explicit zeros.
+    ///
+    /// # Panics
+    ///
+    /// - if the block shape has a zero dimension, or does not divide the
+    ///   shape of the matrix.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mat = CsMat::new(
+    ///     (4, 4),
+    ///     vec![0, 2, 3, 4, 5],
+    ///     vec![0, 1, 1, 3, 2],
+    ///     vec![1., 2., 3., 4., 5.],
+    /// );
+    /// let bsr = mat.to_bsr((2, 2));
+    /// assert_eq!(bsr.nnz_blocks(), 2);
+    /// assert_eq!(bsr.data(), &[1., 2., 0., 3., 0., 4., 5., 0.]);
+    /// assert_eq!(bsr.to_csr().to_dense(), mat.to_dense());
+    /// ```
+    pub fn to_bsr(&self, block_shape: Shape) -> BsrMatI<N, I>
+    where
+        N: Copy + Num,
+    {
+        assert!(
+            divides(block_shape, self.shape()),
+            "Block shape does not divide the matrix shape"
+        );
+        let (r, c) = block_shape;
+        let converted;
+        let csr = if self.is_csr() {
+            self.view()
+        } else {
+            converted = self.to_other_storage_with(N::zero());
+            converted.view()
+        };
+        let mut indptr = Vec::with_capacity(self.rows() / r + 1);
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        indptr.push(I::zero());
+        let mut block_cols = Vec::new();
+        for i in 0..self.rows() / r {
+            let rows = i * r..(i + 1) * r;
+            block_cols.clear();
+            for row in rows.clone() {
+                let vec = csr.outer_view(row).unwrap();
+                block_cols.extend(vec.indices().iter().map(|j| j.index() / c));
+            }
+            block_cols.sort_unstable();
+            block_cols.dedup();
+            let start = indices.len();
+            indices.extend(block_cols.iter().map(|&j| I::from_usize(j)));
+            data.resize(indices.len() * r * c, N::zero());
+            for (row_in_block, row) in rows.enumerate() {
+                for (col, &val) in csr.outer_view(row).unwrap().iter() {
+                    let pos = block_cols.binary_search(&(col / c)).unwrap();
+                    let block = (start + pos) * r * c;
+                    data[block + row_in_block * c + col % c] = val;
+                }
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        BsrMatI {
+            nrows: self.rows(),
+            ncols: self.cols(),
+            block_shape,
+            indptr,
+            indices,
+            data,
+        }
+    }
+}
+
+impl<N, I: SpIndex> SparseMat for BsrMatI<N, I> {
+    fn rows(&self) -> usize {
+        self.rows()
+    }
+
+    fn cols(&self) -> usize {
+        self.cols()
+    }
+
+    fn nnz(&self) -> usize {
+        self.nnz()
+    }
+}
+
+impl<'a, 'b, N, I, DS2> Mul<&'b ArrayBase<DS2, Ix1>> for &'a BsrMatI<N, I>
+where
+    N: 'a + Copy + Num,
+    I: 'a + SpIndex,
+    DS2: 'b + ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn mul(self, rhs: &'b ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        assert_eq!(self.cols(), rhs.len(), "Dimension mismatch");
+        let (r, c) = self.block_shape;
+        let mut res = Array::zeros(self.rows());
+        for (i, j, block) in self.blocks() {
+            let x = rhs.slice(ndarray::s![j * c..(j + 1) * c]);
+            for (row, block_row) in block.chunks_exact(c).enumerate() {
+                let acc = block_row
+                    .iter()
+                    .zip(x.iter())
+                    .fold(N::zero(), |acc, (&a, &x)| acc + a * x);
+                let y = &mut res[i * r + row];
+                *y = *y + acc;
+            }
+        }
+        res
+    }
+}
+
+impl<N, I, DS2> Dot<ArrayBase<DS2, Ix1>> for BsrMatI<N, I>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    DS2: ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn dot(&self, rhs: &ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        Mul::mul(self, rhs)
+    }
+}
+
+impl<'a, 'b, N, I, DS2> Mul<&'b ArrayBase<DS2, Ix2>> for &'a BsrMatI<N, I>
+where
+    N: 'a + Copy + Num,
+    I: 'a + SpIndex,
+    DS2: 'b + ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix2>;
+
+    fn mul(self, rhs: &'b ArrayBase<DS2, Ix2>) -> Array<N, Ix2> {
+        assert_eq!(self.cols(), rhs.nrows(), "Dimension mismatch");
+        let (r, c) = self.block_shape;
+        let mut res = Array::zeros((self.rows(), rhs.ncols()));
+        for (i, j, block) in self.blocks() {
+            for (row, block_row) in block.chunks_exact(c).enumerate() {
+                let mut res_row = res.row_mut(i * r + row);
+                for (col, &a) in block_row.iter().enumerate() {
+                    let rhs_row = rhs.row(j * c + col);
+                    res_row.zip_mut_with(&rhs_row, |y, &x| *y = *y + a * x);
+                }
+            }
+        }
+        res
+    }
+}
+
+impl<N, I, DS2> Dot<ArrayBase<DS2, Ix2>> for BsrMatI<N, I>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    DS2: ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix2>;
+
+    fn dot(&self, rhs: &ArrayBase<DS2, Ix2>) -> Array<N, Ix2> {
+        Mul::mul(self, rhs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::errors::SprsError;
+    use crate::sparse::{BsrMat, BsrMatI, CsMat, CsMatI};
+    use ndarray::{arr1, arr2, Array};
+
+    fn block_matrix() -> CsMat<f64> {
+        // two block rows of 2x3 blocks, the (1, 0) block being empty
+        CsMat::new(
+            (4, 6),
+            vec![0, 3, 5, 7, 8],
+            vec![0, 2, 4, 1, 3, 4, 5, 5],
+            vec![1., 2., 3., 4., 5., 6., 7., 8.],
+        )
+    }
+
+    #[test]
+    fn csr_roundtrip() {
+        let mat = block_matrix();
+        let bsr = mat.to_bsr((2, 3));
+        assert_eq!(bsr.block_dims(), (2, 2));
+        assert_eq!(bsr.indptr(), &[0, 2, 3]);
+        assert_eq!(bsr.indices(), &[0, 1, 1]);
+        assert_eq!(bsr.nnz(), 18);
+        assert_eq!(bsr.to_csr().to_dense(), mat.to_dense());
+        assert_eq!(mat.to_csc().to_bsr((2, 3)), bsr);
+        assert_eq!(bsr.get(1, 3), Some(&5.));
+        assert_eq!(bsr.get(1, 4), Some(&0.));
+        assert_eq!(bsr.get(2, 0), None);
+        assert_eq!(bsr.get(4, 0), None);
+
+        // a block shape of 1x1 gives back the same structure
+        let bsr: BsrMatI<f64, u32> =
+            CsMatI::new((2, 2), vec![0, 1, 2], vec![1, 0], vec![1., 2.])
+                .to_bsr((1, 1));
+        assert_eq!(bsr.to_csr().indices(), &[1, 0]);
+    }
+
+    #[test]
+    fn blocks() {
+        let mut bsr = block_matrix().to_bsr((2, 3));
+        assert_eq!(
+            bsr.block(0, 1).unwrap(),
+            arr2(&[[0., 3., 0.], [5., 0., 0.]])
+        );
+        assert_eq!(
+            bsr.block(1, 1).unwrap(),
+            arr2(&[[0., 6., 7.], [0., 0., 8.]])
+        );
+        assert!(bsr.block(1, 0).is_none());
+        assert!(bsr.block(2, 0).is_none());
+        bsr.block_mut(1, 1).unwrap()[[1, 0]] = 9.;
+        assert_eq!(bsr.get(3, 3), Some(&9.));
+    }
+
+    #[test]
+    fn products() {
+        let mat = block_matrix();
+        let bsr = mat.to_bsr((2, 3));
+        let x = arr1(&[1., -2., 3., 0.5, 2., -1.]);
+        assert_eq!(&bsr * &x, &mat * &x);
+        let rhs = Array::from_shape_fn((6, 3), |(i, j)| (i * 3 + j) as f64);
+        assert_eq!(&bsr * &rhs, &mat * &rhs);
+        let bsr = mat.to_bsr((1, 2));
+        assert_eq!(&bsr * &rhs, &mat * &rhs);
+    }
+
+    #[test]
+    fn transpose() {
+        let mat = block_matrix();
+        let bsr = mat.to_bsr((2, 3));
+        let transposed = bsr.transpose();
+        assert_eq!(transposed.block_shape(), (3, 2));
+        assert_eq!(transposed.indptr(), &[0, 1, 3]);
+        assert_eq!(transposed.indices(), &[0, 0, 1]);
+        assert_eq!(
+            transposed.to_csr().to_dense(),
+            mat.transpose_view().to_dense()
+        );
+        assert_eq!(transposed.transpose(), bsr);
+    }
+
+    #[test]
+    fn invalid_components() {
+        let res: Result<BsrMat<f64>, _> =
+            BsrMat::try_new((4, 4), (3, 2), vec![0, 0], vec![], vec![]);
+        assert!(res.is_err());
+        let res: Result<BsrMat<f64>, _> =
+            BsrMat::try_new((4, 4), (2, 2), vec![0, 1, 1], vec![0], vec![1.]);
+        assert_eq!(
+            res.unwrap_err(),
+            SprsError::IllegalArguments(
+                "Data length does not match the number of blocks"
+            )
+        );
+        let res: Result<BsrMat<f64>, _> = BsrMat::try_new(
+            (4, 4),
+            (2, 2),
+            vec![0, 2, 2],
+            vec![1, 0],
+            vec![0.; 8],
+        );
+        assert_eq!(res.unwrap_err(), SprsError::NonSortedIndices);
+        let bsr = BsrMat::new(
+            (2, 4),
+            (2, 2),
+            vec![0, 1],
+            vec![1],
+            vec![1., 2., 3., 4.],
+        );
+        assert_eq!(
+            bsr.to_csr().to_dense(),
+            arr2(&[[0., 0., 1., 2.], [0., 0., 3., 4.]])
+        );
+    }
+}
@@ -316,7 +316,7 @@ where
     IStorage: Deref<Target = [I]>,
     DStorage: Deref<Target = [N]>,
 {
-    fn new_checked(
+    pub(crate) fn new_checked(
         storage: CompressedStorage,
         shape: (usize, usize),
         indptr: IptrStorage,
@@ -1074,6 +1074,34 @@ where
         }
     }
 
+    /// Same as `to_other_storage`, for scalar types which do not implement
+    /// `Default`, `placeholder` being used to allocate the converted data.
+    pub(crate) fn to_other_storage_with(
+        &self,
+        placeholder: N,
+    ) -> CsMatI<N, I, Iptr>
+    where
+        N: Clone,
+    {
+        let mut indptr = vec![Iptr::zero(); self.inner_dims() + 1];
+        let mut indices = vec![I::zero(); self.nnz()];
+        let mut data = vec![placeholder; self.nnz()];
+        raw::convert_mat_storage(
+            self.view(),
+            &mut indptr,
+            &mut indices,
+            &mut data,
+        );
+        CsMatI {
+            storage: self.storage().other_storage(),
+            nrows: self.nrows,
+            ncols: self.ncols,
+            indptr,
+            indices,
+            data,
+        }
+    }
+
     /// Generate a one-hot matrix, compressing the inner dimension.
     ///
     /// Returns a matrix with the same size, the same CSR/CSC type,
@@ -1436,23 +1464,7 @@ where
     where
         N: Clone,
     {
-        let mut indptr = vec![Iptr::zero(); self.inner_dims() + 1];
-        let mut indices = vec![I::zero(); self.nnz()];
-        let mut data = vec![N::default(); self.nnz()];
-        raw::convert_mat_storage(
-            self.view(),
-            &mut indptr,
-            &mut indices,
-            &mut data,
-        );
-        CsMatI {
-            storage: self.storage().other_storage(),
-            nrows: self.nrows,
-            ncols: self.ncols,
-            indptr,
-            indices,
-            data,
-        }
+        self.to_other_storage_with(N::default())
     }
 
     /// Create a new CSC matrix equivalent to this one.
@@ -0,0 +1,376 @@
+//! Diagonal format matrices
+//!
+//! Banded matrices are described by a few diagonals and their offsets from
+//! the main diagonal. This module provides the `DiaMat` storage type, which
+//! stores such diagonals as dense arrays, and the `diags` function which
+//! builds a compressed matrix from a list of diagonals.
+
+use std::ops::{Mul, Range};
+
+use ndarray::linalg::Dot;
+use ndarray::{s, Array, ArrayBase, Ix1};
+use num_traits::Num;
+
+use crate::errors::SprsError;
+use crate::indexing::SpIndex;
+use crate::sparse::prelude::*;
+use crate::Shape;
+
+/// The range of rows crossed by the diagonal of offset `offset`, in a
+/// matrix of shape `shape`
+fn diagonal_rows(shape: Shape, offset: isize) -> Range<usize> {
+    let (rows, cols) = (shape.0 as isize, shape.1 as isize);
+    let start = (-offset).max(0).min(rows);
+    let end = (cols - offset).min(rows).max(start);
+    start as usize..end as usize
+}
+
+/// Test whether the diagonal of offset `offset` crosses a matrix of shape
+/// `shape`
+fn offset_in_bounds(shape: Shape, offset: isize) -> bool {
+    offset > -(shape.0 as isize) && offset < shape.1 as isize
+}
+
+impl<N> DiaMat<N> {
+    /// Try to create a diagonal matrix of shape `shape` from its raw
+    /// components.
+    ///
+    /// The diagonal of offset `offsets[k]` is stored in
+    /// `data[k * cols..(k + 1) * cols]`, its entry at `(i, j)` being stored
+    /// at position `j`.
+    ///
+    /// # Errors
+    ///
+    /// - if `data` does not hold `cols` values per diagonal,
+    /// - if an offset is repeated,
+    /// - if an offset lies outside of the matrix, ie is not greater than
+    ///   `-rows` and less than `cols`.
+    pub fn try_new(
+        shape: Shape,
+        offsets: Vec<isize>,
+        data: Vec<N>,
+    ) -> Result<Self, SprsError> {
+        if data.len() != offsets.len() * shape.1 {
+            return Err(SprsError::IllegalArguments(
+                "Data length does not match the number of diagonals",
+            ));
+        }
+        if !offsets
+            .iter()
+            .all(|&offset| offset_in_bounds(shape, offset))
+        {
+            return Err(SprsError::IllegalArguments(
+                "Offset outside of the matrix",
+            ));
+        }
+        let mut sorted = offsets.clone();
+        sorted.sort_unstable();
+        if sorted.windows(2).any(|w| w[0] == w[1]) {
+            return Err(SprsError::IllegalArguments("Repeated offset"));
+        }
+        Ok(Self {
+            nrows: shape.0,
+            ncols: shape.1,
+            offsets,
+            data,
+        })
+    }
+
+    /// Create a diagonal matrix from its raw components.
+    ///
+    /// # Panics
+    ///
+    /// - if the components do not describe a valid matrix, see `try_new`.
+    pub fn new(shape: Shape, offsets: Vec<isize>, data: Vec<N>) -> Self {
+        Self::try_new(shape, offsets, data).unwrap()
+    }
+
+    /// The number of rows of the matrix
+    pub fn rows(&self) -> usize {
+        self.nrows
+    }
+
+    /// The number of cols of the matrix
+    pub fn cols(&self) -> usize {
+        self.ncols
+    }
+
+    /// The shape of the matrix, as a `(rows, cols)` tuple
+    pub fn shape(&self) -> Shape {
+        (self.nrows, self.ncols)
+    }
+
+    /// The offsets of the stored diagonals
+    pub fn offsets(&self) -> &[isize] {
+        &self.offsets[..]
+    }
+
+    /// The values of the stored diagonals
+    pub fn data(&self) -> &[N] {
+        &self.data[..]
+    }
+
+    /// The values of the `k`-th stored diagonal, indexed by column
+    ///
+    /// # Panics
+    ///
+    /// - if `k` is not less than the number of stored diagonals.
+    pub fn diagonal(&self, k: usize) -> &[N] {
+        &self.data[k * self.ncols..(k + 1) * self.ncols]
+    }
+
+    /// The number of stored entries located inside the matrix, including
+    /// explicit zeros
+    pub fn nnz(&self) -> usize {
+        self.offsets
+            .iter()
+            .map(|&offset| diagonal_rows(self.shape(), offset).len())
+            .sum()
+    }
+
+    /// Create a CSR matrix from this matrix. Zero values are not stored.
+    pub fn to_csr<I: SpIndex>(&self) -> CsMatI<N, I>
+    where
+        N: Clone + Num,
+    {
+        // iterating the diagonals by increasing offsets yields sorted
+        // column indices in each row
+        let mut order: Vec<usize> = (0..self.offsets.len()).collect();
+        order.sort_unstable_by_key(|&k| self.offsets[k]);
+        let mut indptr = Vec::with_capacity(self.nrows + 1);
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        indptr.push(I::zero());
+        for i in 0..self.nrows {
+            for &k in &order {
+                let j = i as isize + self.offsets[k];
+                if j < 0 || j >= self.ncols as isize {
+                    continue;
+                }
+                let val = &self.diagonal(k)[j as usize];
+                if !val.is_zero() {
+                    indices.push(I::from_usize(j as usize));
+                    data.push(val.clone());
+                }
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        CsMatI::new_trusted(crate::CSR, self.shape(), indptr, indices, data)
+    }
+}
+
+impl<N> SparseMat for DiaMat<N> {
+    fn rows(&self) -> usize {
+        self.rows()
+    }
+
+    fn cols(&self) -> usize {
+        self.cols()
+    }
+
+    fn nnz(&self) -> usize {
+        self.nnz()
+    }
+}
+
+impl<'a, 'b, N, DS2> Mul<&'b ArrayBase<DS2, Ix1>> for &'a DiaMat<N>
+where
+    N: 'a + Copy + Num,
+    DS2: 'b + ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn mul(self, rhs: &'b ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        assert_eq!(self.cols(), rhs.len(), "Dimension mismatch");
+        let mut res = Array::zeros(self.rows());
+        for (k, &offset) in self.offsets.iter().enumerate() {
+            let rows = diagonal_rows(self.shape(), offset);
+            let start = (rows.start as isize + offset) as usize;
+            let cols = start..start + rows.len();
+            let diag = &self.diagonal(k)[cols.clone()];
+            let x = rhs.slice(s![cols]);
+            res.slice_mut(s![rows])
+                .iter_mut()
+                .zip(diag.iter().zip(x.iter()))
+                .for_each(|(y, (&a, &x))| *y = *y + a * x);
+        }
+        res
+    }
+}
+
+impl<N, DS2> Dot<ArrayBase<DS2, Ix1>> for DiaMat<N>
+where
+    N: Copy + Num,
+    DS2: ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn dot(&self, rhs: &ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        Mul::mul(self, rhs)
+    }
+}
+
+/// Build a CSR matrix of shape `shape` from its diagonals.
+///
+/// The diagonal `diagonals[k]` is placed at offset `offsets[k]` from the
+/// main diagonal, positive offsets being above the main diagonal. Each
+/// diagonal either holds as many values as there are entries on the
+/// diagonal, starting from its top left entry, or a single value that is
+/// repeated along the diagonal. Zero values are not stored.
+///
+/// This is similar to SciPy's `diags` function.
+///
+/// # Panics
+///
+/// - if `diagonals` and `offsets` do not have the same length,
+/// - if an offset is repeated,
+/// - if an offset lies outside of the matrix, ie is not greater than
+///   `-rows` and less than `cols`,
+/// - if the length of a diagonal does not match its offset.
+///
+/// # Example
+///
+/// ```rust
+/// use ndarray::arr2;
+/// // the 1D laplacian, a tridiagonal matrix
+/// let lap: sprs::CsMat<f64> =
+///     sprs::diags((4, 4), &[&[1.][..], &[-2.], &[1.]], &[-1, 0, 1]);
+/// let expected = arr2(&[
+///     [-2., 1., 0., 0.],
+///     [1., -2., 1., 0.],
+///     [0., 1., -2., 1.],
+///     [0., 0., 1., -2.],
+/// ]);
+/// assert_eq!(lap.to_dense(), expected);
+/// let upper: sprs::CsMat<i32> =
+///     sprs::diags((3, 4), &[vec![1, 2, 3]], &[1]);
+/// assert_eq!(upper.indices(), &[1, 2, 3]);
+/// ```
+pub fn diags<N, I, D>(
+    shape: Shape,
+    diagonals: &[D],
+    offsets: &[isize],
+) -> CsMatI<N, I>
+where
+    N: Clone + Num,
+    I: SpIndex,
+    D: AsRef<[N]>,
+{
+    assert_eq!(
+        diagonals.len(),
+        offsets.len(),
+        "one offset per diagonal is required"
+    );
+    let cols = shape.1;
+    let mut data = vec![N::zero(); diagonals.len() * cols];
+    for (k, (diagonal, &offset)) in diagonals.iter().zip(offsets).enumerate() {
+        let diagonal = diagonal.as_ref();
+        assert!(
+            offset_in_bounds(shape, offset),
+            "offset outside of the matrix"
+        );
+        let rows = diagonal_rows(shape, offset);
+        assert!(
+            diagonal.len() == rows.len() || diagonal.len() == 1,
+            "diagonal length does not match its offset"
+        );
+        let start = k * cols + (rows.start as isize + offset) as usize;
+        let dest = &mut data[start..start + rows.len()];
+        if diagonal.len() == rows.len() {
+            dest.clone_from_slice(diagonal);
+        } else {
+            dest.iter_mut().for_each(|val| *val = diagonal[0].clone());
+        }
+    }
+    DiaMat::new(shape, offsets.to_vec(), data).to_csr()
+}
+
+#[cfg(test)]
+mod test {
+    use super::diags;
+    use crate::errors::SprsError;
+    use crate::sparse::{CsMat, CsMatI, DiaMat};
+    use ndarray::{arr1, arr2};
+
+    fn banded() -> DiaMat<f64> {
+        // a 3x4 matrix with its main diagonal, the first superdiagonal and
+        // the first subdiagonal
+        DiaMat::new(
+            (3, 4),
+            vec![1, -1, 0],
+            vec![
+                9., 1., 2., 3., // superdiagonal
+                4., 5., 9., 9., // subdiagonal
+                6., 7., 8., 9., // main diagonal
+            ],
+        )
+    }
+
+    #[test]
+    fn to_csr() {
+        let mat = banded();
+        assert_eq!(mat.nnz(), 8);
+        let csr: CsMatI<f64, u16> = mat.to_csr();
+        let expected =
+            arr2(&[[6., 1., 0., 0.], [4., 7., 2., 0.], [0., 5., 8., 3.]]);
+        assert_eq!(csr.to_dense(), expected);
+        assert_eq!(csr.indices(), &[0, 1, 0, 1, 2, 1, 2, 3]);
+
+        // zeros are not stored
+        let mat = DiaMat::new((2, 2), vec![0, 1], vec![1, 0, 0, 0]);
+        let csr: CsMat<i32> = mat.to_csr();
+        assert_eq!(csr.nnz(), 1);
+    }
+
+    #[test]
+    fn products() {
+        let mat = banded();
+        let csr: CsMat<f64> = mat.to_csr();
+        let x = arr1(&[1., -2., 0.5, 3.]);
+        assert_eq!(&mat * &x, &csr * &x);
+        let wide = DiaMat::new((2, 5), vec![3, -1], vec![1.; 10]);
+        let x = arr1(&[1., 2., 3., 4., 5.]);
+        assert_eq!(&wide * &x, arr1(&[4., 6.]));
+    }
+
+    #[test]
+    fn invalid_components() {
+        let res = DiaMat::try_new((2, 2), vec![0, 0], vec![1.; 4]);
+        assert_eq!(
+            res.unwrap_err(),
+            SprsError::IllegalArguments("Repeated offset")
+        );
+        let res = DiaMat::try_new((2, 2), vec![0, 1], vec![1.; 3]);
+        assert!(res.is_err());
+        for &offset in &[3, 5, -3, -4] {
+            let res = DiaMat::try_new((3, 3), vec![offset], vec![1.; 3]);
+            assert_eq!(
+                res.unwrap_err(),
+                SprsError::IllegalArguments("Offset outside of the matrix")
+            );
+        }
+    }
+
+    #[test]
+    fn diags_construction() {
+        let mat: CsMat<i32> = diags(
+            (4, 3),
+            &[vec![1, 2, 3], vec![4, 5, 6], vec![7]],
+            &[0, -1, 2],
+        );
+        let expected = arr2(&[[1, 0, 7], [4, 2, 0], [0, 5, 3], [0, 0, 6]]);
+        assert_eq!(mat.to_dense(), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn diags_bad_length() {
+        let _: CsMat<i32> = diags((3, 3), &[vec![1, 2, 3]], &[1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "offset outside of the matrix")]
+    fn diags_offset_out_of_bounds() {
+        let _: CsMat<f64> = diags((3, 3), &[&[1.][..]], &[-5]);
+    }
+}
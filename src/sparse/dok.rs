@@ -0,0 +1,369 @@
+//! Dictionary of keys format matrix
+//!
+//! A dictionary of keys matrix stores its non-zero entries in a hash map
+//! indexed by their location. This enables constant time random access
+//! updates, which makes this format suited to build matrices whose entries
+//! are not known in order, before converting them to the compressed format.
+
+use std::collections::hash_map::{self, Entry};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::AddAssign;
+
+use crate::indexing::SpIndex;
+use crate::sparse::prelude::*;
+use crate::sparse::CompressedStorage;
+
+impl<N, I: SpIndex + Hash> DokMatI<N, I> {
+    /// Create a new empty matrix of shape `(nb_rows, nb_cols)`
+    pub fn new(shape: (usize, usize)) -> Self {
+        Self {
+            rows: shape.0,
+            cols: shape.1,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Create a new empty matrix of shape `(nb_rows, nb_cols)`, with
+    /// space for at least `cap` non-zero entries
+    pub fn with_capacity(shape: (usize, usize), cap: usize) -> Self {
+        Self {
+            rows: shape.0,
+            cols: shape.1,
+            entries: HashMap::with_capacity(cap),
+        }
+    }
+
+    /// The number of rows of the matrix
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of cols of the matrix
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The shape of the matrix, as a `(rows, cols)` tuple
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// The number of non-zero entries
+    pub fn nnz(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn key(&self, row: usize, col: usize) -> (I, I) {
+        assert!(row < self.rows, "row index out of bounds");
+        assert!(col < self.cols, "col index out of bounds");
+        (I::from_usize(row), I::from_usize(col))
+    }
+
+    /// Access the element located at `row` and `col`.
+    ///
+    /// Returns `None` if there is no non-zero entry at this location,
+    /// including if the location is out of bounds.
+    pub fn get(&self, row: usize, col: usize) -> Option<&N> {
+        let row = I::try_from_usize(row)?;
+        let col = I::try_from_usize(col)?;
+        self.entries.get(&(row, col))
+    }
+
+    /// Mutable access to the element located at `row` and `col`.
+    ///
+    /// Returns `None` if there is no non-zero entry at this location,
+    /// including if the location is out of bounds.
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut N> {
+        let row = I::try_from_usize(row)?;
+        let col = I::try_from_usize(col)?;
+        self.entries.get_mut(&(row, col))
+    }
+
+    /// Set the value of the element located at `row` and `col`, returning
+    /// its previous value if it was a non-zero entry.
+    ///
+    /// The value is stored even if it is zero, see `remove` to remove a
+    /// non-zero entry.
+    ///
+    /// # Panics
+    ///
+    /// - if the location is out of bounds.
+    pub fn set(&mut self, row: usize, col: usize, val: N) -> Option<N> {
+        let key = self.key(row, col);
+        self.entries.insert(key, val)
+    }
+
+    /// Remove the element located at `row` and `col`, returning its value
+    /// if it was a non-zero entry.
+    pub fn remove(&mut self, row: usize, col: usize) -> Option<N> {
+        let row = I::try_from_usize(row)?;
+        let col = I::try_from_usize(col)?;
+        self.entries.remove(&(row, col))
+    }
+
+    /// Add `val` to the element located at `row` and `col`, creating a
+    /// non-zero entry holding `val` if there is none at this location.
+    ///
+    /// # Panics
+    ///
+    /// - if the location is out of bounds.
+    pub fn accumulate(&mut self, row: usize, col: usize, val: N)
+    where
+        N: AddAssign,
+    {
+        let key = self.key(row, col);
+        match self.entries.entry(key) {
+            Entry::Occupied(mut entry) => *entry.get_mut() += val,
+            Entry::Vacant(entry) => {
+                entry.insert(val);
+            }
+        }
+    }
+
+    /// Remove all the non-zero entries, keeping the allocated memory
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Change the shape of the matrix, removing the non-zero entries that
+    /// do not fit in the new shape.
+    pub fn resize(&mut self, shape: (usize, usize)) {
+        let (rows, cols) = shape;
+        if rows < self.rows || cols < self.cols {
+            self.entries
+                .retain(|&(i, j), _| i.index() < rows && j.index() < cols);
+        }
+        self.rows = rows;
+        self.cols = cols;
+    }
+
+    /// Get an iterator over the non-zero entries of this matrix, in an
+    /// unspecified order
+    pub fn iter(&self) -> DokIter<'_, N, I> {
+        DokIter {
+            entries: self.entries.iter(),
+        }
+    }
+
+    /// Create a CSR matrix from this matrix
+    pub fn to_csr(&self) -> CsMatI<N, I>
+    where
+        N: Clone,
+    {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_unstable_by_key(|&(&loc, _)| loc);
+        self.compress(crate::CSR, self.rows, entries)
+    }
+
+    /// Create a CSC matrix from this matrix
+    pub fn to_csc(&self) -> CsMatI<N, I>
+    where
+        N: Clone,
+    {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_unstable_by_key(|&(&(row, col), _)| (col, row));
+        self.compress(crate::CSC, self.cols, entries)
+    }
+
+    /// Build a compressed matrix from the entries sorted in its storage
+    /// order
+    fn compress(
+        &self,
+        storage: CompressedStorage,
+        outer_dims: usize,
+        entries: Vec<(&(I, I), &N)>,
+    ) -> CsMatI<N, I>
+    where
+        N: Clone,
+    {
+        let mut indptr = vec![I::zero(); outer_dims + 1];
+        let mut indices = Vec::with_capacity(entries.len());
+        let mut data = Vec::with_capacity(entries.len());
+        for (&(row, col), val) in entries {
+            let (outer, inner) = match storage {
+                CompressedStorage::CSR => (row, col),
+                CompressedStorage::CSC => (col, row),
+            };
+            indptr[outer.index() + 1] += I::one();
+            indices.push(inner);
+            data.push(val.clone());
+        }
+        for i in 0..outer_dims {
+            let prev = indptr[i];
+            indptr[i + 1] += prev;
+        }
+        CsMatI::new_trusted(storage, self.shape(), indptr, indices, data)
+    }
+
+    /// Create a triplet matrix from this matrix, its entries being stored
+    /// in an unspecified order
+    pub fn to_trimat(&self) -> TriMatI<N, I>
+    where
+        N: Clone,
+    {
+        let mut mat = TriMatI::with_capacity(self.shape(), self.nnz());
+        for (val, (row, col)) in self.iter() {
+            mat.add_triplet(row.index(), col.index(), val.clone());
+        }
+        mat
+    }
+}
+
+impl<N: PartialEq, I: SpIndex + Hash> PartialEq for DokMatI<N, I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape() == other.shape() && self.entries == other.entries
+    }
+}
+
+/// An iterator over the non-zero entries of a `DokMatI`, yielding their
+/// value and their `(row, col)` location
+#[derive(Clone, Debug)]
+pub struct DokIter<'a, N, I> {
+    entries: hash_map::Iter<'a, (I, I), N>,
+}
+
+impl<'a, N, I: Copy> Iterator for DokIter<'a, N, I> {
+    type Item = (&'a N, (I, I));
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|(&loc, val)| (val, loc))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<'a, N, I: Copy> ExactSizeIterator for DokIter<'a, N, I> {}
+
+impl<'a, N, I: SpIndex + Hash> IntoIterator for &'a DokMatI<N, I> {
+    type Item = (&'a N, (I, I));
+    type IntoIter = DokIter<'a, N, I>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<N, I: SpIndex + Hash> SparseMat for DokMatI<N, I> {
+    fn rows(&self) -> usize {
+        self.rows()
+    }
+
+    fn cols(&self) -> usize {
+        self.cols()
+    }
+
+    fn nnz(&self) -> usize {
+        self.nnz()
+    }
+}
+
+impl<N, I: SpIndex + Hash> SparseMat for &DokMatI<N, I> {
+    fn rows(&self) -> usize {
+        (*self).rows()
+    }
+
+    fn cols(&self) -> usize {
+        (*self).cols()
+    }
+
+    fn nnz(&self) -> usize {
+        (*self).nnz()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sparse::{CsMat, DokMat, DokMatI, TriMat};
+
+    #[test]
+    fn random_access_updates() {
+        let mut mat = DokMat::new((3, 4));
+        assert_eq!(mat.set(0, 1, 2.), None);
+        assert_eq!(mat.set(2, 3, 1.), None);
+        assert_eq!(mat.set(0, 1, 3.), Some(2.));
+        mat.accumulate(2, 3, 4.);
+        mat.accumulate(1, 0, -1.);
+        assert_eq!(mat.nnz(), 3);
+        assert_eq!(mat.get(0, 1), Some(&3.));
+        assert_eq!(mat.get(2, 3), Some(&5.));
+        assert_eq!(mat.get(1, 0), Some(&-1.));
+        assert_eq!(mat.get(1, 1), None);
+        assert_eq!(mat.get(5, 1), None);
+
+        *mat.get_mut(1, 0).unwrap() *= 2.;
+        assert_eq!(mat.get(1, 0), Some(&-2.));
+        assert_eq!(mat.remove(0, 1), Some(3.));
+        assert_eq!(mat.remove(0, 1), None);
+        assert_eq!(mat.nnz(), 2);
+
+        let mut entries: Vec<_> = mat.iter().collect();
+        entries.sort_by_key(|&(_, loc)| loc);
+        assert_eq!(entries, vec![(&-2., (1, 0)), (&5., (2, 3))]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_out_of_bounds() {
+        let mut mat = DokMat::new((3, 4));
+        mat.set(3, 0, 1.);
+    }
+
+    #[test]
+    fn resize() {
+        let mut mat = DokMat::new((3, 3));
+        mat.set(0, 0, 1);
+        mat.set(1, 2, 2);
+        mat.set(2, 1, 3);
+        mat.resize((2, 4));
+        assert_eq!(mat.shape(), (2, 4));
+        assert_eq!(mat.nnz(), 2);
+        assert_eq!(mat.get(2, 1), None);
+        mat.set(1, 3, 4);
+        mat.resize((5, 5));
+        assert_eq!(mat.nnz(), 3);
+        assert_eq!(mat.get(1, 3), Some(&4));
+
+        let mut other = DokMat::new((5, 5));
+        other.set(1, 3, 4);
+        other.set(0, 0, 1);
+        other.set(1, 2, 2);
+        assert_eq!(mat, other);
+        other.resize((5, 6));
+        assert_ne!(mat, other);
+    }
+
+    #[test]
+    fn conversions() {
+        let mut mat: DokMatI<f64, u32> = DokMatI::new((3, 4));
+        mat.set(2, 0, 4.);
+        mat.set(0, 3, 2.);
+        mat.set(0, 1, 1.);
+        mat.set(1, 1, 3.);
+        mat.set(2, 3, 5.);
+        let csr = mat.to_csr();
+        assert!(csr.is_csr());
+        assert_eq!(csr.indptr(), &[0, 2, 3, 5]);
+        assert_eq!(csr.indices(), &[1, 3, 1, 0, 3]);
+        assert_eq!(csr.data(), &[1., 2., 3., 4., 5.]);
+        assert_eq!(mat.to_csc(), csr.to_csc());
+        assert_eq!(mat.to_trimat().to_csr(), csr);
+
+        let empty: DokMat<f64> = DokMat::new((2, 3));
+        assert_eq!(empty.to_csr(), CsMat::zero((2, 3)));
+        assert_eq!(empty.to_csc(), CsMat::zero((2, 3)).to_csc());
+    }
+
+    #[test]
+    fn overwrite_instead_of_sum() {
+        let mut dok = DokMat::new((2, 2));
+        let mut tri = TriMat::new((2, 2));
+        for &val in &[1, 2, 3] {
+            dok.set(1, 0, val);
+            tri.add_triplet(1, 0, val);
+        }
+        assert_eq!(dok.to_csr().get(1, 0), Some(&3));
+        assert_eq!(tri.to_csr().get(1, 0), Some(&6));
+    }
+}
@@ -1,11 +1,17 @@
 ///! Sparse linear algebra
 ///!
 ///! This module contains solvers for sparse linear systems. Currently
-///! there are solver for sparse triangular systems and symmetric systems.
+///! there are solver for sparse triangular systems and symmetric systems,
+///! a sparse LU factorization for general systems in the `lu` module,
+///! iterative solvers in the `iterative` module, and incomplete
+///! factorizations to precondition them in the `ilu` module.
 use num_traits::Num;
 use std::iter::IntoIterator;
 
 pub mod etree;
+pub mod ilu;
+pub mod iterative;
+pub mod lu;
 pub mod ordering;
 pub mod trisolve;
 
@@ -0,0 +1,436 @@
+//! Incomplete LU factorizations
+//!
+//! An incomplete LU factorization approximates a sparse matrix `A` by the
+//! product of sparse triangular factors `L * U`, whose fill-in is limited
+//! either to the sparsity pattern of `A` (ILU(0)) or by dropping small
+//! entries (ILUT). The factors are cheap to solve with, which makes them
+//! good preconditioners for iterative solvers.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::ops::Deref;
+
+use num_traits::{Float, Num};
+
+use crate::errors::SprsError;
+use crate::indexing::SpIndex;
+use crate::sparse::linalg::iterative::Preconditioner;
+use crate::sparse::linalg::trisolve;
+use crate::sparse::prelude::*;
+use crate::sparse::CompressedStorage;
+
+/// An incomplete LU factorization `A ~ L * U` of a square matrix, where `L`
+/// is unit lower triangular and `U` is upper triangular. Both factors are
+/// stored in CSR, with their diagonal, so they can be used directly with
+/// `lsolve_csr_dense_rhs` and `usolve_csr_dense_rhs`.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::ilu::IncompleteLu;
+/// let mat: sprs::CsMat<f64> =
+///     sprs::diags((4, 4), &[&[-2.][..], &[4.], &[-1.]], &[-1, 0, 1]);
+/// let ilu = IncompleteLu::ilu0(&mat).unwrap();
+/// // a tridiagonal matrix has no fill-in, so its ILU(0) is exact
+/// let mut x = vec![3., 1., 1., 2.];
+/// ilu.solve(&mut x).unwrap();
+/// assert!(x.iter().all(|&x| (x - 1.).abs() < 1e-12));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct IncompleteLu<N, I: SpIndex = usize, Iptr: SpIndex = I> {
+    l: CsMatI<N, I, Iptr>,
+    u: CsMatI<N, I, Iptr>,
+}
+
+/// Accumulates the rows of a triangular factor
+struct RowsBuilder<N> {
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    data: Vec<N>,
+}
+
+impl<N> RowsBuilder<N> {
+    fn new() -> Self {
+        Self {
+            indptr: vec![0],
+            indices: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Push a row, whose entries should be sorted by column
+    fn push_row<It: IntoIterator<Item = (usize, N)>>(&mut self, row: It) {
+        for (j, val) in row {
+            self.indices.push(j);
+            self.data.push(val);
+        }
+        self.indptr.push(self.indices.len());
+    }
+
+    fn build<I: SpIndex, Iptr: SpIndex>(self) -> CsMatI<N, I, Iptr> {
+        let n = self.indptr.len() - 1;
+        CsMatI::new_trusted(
+            CompressedStorage::CSR,
+            (n, n),
+            self.indptr.into_iter().map(Iptr::from_usize).collect(),
+            self.indices.into_iter().map(I::from_usize).collect(),
+            self.data,
+        )
+    }
+}
+
+impl<N, I, Iptr> IncompleteLu<N, I, Iptr>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    Iptr: SpIndex,
+{
+    /// Compute the ILU(0) factorization of a matrix, whose factors have
+    /// the sparsity pattern of the lower and upper parts of the matrix.
+    ///
+    /// # Errors
+    ///
+    /// - `SingularMatrix` if a diagonal entry is not stored, or if a zero
+    ///   pivot is encountered.
+    ///
+    /// # Panics
+    ///
+    /// - if the matrix is not square.
+    pub fn ilu0<IpS, IS, DS>(
+        mat: &CsMatBase<N, I, IpS, IS, DS, Iptr>,
+    ) -> Result<Self, SprsError>
+    where
+        N: Default,
+        IpS: Deref<Target = [Iptr]>,
+        IS: Deref<Target = [I]>,
+        DS: Deref<Target = [N]>,
+    {
+        assert_eq!(mat.rows(), mat.cols(), "Matrix is not square");
+        let n = mat.rows();
+        let (indptr, indices, mut data) = mat.to_csr().into_raw_storage();
+        let row_range = |i: usize| indptr[i].index()..indptr[i + 1].index();
+        let mut diag_pos = Vec::with_capacity(n);
+        for i in 0..n {
+            let range = row_range(i);
+            let start = range.start;
+            match indices[range].binary_search(&I::from_usize(i)) {
+                Ok(pos) => diag_pos.push(start + pos),
+                Err(_) => return Err(SprsError::SingularMatrix),
+            }
+        }
+        // IKJ variant of the gaussian elimination, restricted to the
+        // existing pattern, which is located through `positions`
+        let mut positions = vec![None; n];
+        for i in 0..n {
+            for pos in row_range(i) {
+                positions[indices[pos].index()] = Some(pos);
+            }
+            for kp in row_range(i).start..diag_pos[i] {
+                let k = indices[kp].index();
+                let factor = data[kp] / data[diag_pos[k]];
+                data[kp] = factor;
+                for jp in diag_pos[k] + 1..row_range(k).end {
+                    if let Some(pos) = positions[indices[jp].index()] {
+                        data[pos] = data[pos] - factor * data[jp];
+                    }
+                }
+            }
+            if data[diag_pos[i]].is_zero() {
+                return Err(SprsError::SingularMatrix);
+            }
+            for pos in row_range(i) {
+                positions[indices[pos].index()] = None;
+            }
+        }
+        let mut l = RowsBuilder::new();
+        let mut u = RowsBuilder::new();
+        let entry = |pos: usize| (indices[pos].index(), data[pos]);
+        for (i, &diag) in diag_pos.iter().enumerate() {
+            let range = row_range(i);
+            l.push_row(
+                (range.start..diag).map(entry).chain(Some((i, N::one()))),
+            );
+            u.push_row((diag..range.end).map(entry));
+        }
+        Ok(Self {
+            l: l.build(),
+            u: u.build(),
+        })
+    }
+
+    /// The unit lower triangular factor
+    pub fn l(&self) -> CsMatViewI<N, I, Iptr> {
+        self.l.view()
+    }
+
+    /// The upper triangular factor
+    pub fn u(&self) -> CsMatViewI<N, I, Iptr> {
+        self.u.view()
+    }
+
+    /// Get the `(L, U)` factors
+    pub fn into_factors(self) -> (CsMatI<N, I, Iptr>, CsMatI<N, I, Iptr>) {
+        (self.l, self.u)
+    }
+
+    /// The number of non-zero entries stored in both factors
+    pub fn nnz(&self) -> usize {
+        self.l.nnz() + self.u.nnz()
+    }
+
+    /// Solve the system `L * U * x = rhs` in place.
+    ///
+    /// # Panics
+    ///
+    /// - if the dimension of `rhs` does not match the factors.
+    pub fn solve(&self, rhs: &mut [N]) -> Result<(), SprsError> {
+        trisolve::lsolve_csr_dense_rhs(self.l.view(), rhs)?;
+        trisolve::usolve_csr_dense_rhs(self.u.view(), rhs)
+    }
+}
+
+impl<N, I, Iptr> IncompleteLu<N, I, Iptr>
+where
+    N: Float,
+    I: SpIndex,
+    Iptr: SpIndex,
+{
+    /// Compute the ILUT factorization of a matrix, using the dual
+    /// threshold strategy of Saad, ILUT: a dual threshold incomplete LU
+    /// factorization, 1994.
+    ///
+    /// While eliminating row `i`, entries smaller than `drop_tol` times the
+    /// norm of the row `i` of the matrix are dropped. Then at most
+    /// `max_fill` of the largest entries are kept in each of the lower and
+    /// upper parts of the row, the diagonal being always kept. A null
+    /// `drop_tol` with `max_fill` equal to the dimension of the matrix
+    /// computes a complete LU factorization without pivoting.
+    ///
+    /// # Errors
+    ///
+    /// - `SingularMatrix` if a zero pivot is encountered.
+    ///
+    /// # Panics
+    ///
+    /// - if the matrix is not square.
+    pub fn ilut<IpS, IS, DS>(
+        mat: &CsMatBase<N, I, IpS, IS, DS, Iptr>,
+        drop_tol: N,
+        max_fill: usize,
+    ) -> Result<Self, SprsError>
+    where
+        N: Default,
+        IpS: Deref<Target = [Iptr]>,
+        IS: Deref<Target = [I]>,
+        DS: Deref<Target = [N]>,
+    {
+        assert_eq!(mat.rows(), mat.cols(), "Matrix is not square");
+        let n = mat.rows();
+        let csr = mat.to_csr();
+        let mut l = RowsBuilder::new();
+        let mut u = RowsBuilder::<N>::new();
+        // the dense working row, and the columns of its non zero pattern
+        let mut work = vec![N::zero(); n];
+        let mut in_pattern = vec![false; n];
+        let mut pattern = Vec::with_capacity(n);
+        let mut lower_cols = BinaryHeap::new();
+        for (i, row) in csr.outer_iterator().enumerate() {
+            let row_norm = row
+                .iter()
+                .fold(N::zero(), |acc, (_, &val)| acc + val * val)
+                .sqrt();
+            let threshold = drop_tol * row_norm;
+            for (j, &val) in row.iter() {
+                work[j] = val;
+                in_pattern[j] = true;
+                pattern.push(j);
+                if j < i {
+                    lower_cols.push(Reverse(j));
+                }
+            }
+            // the heap yields the lower columns in increasing order, fill-in
+            // being only created in columns larger than the current one
+            let mut lower = Vec::new();
+            while let Some(Reverse(k)) = lower_cols.pop() {
+                let factor = work[k] / u.data[u.indptr[k]];
+                work[k] = N::zero();
+                if factor.abs() < threshold {
+                    continue;
+                }
+                lower.push((k, factor));
+                let k_range = u.indptr[k] + 1..u.indptr[k + 1];
+                for (&j, &u_kj) in
+                    u.indices[k_range.clone()].iter().zip(&u.data[k_range])
+                {
+                    if !in_pattern[j] {
+                        in_pattern[j] = true;
+                        pattern.push(j);
+                        if j < i {
+                            lower_cols.push(Reverse(j));
+                        }
+                    }
+                    work[j] = work[j] - factor * u_kj;
+                }
+            }
+            let diag = work[i];
+            let mut upper: Vec<_> = pattern
+                .iter()
+                .filter(|&&j| j > i && work[j].abs() >= threshold)
+                .map(|&j| (j, work[j]))
+                .collect();
+            for &j in &pattern {
+                work[j] = N::zero();
+                in_pattern[j] = false;
+            }
+            pattern.clear();
+            if diag.is_zero() {
+                return Err(SprsError::SingularMatrix);
+            }
+            keep_largest(&mut lower, max_fill);
+            keep_largest(&mut upper, max_fill);
+            l.push_row(lower.into_iter().chain(Some((i, N::one()))));
+            u.push_row(Some((i, diag)).into_iter().chain(upper));
+        }
+        Ok(Self {
+            l: l.build(),
+            u: u.build(),
+        })
+    }
+}
+
+/// Keep the `count` entries of largest magnitude, sorted by column
+fn keep_largest<N: Float>(entries: &mut Vec<(usize, N)>, count: usize) {
+    if entries.len() > count {
+        entries.sort_by(|a, b| {
+            b.1.abs().partial_cmp(&a.1.abs()).unwrap_or(Ordering::Equal)
+        });
+        entries.truncate(count);
+    }
+    entries.sort_by_key(|&(j, _)| j);
+}
+
+impl<N, I, Iptr> Preconditioner<N> for IncompleteLu<N, I, Iptr>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    Iptr: SpIndex,
+{
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        z.copy_from_slice(r);
+        // the factors have non zero diagonals by construction
+        self.solve(z).expect("Singular incomplete factor");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sparse::linalg::iterative::{
+        Gmres, IdentityPreconditioner, PreconditioningSide,
+    };
+    use crate::sparse::CsMat;
+
+    /// A non symmetric matrix on a grid, with a five point stencil
+    fn grid_matrix(n: usize) -> CsMat<f64> {
+        crate::test_data::five_point_stencil(
+            n,
+            |k| 4. + (k % 3) as f64,
+            [-1., -1.6, -0.4, -1.],
+        )
+    }
+
+    fn assert_close(a: &CsMat<f64>, b: &CsMat<f64>, tol: f64) {
+        let diff = a - b;
+        assert!(diff.data().iter().all(|x| x.abs() < tol));
+    }
+
+    #[test]
+    fn ilu0_pattern() {
+        let mat = grid_matrix(5);
+        let ilu = IncompleteLu::ilu0(&mat).unwrap();
+        // the factors partition the pattern of the matrix, with an added
+        // unit diagonal in L
+        assert_eq!(ilu.nnz(), mat.nnz() + 25);
+        assert!(ilu
+            .l()
+            .outer_iterator()
+            .enumerate()
+            .all(|(i, row)| row.get(i) == Some(&1.)));
+        assert!(crate::is_upper_triangular(&ilu.u()));
+        // the product matches the matrix on its pattern
+        let prod = &ilu.l() * &ilu.u();
+        for (&val, (i, j)) in mat.iter() {
+            assert!((prod.get(i, j).unwrap() - val).abs() < 1e-12);
+        }
+        // a csc input gives the same factorization
+        assert_eq!(IncompleteLu::ilu0(&mat.to_csc()).unwrap(), ilu);
+    }
+
+    #[test]
+    fn ilu0_tridiagonal_is_exact() {
+        let mat: CsMat<f64> =
+            crate::diags((6, 6), &[&[-2.][..], &[5.], &[-1.]], &[-1, 0, 1]);
+        let ilu = IncompleteLu::ilu0(&mat).unwrap();
+        assert_close(&(&ilu.l() * &ilu.u()), &mat, 1e-12);
+    }
+
+    #[test]
+    fn ilut_fill() {
+        let mat = grid_matrix(5);
+        // without dropping, ILUT is the complete LU factorization
+        let lu = IncompleteLu::ilut(&mat, 0., 25).unwrap();
+        assert_close(&(&lu.l() * &lu.u()), &mat, 1e-12);
+        assert!(lu.nnz() > mat.nnz() + 25);
+
+        let ilut = IncompleteLu::ilut(&mat, 1e-2, 3).unwrap();
+        assert!(ilut.nnz() < lu.nnz());
+        for (row_l, row_u) in
+            ilut.l().outer_iterator().zip(ilut.u().outer_iterator())
+        {
+            assert!(row_l.nnz() <= 4);
+            assert!(row_u.nnz() <= 4);
+        }
+    }
+
+    #[test]
+    fn ilu_preconditioned_gmres() {
+        let mat = grid_matrix(10);
+        let rhs: Vec<f64> = (0..100).map(|i| (i % 7) as f64 - 3.).collect();
+        let solver = Gmres::new().tolerance(1e-10);
+        let mut x = vec![0.; 100];
+        let plain = solver
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+
+        let ilu0 = IncompleteLu::ilu0(&mat).unwrap();
+        let ilut = IncompleteLu::ilut(&mat, 1e-3, 10).unwrap();
+        for precond in &[ilu0, ilut] {
+            for &side in
+                &[PreconditioningSide::Left, PreconditioningSide::Right]
+            {
+                let mut x = vec![0.; 100];
+                let stats = solver
+                    .preconditioning(side)
+                    .solve(&mat, precond, &rhs, &mut x)
+                    .unwrap();
+                assert!(stats.iterations < plain.iterations);
+                let mut r = &mat * &ndarray::arr1(&x);
+                r.iter_mut().zip(&rhs).for_each(|(r, &b)| *r -= b);
+                assert!(r.iter().all(|r| r.abs() < 1e-8));
+            }
+        }
+    }
+
+    #[test]
+    fn ilu_singular() {
+        let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![1, 0], vec![1., 2.]);
+        assert_eq!(
+            IncompleteLu::ilu0(&mat).unwrap_err(),
+            SprsError::SingularMatrix
+        );
+        assert_eq!(
+            IncompleteLu::ilut(&mat, 0., 2).unwrap_err(),
+            SprsError::SingularMatrix
+        );
+    }
+}
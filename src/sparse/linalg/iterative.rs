@@ -0,0 +1,1094 @@
+//! Iterative solvers for sparse linear systems
+//!
+//! Iterative solvers only access the matrix of a system through
+//! matrix-vector products, which makes them suitable for large systems that
+//! a direct factorization would not fit in memory. The matrix is described
+//! by the `LinearOperator` trait, implemented by the sparse matrix types,
+//! and the convergence can be improved by a `Preconditioner`.
+
+use std::fmt;
+use std::ops::Deref;
+
+use num_traits::{Float, Num};
+
+use crate::errors::SprsError;
+use crate::indexing::SpIndex;
+use crate::sparse::prelude::*;
+use crate::sparse::prod;
+use crate::Shape;
+
+/// A linear operator, which can be applied to dense vectors
+pub trait LinearOperator<N> {
+    /// The shape of the operator, as a `(rows, cols)` tuple
+    fn shape(&self) -> Shape;
+
+    /// Compute the product `y = A * x`, overwriting `y`.
+    ///
+    /// # Panics
+    ///
+    /// - if the dimensions of `x` and `y` do not match the operator.
+    fn apply(&self, x: &[N], y: &mut [N]);
+}
+
+impl<N, I, IpS, IS, DS, Iptr> LinearOperator<N>
+    for CsMatBase<N, I, IpS, IS, DS, Iptr>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    Iptr: SpIndex,
+    IpS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+{
+    fn shape(&self) -> Shape {
+        self.shape()
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        y.iter_mut().for_each(|y| *y = N::zero());
+        if self.is_csr() {
+            prod::mul_acc_mat_vec_csr(self.view(), x, y);
+        } else {
+            prod::mul_acc_mat_vec_csc(self.view(), x, y);
+        }
+    }
+}
+
+impl<N, I, Iptr> LinearOperator<N> for SymCsMatI<N, I, Iptr>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    Iptr: SpIndex,
+{
+    fn shape(&self) -> Shape {
+        self.shape()
+    }
+
+    fn apply(&self, x: &[N], y: &mut [N]) {
+        assert_eq!(self.cols(), x.len(), "Dimension mismatch");
+        assert_eq!(self.rows(), y.len(), "Dimension mismatch");
+        y.iter_mut().for_each(|y| *y = N::zero());
+        for (&a, (i, j)) in self.upper().iter() {
+            let (i, j) = (i.index(), j.index());
+            y[i] = y[i] + a * x[j];
+            if i != j {
+                y[j] = y[j] + a * x[i];
+            }
+        }
+    }
+}
+
+/// A preconditioner M of a linear system, approximating its matrix A by an
+/// operator which is cheap to invert. A good preconditioner makes M^-1 A
+/// close to the identity, which speeds up the convergence of iterative
+/// solvers.
+pub trait Preconditioner<N> {
+    /// Compute `z = M^-1 * r`, overwriting `z`.
+    fn apply(&self, r: &[N], z: &mut [N]);
+}
+
+/// The identity preconditioner, which leaves the system unchanged
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdentityPreconditioner;
+
+impl<N: Copy> Preconditioner<N> for IdentityPreconditioner {
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        z.copy_from_slice(r);
+    }
+}
+
+/// The Jacobi preconditioner, which approximates a matrix by its diagonal
+#[derive(Clone, Debug, PartialEq)]
+pub struct JacobiPreconditioner<N> {
+    inv_diag: Vec<N>,
+}
+
+impl<N: Copy + Num> JacobiPreconditioner<N> {
+    /// Create the Jacobi preconditioner of a square matrix.
+    ///
+    /// # Errors
+    ///
+    /// - `SingularMatrix` if a diagonal entry is zero or not stored.
+    ///
+    /// # Panics
+    ///
+    /// - if the matrix is not square.
+    pub fn new<I, IpS, IS, DS, Iptr>(
+        mat: &CsMatBase<N, I, IpS, IS, DS, Iptr>,
+    ) -> Result<Self, SprsError>
+    where
+        I: SpIndex,
+        Iptr: SpIndex,
+        IpS: Deref<Target = [Iptr]>,
+        IS: Deref<Target = [I]>,
+        DS: Deref<Target = [N]>,
+    {
+        assert_eq!(mat.rows(), mat.cols(), "Matrix is not square");
+        let inv_diag = (0..mat.rows())
+            .map(|i| match mat.get_outer_inner(i, i) {
+                Some(&d) if !d.is_zero() => Ok(N::one() / d),
+                _ => Err(SprsError::SingularMatrix),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { inv_diag })
+    }
+}
+
+impl<N: Copy + Num> Preconditioner<N> for JacobiPreconditioner<N> {
+    fn apply(&self, r: &[N], z: &mut [N]) {
+        assert_eq!(self.inv_diag.len(), r.len(), "Dimension mismatch");
+        for ((z, &r), &d) in z.iter_mut().zip(r).zip(&self.inv_diag) {
+            *z = d * r;
+        }
+    }
+}
+
+/// Statistics describing the convergence of an iterative solve
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolveStats<N> {
+    /// The number of iterations which were performed
+    pub iterations: usize,
+    /// The norm of the residual `b - A * x` when the solve stopped, or of
+    /// the preconditioned residual `M^-1 * (b - A * x)` for a left
+    /// preconditioned solve. Methods updating the residual by a recurrence,
+    /// such as the conjugate gradient, report the recurrence residual,
+    /// which can drift from the residual of the returned solution in
+    /// finite precision.
+    pub residual_norm: N,
+    /// The norm of the residual before the first iteration and after each
+    /// iteration, as estimated by the method
+    pub history: Vec<N>,
+}
+
+/// The reasons for an iterative solve to fail. The solution vector holds
+/// the last iterate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IterativeError<N> {
+    /// The tolerance was not reached in the allowed number of iterations
+    MaxIterations(SolveStats<N>),
+    /// The method cannot continue, eg because the matrix or the
+    /// preconditioner do not satisfy its requirements
+    Breakdown(SolveStats<N>),
+}
+
+impl<N> IterativeError<N> {
+    /// The convergence statistics of the failed solve
+    pub fn stats(&self) -> &SolveStats<N> {
+        match self {
+            IterativeError::MaxIterations(stats) => stats,
+            IterativeError::Breakdown(stats) => stats,
+        }
+    }
+}
+
+impl<N: fmt::Display> fmt::Display for IterativeError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IterativeError::MaxIterations(stats) => write!(
+                f,
+                "no convergence after {} iterations, residual norm {}",
+                stats.iterations, stats.residual_norm
+            ),
+            IterativeError::Breakdown(stats) => write!(
+                f,
+                "breakdown after {} iterations, residual norm {}",
+                stats.iterations, stats.residual_norm
+            ),
+        }
+    }
+}
+
+impl<N: fmt::Debug + fmt::Display> std::error::Error for IterativeError<N> {}
+
+fn dot<N: Float>(x: &[N], y: &[N]) -> N {
+    x.iter().zip(y).fold(N::zero(), |acc, (&x, &y)| acc + x * y)
+}
+
+fn norm<N: Float>(x: &[N]) -> N {
+    dot(x, x).sqrt()
+}
+
+/// Compute `y = y + a * x`
+fn axpy<N: Float>(a: N, x: &[N], y: &mut [N]) {
+    for (y, &x) in y.iter_mut().zip(x) {
+        *y = *y + a * x;
+    }
+}
+
+/// Compute the residual `r = b - A * x`
+fn residual<N, A>(op: &A, rhs: &[N], x: &[N], r: &mut [N])
+where
+    N: Float,
+    A: LinearOperator<N> + ?Sized,
+{
+    op.apply(x, r);
+    for (r, &b) in r.iter_mut().zip(rhs) {
+        *r = b - *r;
+    }
+}
+
+/// Solve a system whose right hand side is zero, whose solution is zero.
+/// A tolerance relative to the norm of the right hand side could not
+/// accept any other iterate.
+fn solve_zero_rhs<N: Float>(x: &mut [N]) -> SolveStats<N> {
+    x.iter_mut().for_each(|x| *x = N::zero());
+    SolveStats {
+        iterations: 0,
+        residual_norm: N::zero(),
+        history: vec![N::zero()],
+    }
+}
+
+fn check_dimensions<N, A>(op: &A, rhs: &[N], x: &[N])
+where
+    A: LinearOperator<N> + ?Sized,
+{
+    let (rows, cols) = op.shape();
+    assert_eq!(rows, cols, "Non square operator passed to solver");
+    assert_eq!(rows, rhs.len(), "Dimension mismatch");
+    assert_eq!(cols, x.len(), "Dimension mismatch");
+}
+
+/// Builder pattern structure to configure and run the preconditioned
+/// conjugate gradient method.
+///
+/// The conjugate gradient method solves systems whose matrix is symmetric
+/// positive definite, and the preconditioner should also be symmetric
+/// positive definite. The solve stops when the norm of the residual
+/// `b - A * x` is not larger than the tolerance times the norm of `b`, a
+/// zero `b` giving a zero solution.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::iterative::{ConjugateGradient, JacobiPreconditioner};
+/// // the 1D laplacian
+/// let lap: sprs::CsMat<f64> =
+///     sprs::diags((5, 5), &[&[-1.][..], &[2.], &[-1.]], &[-1, 0, 1]);
+/// let rhs = [1., 0., 0., 0., 1.];
+/// let mut x = [0.; 5];
+/// let precond = JacobiPreconditioner::new(&lap).unwrap();
+/// let stats = ConjugateGradient::new()
+///     .tolerance(1e-10)
+///     .solve(&lap, &precond, &rhs, &mut x)
+///     .unwrap();
+/// assert!(stats.residual_norm < 1e-10);
+/// assert!(x.iter().all(|&x| (x - 1.).abs() < 1e-8));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConjugateGradient<N> {
+    tolerance: N,
+    max_iter: Option<usize>,
+}
+
+impl<N: Float> Default for ConjugateGradient<N> {
+    fn default() -> Self {
+        Self {
+            tolerance: N::from(1e-8).unwrap(),
+            max_iter: None,
+        }
+    }
+}
+
+impl<N: Float> ConjugateGradient<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the relative tolerance on the residual norm, `1e-8` by default
+    pub fn tolerance(self, tolerance: N) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    /// Set the maximum number of iterations, ten times the dimension of
+    /// the system by default
+    pub fn max_iter(self, max_iter: usize) -> Self {
+        Self {
+            max_iter: Some(max_iter),
+            ..self
+        }
+    }
+
+    /// Solve the system `A * x = rhs`, `x` holding the initial guess of
+    /// the solution on input and the solution on output.
+    ///
+    /// # Errors
+    ///
+    /// - `MaxIterations` if the tolerance is not reached in time,
+    /// - `Breakdown` if the operator or the preconditioner is found not to
+    ///   be positive definite.
+    ///
+    /// # Panics
+    ///
+    /// - if the operator is not square,
+    /// - if the dimensions of `rhs` and `x` do not match the operator.
+    pub fn solve<A, P>(
+        &self,
+        op: &A,
+        precond: &P,
+        rhs: &[N],
+        x: &mut [N],
+    ) -> Result<SolveStats<N>, IterativeError<N>>
+    where
+        A: LinearOperator<N> + ?Sized,
+        P: Preconditioner<N> + ?Sized,
+    {
+        check_dimensions(op, rhs, x);
+        let n = rhs.len();
+        let max_iter = self.max_iter.unwrap_or(10 * n);
+        let rhs_norm = norm(rhs);
+        if rhs_norm == N::zero() {
+            return Ok(solve_zero_rhs(x));
+        }
+        let threshold = self.tolerance * rhs_norm;
+        let mut r = vec![N::zero(); n];
+        residual(op, rhs, x, &mut r);
+        let mut z = vec![N::zero(); n];
+        precond.apply(&r, &mut z);
+        let mut p = z.clone();
+        let mut ap = vec![N::zero(); n];
+        let mut rz = dot(&r, &z);
+        let mut stats = SolveStats {
+            iterations: 0,
+            residual_norm: norm(&r),
+            history: vec![norm(&r)],
+        };
+        while stats.residual_norm > threshold {
+            if stats.iterations == max_iter {
+                return Err(IterativeError::MaxIterations(stats));
+            }
+            op.apply(&p, &mut ap);
+            let pap = dot(&p, &ap);
+            if !(pap > N::zero() && rz > N::zero()) {
+                return Err(IterativeError::Breakdown(stats));
+            }
+            let alpha = rz / pap;
+            axpy(alpha, &p, x);
+            axpy(-alpha, &ap, &mut r);
+            stats.iterations += 1;
+            stats.residual_norm = norm(&r);
+            stats.history.push(stats.residual_norm);
+
+            precond.apply(&r, &mut z);
+            let rz_next = dot(&r, &z);
+            let beta = rz_next / rz;
+            rz = rz_next;
+            for (p, &z) in p.iter_mut().zip(&z) {
+                *p = z + beta * *p;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// The side of the operator on which a preconditioner is applied
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PreconditioningSide {
+    /// Solve `M^-1 * A * x = M^-1 * b`. The tolerance then applies to the
+    /// residual of this preconditioned system.
+    Left,
+    /// Solve `A * M^-1 * y = b`, then compute `x = M^-1 * y`. The tolerance
+    /// then applies to the residual of the original system.
+    Right,
+}
+
+/// A linear map applied to dense vectors, computing `y = f(x)`
+type Apply<'a, N> = dyn FnMut(&[N], &mut [N]) + 'a;
+
+/// Run a solver which applies its operator and preconditioner through
+/// `solver(op, precond, rhs, x)`, with the preconditioner moved to the
+/// requested side.
+fn solve_preconditioned<N, A, P, S>(
+    side: PreconditioningSide,
+    op: &A,
+    precond: &P,
+    rhs: &[N],
+    x: &mut [N],
+    solver: S,
+) -> Result<SolveStats<N>, IterativeError<N>>
+where
+    N: Float,
+    A: LinearOperator<N> + ?Sized,
+    P: Preconditioner<N> + ?Sized,
+    S: FnOnce(
+        &mut Apply<N>,
+        &mut Apply<N>,
+        &[N],
+        &mut [N],
+    ) -> Result<SolveStats<N>, IterativeError<N>>,
+{
+    check_dimensions(op, rhs, x);
+    match side {
+        PreconditioningSide::Left => {
+            let mut tmp = vec![N::zero(); rhs.len()];
+            let mut left_op = |x: &[N], y: &mut [N]| {
+                op.apply(x, &mut tmp);
+                precond.apply(&tmp, y);
+            };
+            let mut identity = |r: &[N], z: &mut [N]| z.copy_from_slice(r);
+            let mut left_rhs = vec![N::zero(); rhs.len()];
+            precond.apply(rhs, &mut left_rhs);
+            solver(&mut left_op, &mut identity, &left_rhs, x)
+        }
+        PreconditioningSide::Right => {
+            let mut right_op = |x: &[N], y: &mut [N]| op.apply(x, y);
+            let mut right_precond = |r: &[N], z: &mut [N]| precond.apply(r, z);
+            solver(&mut right_op, &mut right_precond, rhs, x)
+        }
+    }
+}
+
+/// Builder pattern structure to configure and run the preconditioned
+/// biconjugate gradient stabilized method (BiCGSTAB).
+///
+/// BiCGSTAB solves general square systems with a fixed amount of memory, but
+/// its convergence is irregular and it can break down. The solve stops when
+/// the norm of the residual is not larger than the tolerance times the norm
+/// of the right hand side.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::iterative::{BiCgStab, JacobiPreconditioner};
+/// // a non symmetric tridiagonal matrix
+/// let mat: sprs::CsMat<f64> =
+///     sprs::diags((5, 5), &[&[-2.][..], &[4.], &[-1.]], &[-1, 0, 1]);
+/// let rhs = [3., 1., 1., 1., 2.];
+/// let mut x = [0.; 5];
+/// let precond = JacobiPreconditioner::new(&mat).unwrap();
+/// let stats = BiCgStab::new()
+///     .tolerance(1e-10)
+///     .solve(&mat, &precond, &rhs, &mut x)
+///     .unwrap();
+/// assert!(stats.residual_norm < 1e-9);
+/// assert!(x.iter().all(|&x| (x - 1.).abs() < 1e-8));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BiCgStab<N> {
+    tolerance: N,
+    max_iter: Option<usize>,
+    side: PreconditioningSide,
+}
+
+impl<N: Float> Default for BiCgStab<N> {
+    fn default() -> Self {
+        Self {
+            tolerance: N::from(1e-8).unwrap(),
+            max_iter: None,
+            side: PreconditioningSide::Right,
+        }
+    }
+}
+
+impl<N: Float> BiCgStab<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the relative tolerance on the residual norm, `1e-8` by default
+    pub fn tolerance(self, tolerance: N) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    /// Set the maximum number of iterations, ten times the dimension of
+    /// the system by default
+    pub fn max_iter(self, max_iter: usize) -> Self {
+        Self {
+            max_iter: Some(max_iter),
+            ..self
+        }
+    }
+
+    /// Set the side on which the preconditioner is applied, right by
+    /// default
+    pub fn preconditioning(self, side: PreconditioningSide) -> Self {
+        Self { side, ..self }
+    }
+
+    /// Solve the system `A * x = rhs`, `x` holding the initial guess of
+    /// the solution on input and the solution on output.
+    ///
+    /// # Errors
+    ///
+    /// - `MaxIterations` if the tolerance is not reached in time,
+    /// - `Breakdown` if the method cannot continue, which can happen for
+    ///   singular or strongly indefinite systems.
+    ///
+    /// # Panics
+    ///
+    /// - if the operator is not square,
+    /// - if the dimensions of `rhs` and `x` do not match the operator.
+    pub fn solve<A, P>(
+        &self,
+        op: &A,
+        precond: &P,
+        rhs: &[N],
+        x: &mut [N],
+    ) -> Result<SolveStats<N>, IterativeError<N>>
+    where
+        A: LinearOperator<N> + ?Sized,
+        P: Preconditioner<N> + ?Sized,
+    {
+        let max_iter = self.max_iter.unwrap_or(10 * rhs.len());
+        solve_preconditioned(self.side, op, precond, rhs, x, |op, pr, b, x| {
+            bicgstab(op, pr, b, x, self.tolerance, max_iter)
+        })
+    }
+}
+
+/// Right preconditioned BiCGSTAB, following van der Vorst, 1992
+fn bicgstab<N: Float>(
+    op: &mut Apply<N>,
+    precond: &mut Apply<N>,
+    rhs: &[N],
+    x: &mut [N],
+    tolerance: N,
+    max_iter: usize,
+) -> Result<SolveStats<N>, IterativeError<N>> {
+    let n = rhs.len();
+    let rhs_norm = norm(rhs);
+    if rhs_norm == N::zero() {
+        return Ok(solve_zero_rhs(x));
+    }
+    let threshold = tolerance * rhs_norm;
+    let mut r = vec![N::zero(); n];
+    op(x, &mut r);
+    for (r, &b) in r.iter_mut().zip(rhs) {
+        *r = b - *r;
+    }
+    let r_hat = r.clone();
+    let (mut rho, mut alpha, mut omega) = (N::one(), N::one(), N::one());
+    let mut p = vec![N::zero(); n];
+    let mut v = vec![N::zero(); n];
+    let mut p_hat = vec![N::zero(); n];
+    let mut s_hat = vec![N::zero(); n];
+    let mut t = vec![N::zero(); n];
+    let mut stats = SolveStats {
+        iterations: 0,
+        residual_norm: norm(&r),
+        history: vec![norm(&r)],
+    };
+    while stats.residual_norm > threshold {
+        if stats.iterations == max_iter {
+            return Err(IterativeError::MaxIterations(stats));
+        }
+        let rho_next = dot(&r_hat, &r);
+        if rho_next == N::zero() || omega == N::zero() {
+            return Err(IterativeError::Breakdown(stats));
+        }
+        let beta = (rho_next / rho) * (alpha / omega);
+        rho = rho_next;
+        for ((p, &r), &v) in p.iter_mut().zip(&r).zip(&v) {
+            *p = r + beta * (*p - omega * v);
+        }
+        precond(&p, &mut p_hat);
+        op(&p_hat, &mut v);
+        let r_hat_v = dot(&r_hat, &v);
+        if r_hat_v == N::zero() {
+            return Err(IterativeError::Breakdown(stats));
+        }
+        alpha = rho / r_hat_v;
+        axpy(alpha, &p_hat, x);
+        // r now holds s = r - alpha * v
+        axpy(-alpha, &v, &mut r);
+        stats.iterations += 1;
+        if norm(&r) <= threshold {
+            stats.residual_norm = norm(&r);
+            stats.history.push(stats.residual_norm);
+            break;
+        }
+        precond(&r, &mut s_hat);
+        op(&s_hat, &mut t);
+        let t_t = dot(&t, &t);
+        omega = if t_t == N::zero() {
+            N::zero()
+        } else {
+            dot(&t, &r) / t_t
+        };
+        axpy(omega, &s_hat, x);
+        axpy(-omega, &t, &mut r);
+        stats.residual_norm = norm(&r);
+        stats.history.push(stats.residual_norm);
+    }
+    Ok(stats)
+}
+
+/// Builder pattern structure to configure and run the preconditioned
+/// generalized minimal residual method, restarted every `m` iterations
+/// (GMRES(m)).
+///
+/// GMRES solves general square systems by minimizing the residual over a
+/// growing Krylov subspace, whose basis is stored. Restarting bounds the
+/// memory to `m + 1` vectors, at the cost of a slower convergence. The solve
+/// stops when the norm of the residual is not larger than the tolerance
+/// times the norm of the right hand side. An iteration is one step of the
+/// Arnoldi process, which applies the operator once. Each restart applies
+/// the operator once more to recompute the residual, which is not counted
+/// as an iteration, and which gives the reported residual norm.
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::iterative::{Gmres, IdentityPreconditioner};
+/// let mat: sprs::CsMat<f64> =
+///     sprs::diags((5, 5), &[&[-2.][..], &[4.], &[-1.]], &[-1, 0, 1]);
+/// let rhs = [3., 1., 1., 1., 2.];
+/// let mut x = [0.; 5];
+/// let stats = Gmres::new()
+///     .restart(5)
+///     .tolerance(1e-10)
+///     .solve(&mat.to_csc(), &IdentityPreconditioner, &rhs, &mut x)
+///     .unwrap();
+/// assert!(stats.iterations <= 5);
+/// assert!(x.iter().all(|&x| (x - 1.).abs() < 1e-8));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Gmres<N> {
+    tolerance: N,
+    max_iter: Option<usize>,
+    restart: usize,
+    side: PreconditioningSide,
+}
+
+impl<N: Float> Default for Gmres<N> {
+    fn default() -> Self {
+        Self {
+            tolerance: N::from(1e-8).unwrap(),
+            max_iter: None,
+            restart: 30,
+            side: PreconditioningSide::Right,
+        }
+    }
+}
+
+impl<N: Float> Gmres<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the relative tolerance on the residual norm, `1e-8` by default
+    pub fn tolerance(self, tolerance: N) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    /// Set the maximum number of iterations, ten times the dimension of
+    /// the system by default
+    pub fn max_iter(self, max_iter: usize) -> Self {
+        Self {
+            max_iter: Some(max_iter),
+            ..self
+        }
+    }
+
+    /// Set the number of iterations between restarts, 30 by default
+    ///
+    /// # Panics
+    ///
+    /// - if `restart` is zero.
+    pub fn restart(self, restart: usize) -> Self {
+        assert!(restart > 0, "The restart length must be positive");
+        Self { restart, ..self }
+    }
+
+    /// Set the side on which the preconditioner is applied, right by
+    /// default
+    pub fn preconditioning(self, side: PreconditioningSide) -> Self {
+        Self { side, ..self }
+    }
+
+    /// Solve the system `A * x = rhs`, `x` holding the initial guess of
+    /// the solution on input and the solution on output.
+    ///
+    /// # Errors
+    ///
+    /// - `MaxIterations` if the tolerance is not reached in time,
+    /// - `Breakdown` if the least squares problem becomes singular, which
+    ///   can happen for singular systems.
+    ///
+    /// # Panics
+    ///
+    /// - if the operator is not square,
+    /// - if the dimensions of `rhs` and `x` do not match the operator.
+    pub fn solve<A, P>(
+        &self,
+        op: &A,
+        precond: &P,
+        rhs: &[N],
+        x: &mut [N],
+    ) -> Result<SolveStats<N>, IterativeError<N>>
+    where
+        A: LinearOperator<N> + ?Sized,
+        P: Preconditioner<N> + ?Sized,
+    {
+        let max_iter = self.max_iter.unwrap_or(10 * rhs.len());
+        let restart = self.restart.min(rhs.len()).max(1);
+        solve_preconditioned(self.side, op, precond, rhs, x, |op, pr, b, x| {
+            gmres(op, pr, b, x, self.tolerance, max_iter, restart)
+        })
+    }
+}
+
+/// Right preconditioned GMRES(m), using the modified Gram-Schmidt process
+/// and Givens rotations to solve the least squares problems, see Saad,
+/// Iterative Methods for Sparse Linear Systems, 2003, section 6.5
+fn gmres<N: Float>(
+    op: &mut Apply<N>,
+    precond: &mut Apply<N>,
+    rhs: &[N],
+    x: &mut [N],
+    tolerance: N,
+    max_iter: usize,
+    restart: usize,
+) -> Result<SolveStats<N>, IterativeError<N>> {
+    let n = rhs.len();
+    let m = restart;
+    let rhs_norm = norm(rhs);
+    if rhs_norm == N::zero() {
+        return Ok(solve_zero_rhs(x));
+    }
+    let threshold = tolerance * rhs_norm;
+    let mut r = vec![N::zero(); n];
+    let residual_norm = |x: &[N], r: &mut [N], op: &mut Apply<N>| {
+        op(x, r);
+        for (r, &b) in r.iter_mut().zip(rhs) {
+            *r = b - *r;
+        }
+        norm(r)
+    };
+    let mut beta = residual_norm(x, &mut r, op);
+    let mut stats = SolveStats {
+        iterations: 0,
+        residual_norm: beta,
+        history: vec![beta],
+    };
+    // the Krylov basis, the Hessenberg matrix by rows, the Givens rotations
+    // and the right hand side of the least squares problem
+    let mut basis = vec![vec![N::zero(); n]; m + 1];
+    let mut hessenberg = vec![vec![N::zero(); m]; m + 1];
+    let mut rotations = vec![(N::zero(), N::zero()); m];
+    let mut g = vec![N::zero(); m + 1];
+    let mut z = vec![N::zero(); n];
+    let mut w = vec![N::zero(); n];
+    while beta > threshold {
+        if stats.iterations == max_iter {
+            return Err(IterativeError::MaxIterations(stats));
+        }
+        basis[0]
+            .iter_mut()
+            .zip(&r)
+            .for_each(|(v, &r)| *v = r / beta);
+        g.iter_mut().for_each(|g| *g = N::zero());
+        g[0] = beta;
+        let mut k = 0;
+        while k < m && stats.iterations < max_iter {
+            precond(&basis[k], &mut z);
+            op(&z, &mut w);
+            for i in 0..=k {
+                let h = dot(&w, &basis[i]);
+                hessenberg[i][k] = h;
+                axpy(-h, &basis[i], &mut w);
+            }
+            let h_next = norm(&w);
+            if h_next > N::zero() {
+                basis[k + 1]
+                    .iter_mut()
+                    .zip(&w)
+                    .for_each(|(v, &w)| *v = w / h_next);
+            }
+            for (i, &(c, s)) in rotations[..k].iter().enumerate() {
+                let (a, b) = (hessenberg[i][k], hessenberg[i + 1][k]);
+                hessenberg[i][k] = c * a + s * b;
+                hessenberg[i + 1][k] = c * b - s * a;
+            }
+            let diag = hessenberg[k][k].hypot(h_next);
+            if diag == N::zero() {
+                return Err(IterativeError::Breakdown(stats));
+            }
+            let (c, s) = (hessenberg[k][k] / diag, h_next / diag);
+            rotations[k] = (c, s);
+            hessenberg[k][k] = diag;
+            g[k + 1] = -s * g[k];
+            g[k] = c * g[k];
+            k += 1;
+            stats.iterations += 1;
+            stats.history.push(g[k].abs());
+            // a null h_next means the Krylov subspace is invariant, so
+            // the solution has been found
+            if g[k].abs() <= threshold || h_next == N::zero() {
+                break;
+            }
+        }
+        // solve the triangular least squares system, then update x
+        let mut y = g[..k].to_vec();
+        for i in (0..k).rev() {
+            for j in i + 1..k {
+                y[i] = y[i] - hessenberg[i][j] * y[j];
+            }
+            y[i] = y[i] / hessenberg[i][i];
+        }
+        w.iter_mut().for_each(|w| *w = N::zero());
+        for (&y, v) in y.iter().zip(&basis) {
+            axpy(y, v, &mut w);
+        }
+        precond(&w, &mut z);
+        axpy(N::one(), &z, x);
+        beta = residual_norm(x, &mut r, op);
+        stats.residual_norm = beta;
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sparse::{CsMat, SymCsMat};
+
+    fn laplacian_2d(n: usize) -> CsMat<f64> {
+        crate::test_data::five_point_stencil(n, |_| 4., [-1.; 4])
+    }
+
+    fn check_solution(mat: &CsMat<f64>, rhs: &[f64], x: &[f64], tol: f64) {
+        let mut r = vec![0.; rhs.len()];
+        residual(mat, rhs, x, &mut r);
+        assert!(norm(&r) <= tol * norm(rhs));
+    }
+
+    #[test]
+    fn cg_laplacian() {
+        let mat = laplacian_2d(8);
+        let rhs: Vec<f64> = (0..64).map(|i| (i % 7) as f64 - 3.).collect();
+        let solver = ConjugateGradient::new().tolerance(1e-10);
+
+        let mut x = vec![0.; 64];
+        let stats = solver
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+        check_solution(&mat, &rhs, &x, 1e-10);
+        assert_eq!(stats.history.len(), stats.iterations + 1);
+        assert_eq!(stats.history.last(), Some(&stats.residual_norm));
+        // in exact arithmetic, CG converges in at most n iterations
+        assert!(stats.iterations <= 64);
+
+        let precond = JacobiPreconditioner::new(&mat).unwrap();
+        let mut x_jacobi = vec![0.; 64];
+        solver
+            .solve(&mat.to_csc(), &precond, &rhs, &mut x_jacobi)
+            .unwrap();
+        check_solution(&mat, &rhs, &x_jacobi, 1e-10);
+
+        let sym = SymCsMat::from_full(&mat);
+        let mut x_sym = vec![0.; 64];
+        let stats_sym = solver
+            .solve(&sym, &IdentityPreconditioner, &rhs, &mut x_sym)
+            .unwrap();
+        assert_eq!(stats_sym.iterations, stats.iterations);
+        check_solution(&mat, &rhs, &x_sym, 1e-10);
+
+        // starting from the solution requires no iteration
+        let stats = solver
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+        assert_eq!(stats.iterations, 0);
+    }
+
+    #[test]
+    fn cg_failures() {
+        let mat = laplacian_2d(8);
+        let rhs = vec![1.; 64];
+        let mut x = vec![0.; 64];
+        let res = ConjugateGradient::new().max_iter(2).solve(
+            &mat,
+            &IdentityPreconditioner,
+            &rhs,
+            &mut x,
+        );
+        match res {
+            Err(IterativeError::MaxIterations(stats)) => {
+                assert_eq!(stats.iterations, 2);
+                assert_eq!(stats.history.len(), 3);
+            }
+            _ => panic!("expected the iteration limit to be reached"),
+        }
+
+        // a negative definite matrix
+        let neg = mat.map(|&x| -x);
+        let mut x = vec![0.; 64];
+        let res = ConjugateGradient::new().solve(
+            &neg,
+            &IdentityPreconditioner,
+            &rhs,
+            &mut x,
+        );
+        match res {
+            Err(err @ IterativeError::Breakdown(_)) => {
+                assert_eq!(err.stats().iterations, 0);
+            }
+            _ => panic!("expected a breakdown"),
+        }
+    }
+
+    #[test]
+    fn cg_zero_rhs() {
+        let mat = laplacian_2d(8);
+        let rhs = vec![0.; 64];
+        let mut x = vec![1.; 64];
+        let stats = ConjugateGradient::new()
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+        assert_eq!(stats.iterations, 0);
+        assert_eq!(stats.residual_norm, 0.);
+        assert!(x.iter().all(|&x| x == 0.));
+    }
+
+    /// A centered finite difference discretization of an advection
+    /// diffusion problem on a grid, which is not symmetric
+    fn advection_diffusion_2d(n: usize) -> CsMat<f64> {
+        crate::test_data::five_point_stencil(
+            n,
+            |k| 4. + (k % 3) as f64,
+            [-1., -1.6, -0.4, -1.],
+        )
+    }
+
+    #[test]
+    fn bicgstab_advection_diffusion() {
+        let mat = advection_diffusion_2d(8);
+        let rhs: Vec<f64> = (0..64).map(|i| (i % 5) as f64 - 2.).collect();
+        let precond = JacobiPreconditioner::new(&mat).unwrap();
+        for &side in &[PreconditioningSide::Left, PreconditioningSide::Right] {
+            let solver = BiCgStab::new().tolerance(1e-10).preconditioning(side);
+            for op in &[mat.clone(), mat.to_csc()] {
+                let mut x = vec![0.; 64];
+                let stats = solver.solve(op, &precond, &rhs, &mut x).unwrap();
+                check_solution(&mat, &rhs, &x, 1e-8);
+                assert_eq!(stats.history.last(), Some(&stats.residual_norm));
+            }
+        }
+        // the right preconditioned residual is the true one
+        let mut x = vec![0.; 64];
+        let stats = BiCgStab::new()
+            .tolerance(1e-10)
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+        check_solution(&mat, &rhs, &x, 1e-10);
+        assert!(stats.residual_norm <= 1e-10 * norm(&rhs));
+    }
+
+    #[test]
+    fn gmres_advection_diffusion() {
+        let mat = advection_diffusion_2d(8);
+        let rhs: Vec<f64> = (0..64).map(|i| (i % 5) as f64 - 2.).collect();
+        let precond = JacobiPreconditioner::new(&mat).unwrap();
+        for &side in &[PreconditioningSide::Left, PreconditioningSide::Right] {
+            let solver = Gmres::new()
+                .tolerance(1e-10)
+                .restart(10)
+                .preconditioning(side);
+            for op in &[mat.clone(), mat.to_csc()] {
+                let mut x = vec![0.; 64];
+                let stats = solver.solve(op, &precond, &rhs, &mut x).unwrap();
+                check_solution(&mat, &rhs, &x, 1e-8);
+                assert_eq!(stats.history.len(), stats.iterations + 1);
+            }
+        }
+
+        // without restarts, the residual estimates never increase and the
+        // method converges in at most n iterations
+        let mut x = vec![0.; 64];
+        let full = Gmres::new()
+            .tolerance(1e-10)
+            .restart(64)
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+        check_solution(&mat, &rhs, &x, 1e-10);
+        assert!(full.iterations <= 64);
+        assert!(full.history.windows(2).all(|w| w[1] <= w[0] * (1. + 1e-12)));
+
+        let mut x = vec![0.; 64];
+        let restarted = Gmres::new()
+            .tolerance(1e-10)
+            .restart(4)
+            .solve(&mat, &IdentityPreconditioner, &rhs, &mut x)
+            .unwrap();
+        check_solution(&mat, &rhs, &x, 1e-10);
+        assert!(restarted.iterations >= full.iterations);
+    }
+
+    #[test]
+    fn nonsymmetric_zero_rhs() {
+        let mat = advection_diffusion_2d(8);
+        let precond = JacobiPreconditioner::new(&mat).unwrap();
+        let rhs = vec![0.; 64];
+        for &side in &[PreconditioningSide::Left, PreconditioningSide::Right] {
+            let mut x = vec![1.; 64];
+            let stats = BiCgStab::new()
+                .preconditioning(side)
+                .solve(&mat, &precond, &rhs, &mut x)
+                .unwrap();
+            assert_eq!(stats.iterations, 0);
+            assert!(x.iter().all(|&x| x == 0.));
+
+            let mut x = vec![1.; 64];
+            let stats = Gmres::new()
+                .preconditioning(side)
+                .solve(&mat, &precond, &rhs, &mut x)
+                .unwrap();
+            assert_eq!(stats.iterations, 0);
+            assert!(x.iter().all(|&x| x == 0.));
+        }
+    }
+
+    #[test]
+    fn nonsymmetric_failures() {
+        let mat = advection_diffusion_2d(8);
+        let rhs = vec![1.; 64];
+        let mut x = vec![0.; 64];
+        let res = BiCgStab::new().max_iter(2).solve(
+            &mat,
+            &IdentityPreconditioner,
+            &rhs,
+            &mut x,
+        );
+        match res {
+            Err(IterativeError::MaxIterations(stats)) => {
+                assert_eq!(stats.iterations, 2);
+            }
+            _ => panic!("expected the iteration limit to be reached"),
+        }
+
+        let mut x = vec![0.; 64];
+        let res = Gmres::new().restart(2).max_iter(3).solve(
+            &mat,
+            &IdentityPreconditioner,
+            &rhs,
+            &mut x,
+        );
+        match res {
+            Err(IterativeError::MaxIterations(stats)) => {
+                assert_eq!(stats.iterations, 3);
+                assert_eq!(stats.history.len(), 4);
+            }
+            _ => panic!("expected the iteration limit to be reached"),
+        }
+
+        // a singular matrix whose range does not contain the right hand side
+        let singular = CsMat::new((2, 2), vec![0, 1, 1], vec![1], vec![1.]);
+        let mut x = vec![0.; 2];
+        let res = BiCgStab::new().solve(
+            &singular,
+            &IdentityPreconditioner,
+            &[1., 0.],
+            &mut x,
+        );
+        assert!(matches!(res, Err(IterativeError::Breakdown(_))));
+    }
+
+    #[test]
+    fn jacobi_singular() {
+        let mat = CsMat::new((2, 2), vec![0, 1, 2], vec![0, 0], vec![1., 2.]);
+        assert_eq!(
+            JacobiPreconditioner::new(&mat).unwrap_err(),
+            SprsError::SingularMatrix
+        );
+    }
+}
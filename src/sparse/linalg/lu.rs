@@ -0,0 +1,629 @@
+//! Sparse LU factorization with threshold partial pivoting
+//!
+//! This module computes the factorization `P A Q = L U` of a general square
+//! matrix, where `L` is unit lower triangular, `U` is upper triangular, `P`
+//! is the row permutation chosen by partial pivoting and `Q` a fill-in
+//! reducing column permutation. It uses the left-looking algorithm of
+//! Gilbert and Peierls, Sparse partial pivoting in time proportional to
+//! arithmetic operations, 1988, where each column of the factors is
+//! obtained by a sparse triangular solve with the previous columns of `L`.
+//!
+//! The computation is split into a symbolic step, `LuSymbolic`, computing
+//! the column ordering, and a numeric step, `LuNumeric`. A matrix with the
+//! same sparsity pattern can then be refactored with `LuNumeric::refactor`,
+//! which reuses the pivots and the structure of the factors.
+
+use num_traits::Float;
+
+use crate::errors::SprsError;
+use crate::indexing::SpIndex;
+use crate::sparse::linalg::ordering::reverse_cuthill_mckee;
+use crate::sparse::linalg::trisolve;
+use crate::sparse::permutation::{PermOwnedI, PermViewI};
+use crate::sparse::prelude::*;
+use crate::sparse::CompressedStorage::CSC;
+use crate::stack::{self, DStack};
+use crate::FillInReduction;
+
+/// Builder pattern structure to customize a sparse LU factorization
+///
+/// # Example
+///
+/// ```rust
+/// use sprs::linalg::lu::Lu;
+/// use sprs::{CsMat, FillInReduction};
+/// // a matrix with a zero diagonal, which needs pivoting
+/// let mat: CsMat<f64> = CsMat::new_csc(
+///     (3, 3),
+///     vec![0, 2, 4, 6],
+///     vec![1, 2, 0, 2, 0, 1],
+///     vec![3., 1., 1., 2., 2., 1.],
+/// );
+/// let lu = Lu::new()
+///     .fill_in_reduction(FillInReduction::NoReduction)
+///     .numeric(mat.view())
+///     .unwrap();
+/// let x = lu.solve(&vec![3., 4., 3.]);
+/// assert!(x.iter().all(|&x| (x - 1.).abs() < 1e-12));
+/// ```
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Lu<N> {
+    pivot_threshold: N,
+    fill_red_method: FillInReduction,
+}
+
+impl<N: Float> Default for Lu<N> {
+    fn default() -> Self {
+        Self {
+            pivot_threshold: N::one(),
+            fill_red_method: FillInReduction::ReverseCuthillMcKee,
+        }
+    }
+}
+
+/// Structure to hold the symbolic analysis of a sparse LU factorization
+#[derive(Debug, Clone)]
+pub struct LuSymbolic<I: SpIndex> {
+    col_perm: PermOwnedI<I>,
+}
+
+/// Structure to hold a numeric sparse LU factorization
+#[derive(Debug, Clone)]
+pub struct LuNumeric<N, I: SpIndex> {
+    symbolic: LuSymbolic<I>,
+    row_perm: PermOwnedI<I>,
+    l: CsMatI<N, I>,
+    u: CsMatI<N, I>,
+}
+
+impl<N: Float + Default> Lu<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the threshold for the choice of the pivots, 1 by default.
+    ///
+    /// The diagonal entry of a column is chosen as pivot if its magnitude
+    /// is at least `threshold` times the largest magnitude of the entries
+    /// which can be chosen. Otherwise the entry of largest magnitude is
+    /// chosen. A threshold of 1 gives the usual partial pivoting, while
+    /// smaller thresholds preserve the column ordering more often, at the
+    /// expense of numerical stability.
+    ///
+    /// # Panics
+    ///
+    /// - if the threshold is not in `(0, 1]`.
+    pub fn pivot_threshold(self, threshold: N) -> Self {
+        assert!(
+            threshold > N::zero() && threshold <= N::one(),
+            "The pivot threshold should be in (0, 1]"
+        );
+        Self {
+            pivot_threshold: threshold,
+            ..self
+        }
+    }
+
+    /// Set the column ordering method, `ReverseCuthillMcKee` on the
+    /// structure of `A + A^T` by default.
+    pub fn fill_in_reduction(self, method: FillInReduction) -> Self {
+        Self {
+            fill_red_method: method,
+            ..self
+        }
+    }
+
+    /// Compute the column permutation of the given matrix.
+    ///
+    /// # Panics
+    ///
+    /// - if the method is `CAMDSuiteSparse`, which is only available in
+    ///   `sprs-ldl`.
+    pub fn col_perm<I>(&self, mat: CsMatViewI<N, I>) -> PermOwnedI<I>
+    where
+        I: SpIndex,
+    {
+        match self.fill_red_method {
+            FillInReduction::NoReduction => PermOwnedI::identity(mat.cols()),
+            FillInReduction::ReverseCuthillMcKee => {
+                // the ordering requires a symmetric structure
+                let pattern = mat.map(|_| 1u8);
+                let sym_pattern = &pattern + &pattern.transpose_view();
+                reverse_cuthill_mckee(sym_pattern.view()).perm
+            }
+            _ => panic!("Unavailable column ordering method"),
+        }
+    }
+
+    pub fn symbolic<I>(self, mat: CsMatViewI<N, I>) -> LuSymbolic<I>
+    where
+        I: SpIndex,
+    {
+        LuSymbolic::new(mat, self.col_perm(mat))
+    }
+
+    pub fn numeric<I>(
+        self,
+        mat: CsMatViewI<N, I>,
+    ) -> Result<LuNumeric<N, I>, SprsError>
+    where
+        I: SpIndex,
+    {
+        self.symbolic(mat).factor(mat, self.pivot_threshold)
+    }
+}
+
+impl<I: SpIndex> LuSymbolic<I> {
+    /// Prepare the factorization of the given matrix, with the given
+    /// column permutation.
+    ///
+    /// # Panics
+    ///
+    /// - if the matrix is not square,
+    /// - if the permutation dimension does not match the matrix.
+    pub fn new<N>(mat: CsMatViewI<N, I>, col_perm: PermOwnedI<I>) -> Self {
+        assert_eq!(mat.rows(), mat.cols(), "matrix should be square");
+        assert_eq!(col_perm.dim(), mat.cols(), "Dimension mismatch");
+        Self { col_perm }
+    }
+
+    /// The column permutation Q of the factorization
+    pub fn col_perm(&self) -> PermViewI<I> {
+        self.col_perm.view()
+    }
+
+    /// The size of the linear system associated with this factorization
+    #[inline]
+    pub fn problem_size(&self) -> usize {
+        self.col_perm.dim()
+    }
+
+    /// Compute the numeric factorization of the given matrix, with the
+    /// given pivot threshold, see `Lu::pivot_threshold`.
+    ///
+    /// # Errors
+    ///
+    /// - `SingularMatrix` if no non zero pivot can be found for a column.
+    ///
+    /// # Panics
+    ///
+    /// - if the matrix dimension does not match the symbolic analysis.
+    pub fn factor<N>(
+        self,
+        mat: CsMatViewI<N, I>,
+        pivot_threshold: N,
+    ) -> Result<LuNumeric<N, I>, SprsError>
+    where
+        N: Float + Default,
+    {
+        let n = self.problem_size();
+        assert_eq!(mat.shape(), (n, n), "Dimension mismatch");
+        let csc;
+        let mat = if mat.is_csc() {
+            mat
+        } else {
+            csc = mat.to_other_storage();
+            csc.view()
+        };
+        // the columns of L, storing original row indices, with the unit
+        // diagonal first, and the columns of U, storing pivot positions
+        let mut l_indptr = vec![0];
+        let mut l_indices = Vec::new();
+        let mut l_data = Vec::new();
+        let mut u_indptr = vec![0];
+        let mut u_indices = Vec::new();
+        let mut u_data = Vec::new();
+        // the pivot position of each row, once it has been chosen
+        let mut pinv: Vec<Option<usize>> = vec![None; n];
+        let mut x_workspace = vec![N::zero(); n];
+        let mut visited = vec![false; n];
+        let mut dstack = DStack::with_capacity(2 * n.max(1));
+        let mut u_col = Vec::new();
+        for k in 0..n {
+            let diag_row = self.col_perm.at(k);
+            let col = mat.outer_view(diag_row).expect("k is in bounds");
+            // the non zero pattern of x = L \ A[:, diag_row], in topological
+            // order, where the rows without pivot yet are leaves
+            dstack.clear_right();
+            {
+                let (pinv, l_indptr, l_indices) =
+                    (&pinv, &l_indptr, &l_indices);
+                trisolve::reach(
+                    col.iter().map(|(ind, _)| ind),
+                    |ind| {
+                        pinv[ind].map(|j: usize| {
+                            &l_indices[l_indptr[j] + 1..l_indptr[j + 1]]
+                        })
+                    },
+                    &mut dstack,
+                    &mut visited,
+                );
+            }
+            col.scatter(&mut x_workspace);
+            for &ind in dstack.iter_right().map(stack::extract_stack_val) {
+                if let Some(j) = pinv[ind] {
+                    let xj = x_workspace[ind];
+                    for p in l_indptr[j] + 1..l_indptr[j + 1] {
+                        let row = l_indices[p];
+                        x_workspace[row] = x_workspace[row] - l_data[p] * xj;
+                    }
+                }
+            }
+
+            let reached = || dstack.iter_right().map(stack::extract_stack_val);
+            let mut pivot_row = None;
+            let mut max_abs = N::zero();
+            for &ind in reached().filter(|&&ind| pinv[ind].is_none()) {
+                let abs = x_workspace[ind].abs();
+                if pivot_row.is_none() || abs > max_abs {
+                    pivot_row = Some(ind);
+                    max_abs = abs;
+                }
+            }
+            if pivot_row.is_none() || max_abs.is_zero() || max_abs.is_nan() {
+                return Err(SprsError::SingularMatrix);
+            }
+            if pinv[diag_row].is_none()
+                && x_workspace[diag_row].abs() >= pivot_threshold * max_abs
+            {
+                pivot_row = Some(diag_row);
+            }
+            let pivot_row = pivot_row.expect("checked above");
+            let pivot = x_workspace[pivot_row];
+
+            u_col.clear();
+            u_col.extend(
+                reached().filter_map(|&ind| {
+                    pinv[ind].map(|j| (j, x_workspace[ind]))
+                }),
+            );
+            u_col.sort_by_key(|&(j, _)| j);
+            u_col.push((k, pivot));
+            for &(j, val) in &u_col {
+                u_indices.push(j);
+                u_data.push(val);
+            }
+            u_indptr.push(u_indices.len());
+
+            pinv[pivot_row] = Some(k);
+            l_indices.push(pivot_row);
+            l_data.push(N::one());
+            for &ind in reached().filter(|&&ind| pinv[ind].is_none()) {
+                l_indices.push(ind);
+                l_data.push(x_workspace[ind] / pivot);
+            }
+            l_indptr.push(l_indices.len());
+
+            for &ind in reached() {
+                x_workspace[ind] = N::zero();
+                visited[ind] = false;
+            }
+        }
+
+        // express the rows of L in pivot positions
+        let pinv: Vec<usize> = pinv
+            .into_iter()
+            .map(|j| j.expect("all rows pivoted"))
+            .collect();
+        let mut l_col = Vec::new();
+        for k in 0..n {
+            let range = l_indptr[k]..l_indptr[k + 1];
+            l_col.clear();
+            l_col.extend(
+                l_indices[range.clone()]
+                    .iter()
+                    .zip(&l_data[range.clone()])
+                    .map(|(&row, &val)| (pinv[row], val)),
+            );
+            l_col.sort_by_key(|&(j, _)| j);
+            for (p, &(j, val)) in range.zip(&l_col) {
+                l_indices[p] = j;
+                l_data[p] = val;
+            }
+        }
+        let mut row_perm = vec![I::zero(); n];
+        for (row, &j) in pinv.iter().enumerate() {
+            row_perm[j] = I::from_usize(row);
+        }
+        let to_mat = |indptr: Vec<usize>, indices: Vec<usize>, data| {
+            CsMatI::new_trusted(
+                CSC,
+                (n, n),
+                indptr.into_iter().map(I::from_usize).collect(),
+                indices.into_iter().map(I::from_usize).collect(),
+                data,
+            )
+        };
+        Ok(LuNumeric {
+            symbolic: self,
+            row_perm: PermOwnedI::new_trusted(row_perm),
+            l: to_mat(l_indptr, l_indices, l_data),
+            u: to_mat(u_indptr, u_indices, u_data),
+        })
+    }
+}
+
+impl<N, I> LuNumeric<N, I>
+where
+    N: Float + Default,
+    I: SpIndex,
+{
+    /// Compute the LU factorization of the given matrix, with the default
+    /// options of `Lu`.
+    pub fn new(mat: CsMatViewI<N, I>) -> Result<Self, SprsError> {
+        Lu::new().numeric(mat)
+    }
+
+    /// Update the factorization with the given matrix, reusing the pivots
+    /// and the structure of the factors. The non-zero pattern of the matrix
+    /// must be included in the structure of the factors, which is the case
+    /// for the pattern of the original matrix.
+    ///
+    /// As the pivots are not chosen again, the factorization can be less
+    /// stable than a new one if the values change significantly.
+    ///
+    /// # Errors
+    ///
+    /// - `SingularMatrix` if a pivot becomes zero,
+    /// - `IllegalArguments` if the matrix has an entry outside of the
+    ///   structure of the factors.
+    pub fn refactor(&mut self, mat: CsMatViewI<N, I>) -> Result<(), SprsError> {
+        let n = self.problem_size();
+        assert_eq!(mat.shape(), (n, n), "Dimension mismatch");
+        let csc;
+        let mat = if mat.is_csc() {
+            mat
+        } else {
+            csc = mat.to_other_storage();
+            csc.view()
+        };
+        let mut l_data = vec![N::zero(); self.l.nnz()];
+        let mut u_data = vec![N::zero(); self.u.nnz()];
+        let mut x_workspace = vec![N::zero(); n];
+        let mut in_structure = vec![false; n];
+        let (l_indptr, l_indices) = (self.l.indptr(), self.l.indices());
+        let (u_indptr, u_indices) = (self.u.indptr(), self.u.indices());
+        for k in 0..n {
+            let u_range = u_indptr[k].index()..u_indptr[k + 1].index();
+            let l_range = l_indptr[k].index()..l_indptr[k + 1].index();
+            let structure = u_indices[u_range.clone()]
+                .iter()
+                .chain(&l_indices[l_range.clone()]);
+            for &ind in structure.clone() {
+                in_structure[ind.index()] = true;
+            }
+            let col = mat
+                .outer_view(self.symbolic.col_perm.at(k))
+                .expect("k is in bounds");
+            for (row, &val) in col.iter() {
+                let ind = self.row_perm.at_inv(row);
+                if !in_structure[ind] {
+                    return Err(SprsError::IllegalArguments(
+                        "Entry outside of the structure of the factors",
+                    ));
+                }
+                x_workspace[ind] = val;
+            }
+            for &ind in structure {
+                in_structure[ind.index()] = false;
+            }
+            // the pivot positions in U are sorted, which is a topological
+            // order for the solve with L
+            for &j in &u_indices[u_range.start..u_range.end - 1] {
+                let j = j.index();
+                let xj = x_workspace[j];
+                for p in l_indptr[j].index() + 1..l_indptr[j + 1].index() {
+                    let row = l_indices[p].index();
+                    x_workspace[row] = x_workspace[row] - l_data[p] * xj;
+                }
+            }
+            for p in u_range.clone() {
+                let j = u_indices[p].index();
+                u_data[p] = x_workspace[j];
+                x_workspace[j] = N::zero();
+            }
+            let pivot = u_data[u_range.end - 1];
+            if pivot.is_zero() || pivot.is_nan() {
+                return Err(SprsError::SingularMatrix);
+            }
+            l_data[l_range.start] = N::one();
+            for p in l_range.start + 1..l_range.end {
+                let row = l_indices[p].index();
+                l_data[p] = x_workspace[row] / pivot;
+                x_workspace[row] = N::zero();
+            }
+        }
+        self.l.data_mut().copy_from_slice(&l_data);
+        self.u.data_mut().copy_from_slice(&u_data);
+        Ok(())
+    }
+
+    /// Solve the system A x = rhs
+    pub fn solve<V>(&self, rhs: &V) -> Vec<N>
+    where
+        V: std::ops::Deref<Target = [N]>,
+    {
+        let mut y = &self.row_perm * &rhs[..];
+        // the pivots are non zero by construction
+        trisolve::lsolve_csc_dense_rhs(self.l.view(), &mut y)
+            .expect("non zero diagonal");
+        trisolve::usolve_csc_dense_rhs(self.u.view(), &mut y)
+            .expect("non zero pivots");
+        let qinv = self.symbolic.col_perm.inv();
+        &qinv * &y[..]
+    }
+}
+
+impl<N, I: SpIndex> LuNumeric<N, I> {
+    /// The unit lower triangular factor L, in CSC storage
+    pub fn l(&self) -> CsMatViewI<N, I> {
+        self.l.view()
+    }
+
+    /// The upper triangular factor U, in CSC storage
+    pub fn u(&self) -> CsMatViewI<N, I> {
+        self.u.view()
+    }
+
+    /// The row permutation P chosen by pivoting
+    pub fn row_perm(&self) -> PermViewI<I> {
+        self.row_perm.view()
+    }
+
+    /// The column permutation Q
+    pub fn col_perm(&self) -> PermViewI<I> {
+        self.symbolic.col_perm()
+    }
+
+    /// The size of the linear system associated with this factorization
+    #[inline]
+    pub fn problem_size(&self) -> usize {
+        self.symbolic.problem_size()
+    }
+
+    /// The number of non-zero entries in L and U
+    #[inline]
+    pub fn nnz(&self) -> usize {
+        self.l.nnz() + self.u.nnz()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sparse::CsMat;
+
+    /// A non symmetric matrix on a grid, with a five point stencil
+    fn grid_matrix(n: usize) -> CsMat<f64> {
+        crate::test_data::five_point_stencil(
+            n,
+            |k| 4. + (k % 3) as f64,
+            [-1., -1.6, -0.4, -1.],
+        )
+    }
+
+    fn residual_norm(mat: &CsMat<f64>, x: &[f64], rhs: &[f64]) -> f64 {
+        let ax = mat * &ndarray::arr1(x);
+        ax.iter()
+            .zip(rhs)
+            .map(|(ax, b)| (ax - b) * (ax - b))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// Check that P A Q = L U
+    fn check_factors(mat: &CsMat<f64>, lu: &LuNumeric<f64, usize>) {
+        let prod = (&lu.l() * &lu.u()).to_dense();
+        let (p, q) = (lu.row_perm(), lu.col_perm());
+        for i in 0..mat.rows() {
+            for j in 0..mat.cols() {
+                let val = mat.get(p.at(i), q.at(j)).cloned().unwrap_or(0.);
+                assert!((prod[[i, j]] - val).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn lu_pivoting() {
+        // | 0 1 2 |
+        // | 3 0 1 |
+        // | 1 2 0 |
+        let mat = CsMat::new(
+            (3, 3),
+            vec![0, 2, 4, 6],
+            vec![1, 2, 0, 2, 0, 1],
+            vec![1., 2., 3., 1., 1., 2.],
+        );
+        let lu = Lu::new()
+            .fill_in_reduction(FillInReduction::NoReduction)
+            .numeric(mat.view())
+            .unwrap();
+        check_factors(&mat, &lu);
+        // the largest entry of the first column is chosen as pivot
+        assert_eq!(lu.row_perm().at(0), 1);
+        assert!(crate::is_upper_triangular(&lu.u()));
+        let x = lu.solve(&vec![3., 4., 3.]);
+        assert!(x.iter().all(|&x| (x - 1.).abs() < 1e-12));
+    }
+
+    #[test]
+    fn lu_grid() {
+        let mat = grid_matrix(8);
+        let rhs: Vec<f64> = (0..64).map(|i| (i % 5) as f64 - 2.).collect();
+        for &method in &[
+            FillInReduction::NoReduction,
+            FillInReduction::ReverseCuthillMcKee,
+        ] {
+            let builder = Lu::new().fill_in_reduction(method);
+            for input in &[mat.clone(), mat.to_csc()] {
+                let lu = builder.numeric(input.view()).unwrap();
+                check_factors(&mat, &lu);
+                let x = lu.solve(&rhs);
+                assert!(residual_norm(&mat, &x, &rhs) < 1e-10);
+            }
+        }
+        // the matrix is diagonally dominant, so a small threshold keeps
+        // the diagonal pivots
+        let lu = Lu::new()
+            .fill_in_reduction(FillInReduction::NoReduction)
+            .pivot_threshold(0.1)
+            .numeric(mat.view())
+            .unwrap();
+        assert!(lu.row_perm().is_identity());
+        assert!(lu.l().rows() == 64);
+    }
+
+    #[test]
+    fn lu_refactor() {
+        let mat = grid_matrix(6);
+        let mut lu = LuNumeric::new(mat.view()).unwrap();
+        let row_perm = lu.row_perm().vec();
+        let nnz = lu.nnz();
+        let new_mat = mat.map(|&x| 2. * x + x * x);
+        lu.refactor(new_mat.view()).unwrap();
+        assert_eq!(lu.row_perm().vec(), row_perm);
+        assert_eq!(lu.nnz(), nnz);
+        check_factors(&new_mat, &lu);
+        let rhs = vec![1.; 36];
+        let x = lu.solve(&rhs);
+        assert!(residual_norm(&new_mat, &x, &rhs) < 1e-10);
+
+        let zero = mat.map(|_| 0.);
+        assert_eq!(
+            lu.refactor(zero.view()).unwrap_err(),
+            SprsError::SingularMatrix
+        );
+
+        // an entry that does not fit in the structure of the factors
+        let mut outside = mat.to_dense();
+        outside[[35, 0]] = 1.;
+        let outside = CsMat::csc_from_dense(outside.view(), 0.);
+        assert_eq!(
+            lu.refactor(outside.view()).unwrap_err(),
+            SprsError::IllegalArguments(
+                "Entry outside of the structure of the factors"
+            )
+        );
+    }
+
+    #[test]
+    fn lu_singular() {
+        // the second column is a multiple of the first one
+        let mat = CsMat::new_csc(
+            (3, 3),
+            vec![0, 2, 4, 5],
+            vec![0, 1, 0, 1, 2],
+            vec![1., 2., 2., 4., 1.],
+        );
+        assert_eq!(
+            LuNumeric::new(mat.view()).unwrap_err(),
+            SprsError::SingularMatrix
+        );
+        // a structurally singular matrix
+        let mat = CsMat::new_csc((2, 2), vec![0, 1, 1], vec![0], vec![1.]);
+        assert_eq!(
+            LuNumeric::new(mat.view()).unwrap_err(),
+            SprsError::SingularMatrix
+        );
+    }
+}
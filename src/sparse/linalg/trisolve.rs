@@ -249,6 +249,61 @@ where
     Ok(())
 }
 
+/// Depth first search of the nodes reachable from `roots` in the graph of
+/// a sparse lower triangular matrix, where `children` gives the indices
+/// stored in the column associated to a node, if any.
+///
+/// On return, the reached nodes are marked in `visited`, and the right
+/// stack of `dstack` holds them in a topological order, ie each node comes
+/// before its children.
+///
+/// Only the current path of the search is kept on the left stack, each of
+/// its nodes being stored as an `Enter(node)` value followed by an
+/// `Exit(pos)` value, where `pos` is the position of the next child of the
+/// node to visit. As the nodes on the path are not on the right stack yet,
+/// a capacity of `2 * n` is enough for `dstack`.
+pub(crate) fn reach<'a, I, R, F>(
+    roots: R,
+    children: F,
+    dstack: &mut DStack<StackVal<usize>>,
+    visited: &mut [bool],
+) where
+    I: 'a + SpIndex,
+    R: IntoIterator<Item = usize>,
+    F: Fn(usize) -> Option<&'a [I]>,
+{
+    for root_ind in roots {
+        if visited[root_ind] {
+            continue;
+        }
+        visited[root_ind] = true;
+        dstack.push_left(StackVal::Enter(root_ind));
+        dstack.push_left(StackVal::Exit(0));
+        while let Some(StackVal::Exit(child_pos)) = dstack.pop_left() {
+            let ind = match dstack.pop_left() {
+                Some(StackVal::Enter(ind)) => ind,
+                _ => unreachable!(),
+            };
+            // resume the iteration on the children where it stopped
+            let children = children(ind).unwrap_or(&[]);
+            let next_child = children[child_pos..]
+                .iter()
+                .position(|child| !visited[child.index()]);
+            match next_child {
+                Some(pos) => {
+                    let child_ind = children[child_pos + pos].index();
+                    dstack.push_left(StackVal::Enter(ind));
+                    dstack.push_left(StackVal::Exit(child_pos + pos + 1));
+                    visited[child_ind] = true;
+                    dstack.push_left(StackVal::Enter(child_ind));
+                    dstack.push_left(StackVal::Exit(0));
+                }
+                None => dstack.push_right(StackVal::Enter(ind)),
+            }
+        }
+    }
+}
+
 /// Sparse triangular CSC / sparse vector solve
 ///
 /// lower_tri_mat is a sparse lower triangular matrix of shape (n, n)
@@ -306,38 +361,17 @@ where
     // |      f       7| | w |     | c |     w = c / l7
 
     // compute the non-zero elements of the result by dfs traversal
-    for (root_ind, _) in rhs.iter() {
-        if visited[root_ind] {
-            continue;
-        }
-        dstack.push_left(StackVal::Enter(root_ind));
-        while let Some(stack_val) = dstack.pop_left() {
-            match stack_val {
-                StackVal::Enter(ind) => {
-                    if visited[ind] {
-                        continue;
-                    }
-                    visited[ind] = true;
-                    dstack.push_left(StackVal::Exit(ind));
-                    if let Some(column) = lower_tri_mat.outer_view(ind) {
-                        for (child_ind, _) in column.iter() {
-                            dstack.push_left(StackVal::Enter(child_ind));
-                        }
-                    } else {
-                        unreachable!();
-                    }
-                }
-                StackVal::Exit(ind) => {
-                    dstack.push_right(StackVal::Enter(ind));
-                }
-            }
-        }
-    }
+    let (indptr, indices) = (lower_tri_mat.indptr(), lower_tri_mat.indices());
+    reach(
+        rhs.iter().map(|(ind, _)| ind),
+        |ind| Some(&indices[indptr[ind].index()..indptr[ind + 1].index()]),
+        dstack,
+        visited,
+    );
 
     // solve for the non-zero values into dense workspace
     rhs.scatter(x_workspace);
     for &ind in dstack.iter_right().map(stack::extract_stack_val) {
-        println!("ind: {}", ind);
         let col = lower_tri_mat.outer_view(ind).expect("ind not in bounds");
         lspsolve_csc_process_col(col, ind, x_workspace)?;
     }
@@ -6,6 +6,9 @@ use std::ops::{Deref, Mul};
 use crate::indexing::SpIndex;
 use crate::sparse::{CompressedStorage, CsMatI, CsMatViewI};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 #[derive(Debug, Clone)]
 enum PermStorage<I, IndStorage>
 where
@@ -251,6 +254,56 @@ where
     }
 }
 
+/// The serialized form of a permutation, which only stores the indices of
+/// non-identity permutations.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct PermutationShadow<IndStorage> {
+    dim: usize,
+    perm: Option<IndStorage>,
+}
+
+#[cfg(feature = "serde")]
+impl<I, IndStorage> Serialize for Permutation<I, IndStorage>
+where
+    I: SpIndex + Serialize,
+    IndStorage: Deref<Target = [I]>,
+{
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let perm = match self.storage {
+            Identity => None,
+            FinitePerm { ref perm, .. } => Some(&perm[..]),
+        };
+        PermutationShadow {
+            dim: self.dim,
+            perm,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, I> Deserialize<'de> for Permutation<I, Vec<I>>
+where
+    I: SpIndex + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let shadow = PermutationShadow::<Vec<I>>::deserialize(deserializer)?;
+        match shadow.perm {
+            None => Ok(Permutation::identity(shadow.dim)),
+            Some(perm) if perm.len() == shadow.dim && perm_is_valid(&perm) => {
+                Ok(Permutation::new_trusted(perm))
+            }
+            Some(_) => Err(serde::de::Error::custom("invalid permutation")),
+        }
+    }
+}
+
 impl<'a, 'b, N, I, IndStorage> Mul<&'a [N]> for &'b Permutation<I, IndStorage>
 where
     IndStorage: 'b + Deref<Target = [I]>,
@@ -274,6 +327,9 @@ where
 }
 
 /// Compute the square matrix resulting from the product P * A * P^T
+///
+/// See `SymCsMatI::transform_papt` for symmetric matrices storing only
+/// their upper triangle.
 pub fn transform_mat_papt<N, I, Iptr>(
     mat: CsMatViewI<N, I, Iptr>,
     perm: PermViewI<I>,
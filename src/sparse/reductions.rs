@@ -0,0 +1,488 @@
+//! Reductions of sparse matrices along an axis
+//!
+//! Following ndarray's conventions, reducing along `Axis(0)` collapses the
+//! rows and gives a result per column, while reducing along `Axis(1)` gives
+//! a result per row. The entries which are not stored are taken into account
+//! as zeros.
+//!
+//! The lanes along the outer dimension of the storage are reduced
+//! independently, while for the other axis the lanes are split between
+//! workers which each scan the whole matrix. The reductions of large
+//! matrices are parallel when the `multi_thread` feature is enabled.
+
+use std::ops::Deref;
+
+use ndarray::{Array1, Axis};
+use num_traits::{FromPrimitive, Num};
+#[cfg(feature = "multi_thread")]
+use rayon::prelude::*;
+
+use crate::indexing::SpIndex;
+use crate::sparse::prelude::*;
+use crate::sparse::CompressedStorage::{CSC, CSR};
+
+/// Number of non-zeros under which reductions are not parallelized
+#[cfg(feature = "multi_thread")]
+const PARALLEL_NNZ_THRESHOLD: usize = 1 << 14;
+
+/// The running state of the search of the first extremal entry of a lane,
+/// whose entries are visited by increasing index
+#[derive(Clone)]
+struct Extremum<N> {
+    best: Option<(N, usize)>,
+    /// The index following the last visited entry
+    next: usize,
+    /// The index of the first entry which is not stored
+    first_zero: Option<usize>,
+}
+
+impl<N: Copy + Num + PartialOrd> Extremum<N> {
+    fn new() -> Self {
+        Self {
+            best: None,
+            next: 0,
+            first_zero: None,
+        }
+    }
+
+    fn visit(&mut self, index: usize, val: N, better: fn(&N, &N) -> bool) {
+        if self.first_zero.is_none() && index != self.next {
+            self.first_zero = Some(self.next);
+        }
+        self.next = index + 1;
+        match self.best {
+            Some((ref best, _)) if !better(&val, best) => (),
+            _ => self.best = Some((val, index)),
+        }
+    }
+
+    fn finish(self, lane_len: usize, better: fn(&N, &N) -> bool) -> (N, usize) {
+        let first_zero = match self.first_zero {
+            None if self.next < lane_len => Some(self.next),
+            first_zero => first_zero,
+        };
+        let zero = N::zero();
+        match (self.best, first_zero) {
+            (Some(best), None) => best,
+            (None, Some(index)) => (zero, index),
+            (Some((val, index)), Some(zero_index)) => {
+                if better(&zero, &val) || (zero == val && zero_index < index) {
+                    (zero, zero_index)
+                } else {
+                    (val, index)
+                }
+            }
+            (None, None) => unreachable!("lanes are not empty"),
+        }
+    }
+}
+
+fn greater<N: PartialOrd>(a: &N, b: &N) -> bool {
+    a > b
+}
+
+fn less<N: PartialOrd>(a: &N, b: &N) -> bool {
+    a < b
+}
+
+impl<N, I, IpS, IS, DS, Iptr> CsMatBase<N, I, IpS, IS, DS, Iptr>
+where
+    I: SpIndex,
+    Iptr: SpIndex,
+    IpS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+{
+    /// Reduce each lane of the matrix along `axis`, by visiting its stored
+    /// entries by increasing index starting from the state `init`, then
+    /// computing the result from the final state and the length of the lane.
+    fn reduce_axis<S, R, V, F>(
+        &self,
+        axis: Axis,
+        init: S,
+        visit: V,
+        finish: F,
+    ) -> Array1<R>
+    where
+        N: Sync,
+        S: Clone + Send + Sync,
+        R: Send,
+        V: Fn(&mut S, usize, &N) + Sync,
+        F: Fn(S, usize) -> R + Sync,
+    {
+        let lane_len = self.lane_len(axis);
+        let outer_lanes = match self.storage() {
+            CSR => axis.index() == 1,
+            CSC => axis.index() == 0,
+        };
+        let outer_dims = self.outer_dims();
+        let indptr = self.indptr();
+        let indices = self.indices();
+        let data = self.data();
+        let outer_range = |k: usize| {
+            indptr[k].index_unchecked()..indptr[k + 1].index_unchecked()
+        };
+        if outer_lanes {
+            let reduce_lane = |k: usize| {
+                let mut state = init.clone();
+                let range = outer_range(k);
+                for (i, val) in indices[range.clone()].iter().zip(&data[range])
+                {
+                    visit(&mut state, i.index_unchecked(), val);
+                }
+                finish(state, lane_len)
+            };
+            #[cfg(feature = "multi_thread")]
+            let res = if self.nnz() < PARALLEL_NNZ_THRESHOLD {
+                (0..outer_dims).map(reduce_lane).collect::<Vec<_>>()
+            } else {
+                (0..outer_dims)
+                    .into_par_iter()
+                    .map(reduce_lane)
+                    .collect::<Vec<_>>()
+            };
+            #[cfg(not(feature = "multi_thread"))]
+            let res = (0..outer_dims).map(reduce_lane).collect::<Vec<_>>();
+            return Array1::from(res);
+        }
+
+        // each chunk of lanes scans the outer dimensions in order, so the
+        // entries of a lane are visited by increasing index
+        let nb_lanes = self.inner_dims();
+        let mut states = vec![init; nb_lanes];
+        let reduce_chunk = |(chunk, states): (usize, &mut [S])| {
+            let start = chunk * states.len();
+            let end = start + states.len();
+            for k in 0..outer_dims {
+                let range = outer_range(k);
+                let lane_indices = &indices[range.clone()];
+                let first =
+                    match lane_indices.binary_search(&I::from_usize(start)) {
+                        Ok(pos) | Err(pos) => pos,
+                    };
+                let entries =
+                    lane_indices[first..].iter().zip(&data[range][first..]);
+                for (i, val) in entries {
+                    let i = i.index_unchecked();
+                    if i >= end {
+                        break;
+                    }
+                    visit(&mut states[i - start], k, val);
+                }
+            }
+        };
+        #[cfg(feature = "multi_thread")]
+        {
+            let nb_chunks = if self.nnz() < PARALLEL_NNZ_THRESHOLD {
+                1
+            } else {
+                rayon::current_num_threads()
+            };
+            let chunk_size = nb_lanes / nb_chunks + 1;
+            states
+                .par_chunks_mut(chunk_size)
+                .enumerate()
+                .for_each(reduce_chunk);
+        }
+        #[cfg(not(feature = "multi_thread"))]
+        {
+            let chunk_size = nb_lanes.max(1);
+            states
+                .chunks_mut(chunk_size)
+                .enumerate()
+                .for_each(reduce_chunk);
+        }
+        states
+            .into_iter()
+            .map(|state| finish(state, lane_len))
+            .collect()
+    }
+
+    /// The length of the lanes reduced along `axis`
+    fn lane_len(&self, axis: Axis) -> usize {
+        match axis.index() {
+            0 => self.rows(),
+            1 => self.cols(),
+            _ => panic!("Axis out of bounds"),
+        }
+    }
+
+    /// Sum the entries of the matrix along `axis`, giving the sum of each
+    /// column for `Axis(0)` and the sum of each row for `Axis(1)`.
+    ///
+    /// # Panics
+    ///
+    /// - if `axis` is neither `Axis(0)` nor `Axis(1)`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ndarray::{arr1, Axis};
+    /// use sprs::CsMat;
+    /// // | 1 0 2 |
+    /// // | 0 0 3 |
+    /// let mat = CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 2], vec![1, 2, 3]);
+    /// assert_eq!(mat.sum_axis(Axis(0)), arr1(&[1, 0, 5]));
+    /// assert_eq!(mat.sum_axis(Axis(1)), arr1(&[3, 3]));
+    /// assert_eq!(mat.nnz_axis(Axis(0)), arr1(&[1, 0, 2]));
+    /// assert_eq!(mat.max_axis(Axis(1)), Some(arr1(&[2, 3])));
+    /// assert_eq!(mat.argmin_axis(Axis(1)), Some(arr1(&[1, 0])));
+    /// ```
+    pub fn sum_axis(&self, axis: Axis) -> Array1<N>
+    where
+        N: Copy + Num + Send + Sync,
+    {
+        self.reduce_axis(
+            axis,
+            N::zero(),
+            |sum, _, &val| *sum = *sum + val,
+            |sum, _| sum,
+        )
+    }
+
+    /// Count the stored entries of the matrix along `axis`, explicit zeros
+    /// included.
+    ///
+    /// # Panics
+    ///
+    /// - if `axis` is neither `Axis(0)` nor `Axis(1)`.
+    pub fn nnz_axis(&self, axis: Axis) -> Array1<usize>
+    where
+        N: Sync,
+    {
+        self.reduce_axis(axis, 0, |nnz, _, _| *nnz += 1, |nnz, _| nnz)
+    }
+
+    /// Compute the mean of the entries of the matrix along `axis`.
+    ///
+    /// Returns `None` if the lanes along `axis` are empty.
+    ///
+    /// # Panics
+    ///
+    /// - if `axis` is neither `Axis(0)` nor `Axis(1)`,
+    /// - if the length of the lanes cannot be converted to `N`.
+    pub fn mean_axis(&self, axis: Axis) -> Option<Array1<N>>
+    where
+        N: Copy + Num + FromPrimitive + Send + Sync,
+    {
+        let lane_len = self.lane_len(axis);
+        if lane_len == 0 {
+            return None;
+        }
+        let len = N::from_usize(lane_len)
+            .expect("Converting the axis length to `N` must not fail");
+        Some(self.sum_axis(axis).mapv(|sum| sum / len))
+    }
+
+    /// The first extremal entry of each lane along `axis`, with its index
+    fn extremum_axis(
+        &self,
+        axis: Axis,
+        better: fn(&N, &N) -> bool,
+    ) -> Option<Array1<(N, usize)>>
+    where
+        N: Copy + Num + PartialOrd + Send + Sync,
+    {
+        if self.lane_len(axis) == 0 {
+            return None;
+        }
+        Some(self.reduce_axis(
+            axis,
+            Extremum::new(),
+            |state, index, &val| state.visit(index, val, better),
+            |state, lane_len| state.finish(lane_len, better),
+        ))
+    }
+
+    /// Compute the maximum of the entries of the matrix along `axis`.
+    ///
+    /// Returns `None` if the lanes along `axis` are empty. The result is
+    /// unspecified if the entries cannot be ordered, eg for NaN values.
+    ///
+    /// # Panics
+    ///
+    /// - if `axis` is neither `Axis(0)` nor `Axis(1)`.
+    pub fn max_axis(&self, axis: Axis) -> Option<Array1<N>>
+    where
+        N: Copy + Num + PartialOrd + Send + Sync,
+    {
+        self.extremum_axis(axis, greater)
+            .map(|res| res.mapv(|(val, _)| val))
+    }
+
+    /// Compute the minimum of the entries of the matrix along `axis`.
+    ///
+    /// Returns `None` if the lanes along `axis` are empty. The result is
+    /// unspecified if the entries cannot be ordered, eg for NaN values.
+    ///
+    /// # Panics
+    ///
+    /// - if `axis` is neither `Axis(0)` nor `Axis(1)`.
+    pub fn min_axis(&self, axis: Axis) -> Option<Array1<N>>
+    where
+        N: Copy + Num + PartialOrd + Send + Sync,
+    {
+        self.extremum_axis(axis, less)
+            .map(|res| res.mapv(|(val, _)| val))
+    }
+
+    /// Compute the index of the first maximum of the entries of the matrix
+    /// along `axis`, ie a row index for `Axis(0)`.
+    ///
+    /// Returns `None` if the lanes along `axis` are empty. The result is
+    /// unspecified if the entries cannot be ordered, eg for NaN values.
+    ///
+    /// # Panics
+    ///
+    /// - if `axis` is neither `Axis(0)` nor `Axis(1)`.
+    pub fn argmax_axis(&self, axis: Axis) -> Option<Array1<usize>>
+    where
+        N: Copy + Num + PartialOrd + Send + Sync,
+    {
+        self.extremum_axis(axis, greater)
+            .map(|res| res.mapv(|(_, index)| index))
+    }
+
+    /// Compute the index of the first minimum of the entries of the matrix
+    /// along `axis`, ie a row index for `Axis(0)`.
+    ///
+    /// Returns `None` if the lanes along `axis` are empty. The result is
+    /// unspecified if the entries cannot be ordered, eg for NaN values.
+    ///
+    /// # Panics
+    ///
+    /// - if `axis` is neither `Axis(0)` nor `Axis(1)`.
+    pub fn argmin_axis(&self, axis: Axis) -> Option<Array1<usize>>
+    where
+        N: Copy + Num + PartialOrd + Send + Sync,
+    {
+        self.extremum_axis(axis, less)
+            .map(|res| res.mapv(|(_, index)| index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sparse::{CsMat, TriMat};
+    use ndarray::{arr1, Array1, Array2, Axis};
+
+    fn mixed_signs() -> CsMat<i32> {
+        // | 0 -1  0  2 |
+        // | 3  0  0  0 |
+        // | 0  4  0 -5 |
+        CsMat::new(
+            (3, 4),
+            vec![0, 2, 3, 5],
+            vec![1, 3, 0, 1, 3],
+            vec![-1, 2, 3, 4, -5],
+        )
+    }
+
+    /// The first extremal entry of each lane of a dense matrix
+    fn dense_extremum(
+        dense: &Array2<i32>,
+        axis: Axis,
+        better: fn(&i32, &i32) -> bool,
+    ) -> (Array1<i32>, Array1<usize>) {
+        let lanes = dense.len_of(Axis(1 - axis.index()));
+        let mut vals = Vec::new();
+        let mut args = Vec::new();
+        for k in 0..lanes {
+            let lane = dense.index_axis(Axis(1 - axis.index()), k);
+            let mut best = 0;
+            for (i, val) in lane.iter().enumerate() {
+                if better(val, &lane[best]) {
+                    best = i;
+                }
+            }
+            vals.push(lane[best]);
+            args.push(best);
+        }
+        (Array1::from(vals), Array1::from(args))
+    }
+
+    fn check_reductions(mat: &CsMat<i32>) {
+        let dense = mat.to_dense();
+        for &storage_mat in &[mat, &mat.to_other_storage()] {
+            for &axis in &[Axis(0), Axis(1)] {
+                assert_eq!(storage_mat.sum_axis(axis), dense.sum_axis(axis));
+                let (max, argmax) = dense_extremum(&dense, axis, |a, b| a > b);
+                let (min, argmin) = dense_extremum(&dense, axis, |a, b| a < b);
+                assert_eq!(storage_mat.max_axis(axis), Some(max));
+                assert_eq!(storage_mat.argmax_axis(axis), Some(argmax));
+                assert_eq!(storage_mat.min_axis(axis), Some(min));
+                assert_eq!(storage_mat.argmin_axis(axis), Some(argmin));
+                let nnz = dense.map(|&x| (x != 0) as usize).sum_axis(axis);
+                assert_eq!(storage_mat.nnz_axis(axis), nnz);
+            }
+        }
+    }
+
+    #[test]
+    fn small_reductions() {
+        let mat = mixed_signs();
+        check_reductions(&mat);
+        assert_eq!(mat.sum_axis(Axis(0)), arr1(&[3, 3, 0, -3]));
+        assert_eq!(mat.max_axis(Axis(0)), Some(arr1(&[3, 4, 0, 2])));
+        assert_eq!(mat.argmax_axis(Axis(0)), Some(arr1(&[1, 2, 0, 0])));
+        assert_eq!(mat.min_axis(Axis(1)), Some(arr1(&[-1, 0, -5])));
+        assert_eq!(mat.argmin_axis(Axis(1)), Some(arr1(&[1, 1, 3])));
+        // the first zero of the first row is its first entry
+        assert_eq!(mat.argmax_axis(Axis(1)), Some(arr1(&[3, 0, 1])));
+    }
+
+    #[test]
+    fn explicit_zeros() {
+        // | 0 -1 |
+        // | 1  0 |, the zeros of the second column being stored
+        let mat = CsMat::new_csc(
+            (2, 2),
+            vec![0, 1, 3],
+            vec![1, 0, 1],
+            vec![1, -1, 0],
+        );
+        assert_eq!(mat.nnz_axis(Axis(0)), arr1(&[1, 2]));
+        assert_eq!(mat.max_axis(Axis(0)), Some(arr1(&[1, 0])));
+        assert_eq!(mat.argmax_axis(Axis(0)), Some(arr1(&[1, 1])));
+        assert_eq!(mat.argmin_axis(Axis(1)), Some(arr1(&[1, 1])));
+    }
+
+    #[test]
+    fn mean_and_empty_lanes() {
+        let mat: CsMat<f64> =
+            CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 2], vec![1., 2., 3.]);
+        assert_eq!(mat.mean_axis(Axis(0)), Some(arr1(&[0.5, 0., 2.5])));
+        assert_eq!(mat.mean_axis(Axis(1)), Some(arr1(&[1., 1.])));
+
+        let empty: CsMat<f64> = CsMat::zero((0, 3));
+        assert_eq!(empty.sum_axis(Axis(0)), arr1(&[0., 0., 0.]));
+        assert_eq!(empty.mean_axis(Axis(0)), None);
+        assert_eq!(empty.max_axis(Axis(0)), None);
+        assert_eq!(empty.argmin_axis(Axis(0)), None);
+        assert_eq!(empty.sum_axis(Axis(1)).len(), 0);
+        assert_eq!(empty.max_axis(Axis(1)), Some(Array1::zeros(0)));
+    }
+
+    #[test]
+    fn large_reductions() {
+        // large enough to be reduced in parallel
+        let n = 300;
+        let mut tri = TriMat::new((n, n));
+        for i in 0..n {
+            for j in 0..n {
+                if (i * 7 + j * 13) % 5 == 0 {
+                    let val = (i * 31 + j * 17) % 23;
+                    tri.add_triplet(i, j, 2 * val as i32 - 21);
+                }
+            }
+        }
+        let mat = tri.to_csr();
+        check_reductions(&mat);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_axis() {
+        mixed_signs().sum_axis(Axis(2));
+    }
+}
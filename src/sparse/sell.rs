@@ -0,0 +1,398 @@
+//! Sliced ELLPACK format matrices
+//!
+//! The SELL-C-σ format groups the rows of a matrix into chunks of `C` rows,
+//! each chunk being stored as a padded dense array in column major order.
+//! The rows are first sorted by their number of non-zeros inside windows of
+//! `σ` rows, to limit the padding. The matrix-vector product then operates
+//! on `C` rows at once with contiguous memory accesses, and the chunks are
+//! processed in parallel when the `multi_thread` feature is enabled.
+
+use std::borrow::Cow;
+use std::ops::{Deref, Mul};
+
+use ndarray::linalg::Dot;
+use ndarray::{Array, ArrayBase, Ix1};
+use num_traits::Num;
+#[cfg(feature = "multi_thread")]
+use rayon::prelude::*;
+
+use crate::indexing::SpIndex;
+use crate::sparse::prelude::*;
+use crate::Shape;
+
+impl<N, I: SpIndex> SellMatI<N, I> {
+    /// The number of rows of the matrix
+    pub fn rows(&self) -> usize {
+        self.nrows
+    }
+
+    /// The number of cols of the matrix
+    pub fn cols(&self) -> usize {
+        self.ncols
+    }
+
+    /// The shape of the matrix, as a `(rows, cols)` tuple
+    pub fn shape(&self) -> Shape {
+        (self.nrows, self.ncols)
+    }
+
+    /// The number of rows of a chunk, `C`
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// The size of the windows inside which rows are sorted, `σ`
+    pub fn sigma(&self) -> usize {
+        self.sigma
+    }
+
+    /// The number of chunks
+    pub fn nb_chunks(&self) -> usize {
+        self.chunk_ptr.len() - 1
+    }
+
+    /// The width of the `k`-th chunk, ie the largest number of non-zeros of
+    /// its rows
+    ///
+    /// # Panics
+    ///
+    /// - if `k` is not less than the number of chunks.
+    pub fn chunk_width(&self, k: usize) -> usize {
+        (self.chunk_ptr[k + 1] - self.chunk_ptr[k]) / self.chunk_size
+    }
+
+    /// The original row stored at each position of the chunks, the rows of
+    /// the `k`-th chunk being found at `k * C..(k + 1) * C`
+    pub fn row_permutation(&self) -> &[I] {
+        &self.perm[..]
+    }
+
+    /// The column indices of the stored entries, padding included
+    pub fn indices(&self) -> &[I] {
+        &self.indices[..]
+    }
+
+    /// The values of the stored entries, padding included
+    pub fn data(&self) -> &[N] {
+        &self.data[..]
+    }
+
+    /// The number of non-zero entries, padding excluded
+    pub fn nnz(&self) -> usize {
+        self.slot_lens.iter().map(|len| len.index()).sum()
+    }
+
+    /// The number of stored entries, padding included. Its ratio to `nnz`
+    /// measures the storage overhead of the format for this matrix.
+    pub fn stored_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Create a CSR matrix from this matrix, removing the padding
+    pub fn to_csr(&self) -> CsMatI<N, I>
+    where
+        N: Clone,
+    {
+        let c = self.chunk_size;
+        let mut slots = vec![0; self.nrows];
+        for (slot, row) in self.perm.iter().enumerate() {
+            slots[row.index()] = slot;
+        }
+        let mut indptr = Vec::with_capacity(self.nrows + 1);
+        let mut indices = Vec::with_capacity(self.nnz());
+        let mut data = Vec::with_capacity(self.nnz());
+        indptr.push(I::zero());
+        for &slot in &slots {
+            let start = self.chunk_ptr[slot / c] + slot % c;
+            let len = self.slot_lens[slot].index();
+            for pos in (0..len).map(|j| start + j * c) {
+                indices.push(self.indices[pos]);
+                data.push(self.data[pos].clone());
+            }
+            indptr.push(I::from_usize(indices.len()));
+        }
+        CsMatI::new_trusted(crate::CSR, self.shape(), indptr, indices, data)
+    }
+
+    /// Accumulate the product of the `k`-th chunk by `x` into `res`, which
+    /// holds the results for the rows of the chunk
+    fn chunk_mul_acc(&self, k: usize, x: &[N], res: &mut [N])
+    where
+        N: Copy + Num,
+    {
+        let c = self.chunk_size;
+        let chunk = self.chunk_ptr[k]..self.chunk_ptr[k + 1];
+        let indices = &self.indices[chunk.clone()];
+        let data = &self.data[chunk];
+        let lens = &self.slot_lens[k * c..(k + 1) * c];
+        // the columns of the chunk up to the length of its shortest row
+        // hold no padding, and are processed for all its rows at once
+        let full = lens.iter().map(|len| len.index()).min().unwrap_or(0);
+        let columns = indices.chunks_exact(c).zip(data.chunks_exact(c));
+        for (indices, data) in columns.take(full) {
+            for (y, (&a, &j)) in res.iter_mut().zip(data.iter().zip(indices)) {
+                *y = *y + a * x[j.index()];
+            }
+        }
+        // the remaining entries of the longer rows are processed row by row
+        for (slot, (y, len)) in res.iter_mut().zip(lens).enumerate() {
+            for pos in (full..len.index()).map(|j| j * c + slot) {
+                *y = *y + data[pos] * x[indices[pos].index()];
+            }
+        }
+    }
+}
+
+impl<N, I, IpS, IS, DS, Iptr> CsMatBase<N, I, IpS, IS, DS, Iptr>
+where
+    I: SpIndex,
+    Iptr: SpIndex,
+    IpS: Deref<Target = [Iptr]>,
+    IS: Deref<Target = [I]>,
+    DS: Deref<Target = [N]>,
+{
+    /// Create a sliced ELLPACK matrix from this matrix, with chunks of
+    /// `chunk_size` rows, the rows being sorted by decreasing number of
+    /// non-zeros inside windows of `sigma` rows.
+    ///
+    /// A `sigma` of 1 disables the sorting, while a `sigma` at least equal
+    /// to the number of rows sorts all the rows. Choosing `sigma` as a
+    /// multiple of `chunk_size` is advised.
+    ///
+    /// # Panics
+    ///
+    /// - if `chunk_size` or `sigma` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mat = CsMat::new(
+    ///     (4, 4),
+    ///     vec![0, 1, 4, 5, 7],
+    ///     vec![0, 0, 1, 3, 2, 1, 3],
+    ///     vec![1., 2., 3., 4., 5., 6., 7.],
+    /// );
+    /// let sell = mat.to_sell(2, 4);
+    /// assert_eq!(sell.row_permutation(), &[1, 3, 0, 2]);
+    /// assert_eq!(sell.chunk_width(0), 3);
+    /// assert_eq!(sell.chunk_width(1), 1);
+    /// assert_eq!(sell.stored_len(), 8);
+    /// assert_eq!(sell.to_csr(), mat);
+    /// ```
+    pub fn to_sell(&self, chunk_size: usize, sigma: usize) -> SellMatI<N, I>
+    where
+        N: Copy + Num,
+    {
+        assert!(chunk_size > 0, "The chunk size must be positive");
+        assert!(sigma > 0, "The sorting window must be positive");
+        let converted;
+        let csr = if self.is_csr() {
+            self.view()
+        } else {
+            converted = self.to_other_storage_with(N::zero());
+            converted.view()
+        };
+        let indptr = csr.indptr();
+        let row_len = |row: usize| (indptr[row + 1] - indptr[row]).index();
+        let mut rows: Vec<usize> = (0..self.rows()).collect();
+        for window in rows.chunks_mut(sigma) {
+            // a stable sort keeps the original order of rows of equal
+            // length
+            window.sort_by_key(|&row| std::cmp::Reverse(row_len(row)));
+        }
+        let c = chunk_size;
+        let nb_chunks = rows.chunks(c).len();
+        let mut slot_lens = vec![I::zero(); nb_chunks * c];
+        let mut chunk_ptr = Vec::with_capacity(nb_chunks + 1);
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        chunk_ptr.push(0);
+        for (k, chunk_rows) in rows.chunks(c).enumerate() {
+            let width = chunk_rows.iter().map(|&row| row_len(row)).max();
+            let start = indices.len();
+            let end = start + width.unwrap_or(0) * c;
+            indices.resize(end, I::zero());
+            data.resize(end, N::zero());
+            for (slot, &row) in chunk_rows.iter().enumerate() {
+                let vec = csr.outer_view(row).unwrap();
+                slot_lens[k * c + slot] = I::from_usize(vec.nnz());
+                // padding holds zeros and repeats the last column of the
+                // row, so that all indices are valid. The product never
+                // reads it, see chunk_mul_acc.
+                let pad = vec.indices().last().cloned().unwrap_or_else(I::zero);
+                let positions = (start + slot..end).step_by(c);
+                let mut entries = vec.iter();
+                for pos in positions {
+                    match entries.next() {
+                        Some((col, &val)) => {
+                            indices[pos] = I::from_usize(col);
+                            data[pos] = val;
+                        }
+                        None => indices[pos] = pad,
+                    }
+                }
+            }
+            chunk_ptr.push(end);
+        }
+        SellMatI {
+            nrows: self.rows(),
+            ncols: self.cols(),
+            chunk_size,
+            sigma,
+            perm: rows.into_iter().map(I::from_usize).collect(),
+            slot_lens,
+            chunk_ptr,
+            indices,
+            data,
+        }
+    }
+}
+
+impl<N, I: SpIndex> SparseMat for SellMatI<N, I> {
+    fn rows(&self) -> usize {
+        self.rows()
+    }
+
+    fn cols(&self) -> usize {
+        self.cols()
+    }
+
+    fn nnz(&self) -> usize {
+        self.nnz()
+    }
+}
+
+impl<'a, 'b, N, I, DS2> Mul<&'b ArrayBase<DS2, Ix1>> for &'a SellMatI<N, I>
+where
+    N: 'a + Copy + Num + Send + Sync,
+    I: 'a + SpIndex,
+    DS2: 'b + ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn mul(self, rhs: &'b ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        assert_eq!(self.cols(), rhs.len(), "Dimension mismatch");
+        let x = match rhs.as_slice() {
+            Some(x) => Cow::Borrowed(x),
+            None => Cow::Owned(rhs.to_vec()),
+        };
+        let x = &x[..];
+        // the products are computed in the order of the chunks, then
+        // scattered to the original rows
+        let mut sorted_res = vec![N::zero(); self.slot_lens.len()];
+        #[cfg(feature = "multi_thread")]
+        let chunks = sorted_res.par_chunks_mut(self.chunk_size);
+        #[cfg(not(feature = "multi_thread"))]
+        let chunks = sorted_res.chunks_mut(self.chunk_size);
+        chunks
+            .enumerate()
+            .for_each(|(k, res)| self.chunk_mul_acc(k, x, res));
+        let mut res = Array::zeros(self.rows());
+        for (&row, &val) in self.perm.iter().zip(sorted_res.iter()) {
+            res[row.index()] = val;
+        }
+        res
+    }
+}
+
+impl<N, I, DS2> Dot<ArrayBase<DS2, Ix1>> for SellMatI<N, I>
+where
+    N: Copy + Num + Send + Sync,
+    I: SpIndex,
+    DS2: ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn dot(&self, rhs: &ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        Mul::mul(self, rhs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sparse::{CsMat, CsMatI, SellMatI};
+    use ndarray::{arr1, Array1};
+
+    fn irregular_matrix() -> CsMat<f64> {
+        // 7x6 matrix whose rows hold between 0 and 5 non-zeros
+        CsMat::new(
+            (7, 6),
+            vec![0, 1, 6, 6, 8, 11, 12, 14],
+            vec![2, 0, 1, 2, 3, 5, 1, 4, 0, 3, 5, 5, 2, 3],
+            (1..=14).map(f64::from).collect(),
+        )
+    }
+
+    #[test]
+    fn csr_roundtrip() {
+        let mat = irregular_matrix();
+        for &(c, sigma) in &[(1, 1), (2, 1), (2, 4), (3, 7), (4, 8), (8, 1)] {
+            let sell = mat.to_sell(c, sigma);
+            assert_eq!(sell.nnz(), mat.nnz());
+            assert_eq!(sell.to_csr(), mat);
+            assert_eq!(mat.to_csc().to_sell(c, sigma), sell);
+        }
+        let sell: SellMatI<f64, u16> =
+            CsMatI::new((2, 3), vec![0, 0, 0], vec![], vec![]).to_sell(4, 4);
+        assert_eq!(sell.stored_len(), 0);
+        assert_eq!(sell.to_csr().shape(), (2, 3));
+    }
+
+    #[test]
+    fn sorting_reduces_padding() {
+        let mat = irregular_matrix();
+        let unsorted = mat.to_sell(2, 1);
+        assert_eq!(unsorted.row_permutation(), &[0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(unsorted.nb_chunks(), 4);
+        let widths: Vec<_> = (0..4).map(|k| unsorted.chunk_width(k)).collect();
+        assert_eq!(widths, vec![5, 2, 3, 2]);
+        assert_eq!(unsorted.stored_len(), 24);
+
+        let sorted = mat.to_sell(2, 7);
+        assert_eq!(sorted.row_permutation(), &[1, 4, 3, 6, 0, 5, 2]);
+        let widths: Vec<_> = (0..4).map(|k| sorted.chunk_width(k)).collect();
+        assert_eq!(widths, vec![5, 2, 1, 0]);
+        assert_eq!(sorted.stored_len(), 16);
+        // padding of the first chunk repeats the last column of row 4
+        assert_eq!(&sorted.indices()[..10], &[0, 0, 1, 3, 2, 5, 3, 5, 5, 5]);
+        assert_eq!(&sorted.data()[4..10], &[4., 11., 5., 0., 6., 0.]);
+    }
+
+    #[test]
+    fn products() {
+        let mat = irregular_matrix();
+        let x = arr1(&[1., -2., 0.5, 3., -1., 2.]);
+        let expected = &mat * &x;
+        let params = [(1, 1), (2, 1), (3, 2), (2, 4), (4, 4), (4, 8), (16, 16)];
+        for &(c, sigma) in &params {
+            let sell = mat.to_sell(c, sigma);
+            assert_eq!(&sell * &x, expected);
+        }
+        // non contiguous operand
+        let x_strided: Array1<f64> =
+            Array1::from(vec![1., 0., -2., 0., 0.5, 0., 3., 0., -1., 0., 2.]);
+        let x_strided = x_strided.slice(ndarray::s![..;2]);
+        assert_eq!(&mat.to_sell(4, 8) * &x_strided, expected);
+
+        // the padding does not propagate non finite values
+        let x = arr1(&[std::f64::INFINITY, 1., 2., std::f64::NAN, 3., 4.]);
+        let mat = CsMat::new(
+            (3, 6),
+            vec![0, 3, 3, 4],
+            vec![0, 1, 2, 4],
+            vec![1., 2., 3., 4.],
+        );
+        let sell = mat.to_sell(4, 4);
+        assert_eq!(sell.chunk_width(0), 3);
+        let res = &sell * &x;
+        assert_eq!(res, arr1(&[std::f64::INFINITY, 0., 12.]));
+        assert_eq!(res, &mat * &x);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_chunk_size() {
+        irregular_matrix().to_sell(0, 1);
+    }
+}
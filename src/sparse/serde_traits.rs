@@ -0,0 +1,264 @@
+//! Validated deserialization of sparse matrices and vectors
+//!
+//! The sparse types are deserialized through shadow structures mirroring
+//! their serialized fields, which are then checked to describe a valid
+//! matrix or vector, the same way as the `try_new` constructors, except that
+//! indices are required to be sorted. Invalid payloads are rejected with a
+//! serde error instead of breaking the invariants the algorithms rely upon.
+//!
+//! Compressed matrices and vectors are serialized as their storage order
+//! and compressed arrays, and there is no more compact representation for
+//! them. Only permutations have one, the identity being serialized without
+//! its indices.
+
+use std::ops::Deref;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+use crate::errors::SprsError;
+use crate::indexing::SpIndex;
+use crate::sparse::{CompressedStorage, CsMatBase, CsVecBase, TriMatBase};
+
+#[derive(Deserialize)]
+struct CsMatBaseShadow<IptrStorage, IndStorage, DataStorage> {
+    storage: CompressedStorage,
+    nrows: usize,
+    ncols: usize,
+    indptr: IptrStorage,
+    indices: IndStorage,
+    data: DataStorage,
+}
+
+impl<'de, N, I, Iptr, IptrStorage, IndStorage, DataStorage> Deserialize<'de>
+    for CsMatBase<N, I, IptrStorage, IndStorage, DataStorage, Iptr>
+where
+    I: SpIndex,
+    Iptr: SpIndex,
+    IptrStorage: Deref<Target = [Iptr]> + Deserialize<'de>,
+    IndStorage: Deref<Target = [I]> + Deserialize<'de>,
+    DataStorage: Deref<Target = [N]> + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let CsMatBaseShadow {
+            storage,
+            nrows,
+            ncols,
+            indptr,
+            indices,
+            data,
+        } = CsMatBaseShadow::deserialize(deserializer)?;
+        Self::new_checked(storage, (nrows, ncols), indptr, indices, data)
+            .map_err(|(_, _, _, e)| D::Error::custom(e))
+    }
+}
+
+#[derive(Deserialize)]
+struct CsVecBaseShadow<IStorage, DStorage> {
+    dim: usize,
+    indices: IStorage,
+    data: DStorage,
+}
+
+impl<'de, N, I, IStorage, DStorage> Deserialize<'de>
+    for CsVecBase<IStorage, DStorage>
+where
+    I: SpIndex,
+    IStorage: Deref<Target = [I]> + Deserialize<'de>,
+    DStorage: Deref<Target = [N]> + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let CsVecBaseShadow { dim, indices, data } =
+            CsVecBaseShadow::deserialize(deserializer)?;
+        Self::new_(dim, indices, data).map_err(|(_, _, e)| D::Error::custom(e))
+    }
+}
+
+#[derive(Deserialize)]
+struct TriMatBaseShadow<IStorage, DStorage> {
+    rows: usize,
+    cols: usize,
+    row_inds: IStorage,
+    col_inds: IStorage,
+    data: DStorage,
+}
+
+impl<'de, N, I, IStorage, DStorage> Deserialize<'de>
+    for TriMatBase<IStorage, DStorage>
+where
+    I: SpIndex,
+    IStorage: Deref<Target = [I]> + Deserialize<'de>,
+    DStorage: Deref<Target = [N]> + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let TriMatBaseShadow {
+            rows,
+            cols,
+            row_inds,
+            col_inds,
+            data,
+        } = TriMatBaseShadow::<IStorage, DStorage>::deserialize(deserializer)?;
+        if row_inds.len() != data.len() || col_inds.len() != data.len() {
+            return Err(D::Error::custom(SprsError::IllegalArguments(
+                "all inputs should have the same length",
+            )));
+        }
+        let in_bounds = |inds: &[I], dim: usize| {
+            inds.iter()
+                .all(|i| i.try_index().filter(|&i| i < dim).is_some())
+        };
+        if !in_bounds(&row_inds, rows) || !in_bounds(&col_inds, cols) {
+            return Err(D::Error::custom(SprsError::IllegalArguments(
+                "indices should be within shape",
+            )));
+        }
+        Ok(TriMatBase {
+            rows,
+            cols,
+            row_inds,
+            col_inds,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sparse::permutation::PermOwned;
+    use crate::sparse::{CsMat, CsMatI, CsVec, TriMat};
+    use crate::{CompressedStorage, CSR};
+    use serde::Serialize;
+
+    /// The serialized layout of a `CsMatBase`, without any validation
+    #[derive(Clone, Serialize)]
+    struct RawCsMat {
+        storage: CompressedStorage,
+        nrows: usize,
+        ncols: usize,
+        indptr: Vec<usize>,
+        indices: Vec<usize>,
+        data: Vec<f64>,
+    }
+
+    fn deserialize_raw(raw: &RawCsMat) -> bincode::Result<CsMat<f64>> {
+        bincode::deserialize(&bincode::serialize(raw).unwrap())
+    }
+
+    #[test]
+    fn csmat_roundtrip() {
+        let mat = CsMat::new(
+            (3, 4),
+            vec![0, 2, 3, 5],
+            vec![0, 2, 1, 0, 3],
+            vec![1., 2., 3., 4., -1.5],
+        );
+        let serialized = bincode::serialize(&mat).unwrap();
+        let deserialized: CsMat<f64> =
+            bincode::deserialize(&serialized).unwrap();
+        assert_eq!(mat, deserialized);
+        // views are serialized as their owned counterpart
+        let serialized = bincode::serialize(&mat.view()).unwrap();
+        let deserialized: CsMat<f64> =
+            bincode::deserialize(&serialized).unwrap();
+        assert_eq!(mat, deserialized);
+        let mat: CsMatI<f32, u16, u32> = CsMatI::eye(3);
+        let serialized = bincode::serialize(&mat).unwrap();
+        assert_eq!(mat, bincode::deserialize(&serialized).unwrap());
+    }
+
+    #[test]
+    fn csmat_invalid_payloads() {
+        let valid = RawCsMat {
+            storage: CSR,
+            nrows: 2,
+            ncols: 3,
+            indptr: vec![0, 2, 3],
+            indices: vec![0, 2, 1],
+            data: vec![1., 2., 3.],
+        };
+        assert!(deserialize_raw(&valid).is_ok());
+        let corruptions: [fn(&mut RawCsMat); 5] = [
+            |raw| raw.indptr = vec![0, 3, 2],
+            |raw| raw.indices = vec![0, 3, 1],
+            |raw| raw.indices = vec![2, 0, 1],
+            |raw| raw.data = vec![1., 2.],
+            |raw| raw.nrows = 3,
+        ];
+        for corrupt in &corruptions {
+            let mut raw = valid.clone();
+            corrupt(&mut raw);
+            assert!(deserialize_raw(&raw).is_err());
+        }
+        let mut raw = valid;
+        raw.nrows = 3;
+        let err = deserialize_raw(&raw).unwrap_err();
+        assert_eq!(err.to_string(), "Indptr length does not match dimension");
+    }
+
+    #[test]
+    fn csvec_validation() {
+        let vec = CsVec::new(5, vec![1, 3], vec![1., -1.]);
+        let serialized = bincode::serialize(&vec).unwrap();
+        assert_eq!(vec, bincode::deserialize(&serialized).unwrap());
+
+        let invalid = [
+            (5_usize, vec![3_usize, 1], vec![1., 2.]),
+            (3, vec![1, 3], vec![1., 2.]),
+        ];
+        for raw in &invalid {
+            let serialized = bincode::serialize(raw).unwrap();
+            assert!(bincode::deserialize::<CsVec<f64>>(&serialized).is_err());
+        }
+    }
+
+    #[test]
+    fn trimat_validation() {
+        let mut mat = TriMat::new((2, 3));
+        mat.add_triplet(1, 2, 3.);
+        mat.add_triplet(0, 0, 1.);
+        mat.add_triplet(1, 2, 2.);
+        let serialized = bincode::serialize(&mat).unwrap();
+        assert_eq!(mat, bincode::deserialize(&serialized).unwrap());
+
+        let invalid = [
+            (2_usize, 3_usize, vec![0_usize, 2], vec![0, 1], vec![1., 2.]),
+            (2, 3, vec![0, 1], vec![0, 1], vec![1.]),
+        ];
+        for raw in &invalid {
+            let serialized = bincode::serialize(raw).unwrap();
+            assert!(bincode::deserialize::<TriMat<f64>>(&serialized).is_err());
+        }
+    }
+
+    #[test]
+    fn permutation_serialization() {
+        let perm = PermOwned::new(vec![2, 0, 1]);
+        let serialized = bincode::serialize(&perm).unwrap();
+        let deserialized: PermOwned =
+            bincode::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized.vec(), perm.vec());
+        assert_eq!(deserialized.inv_vec(), perm.inv_vec());
+
+        // the identity permutation is stored without its indices
+        let identity = PermOwned::identity(1000);
+        let serialized = bincode::serialize(&identity).unwrap();
+        assert!(serialized.len() < 16);
+        let deserialized: PermOwned =
+            bincode::deserialize(&serialized).unwrap();
+        assert!(deserialized.is_identity());
+        assert_eq!(deserialized.dim(), 1000);
+
+        let invalid =
+            [(3_usize, Some(vec![0_usize, 0, 1])), (3, Some(vec![0, 2]))];
+        for raw in &invalid {
+            let serialized = bincode::serialize(raw).unwrap();
+            assert!(bincode::deserialize::<PermOwned>(&serialized).is_err());
+        }
+    }
+}
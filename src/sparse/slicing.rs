@@ -0,0 +1,425 @@
+//! Selection of rows and columns of compressed sparse matrices
+//!
+//! Selections along either axis of a `CsMatBase` can be expressed with
+//! ranges, lists of indices (in any order, possibly with repetitions) or
+//! boolean masks. Selecting along the outer dimension only requires copying
+//! the selected outer slices, while selecting along the inner dimension
+//! requires a remapping of the inner indices. In both cases the storage
+//! order of the matrix is preserved.
+
+use std::ops::{
+    Deref, Range, RangeFrom, RangeFull, RangeInclusive, RangeTo,
+    RangeToInclusive,
+};
+
+use crate::indexing::SpIndex;
+use crate::sparse::prelude::*;
+use crate::sparse::CompressedStorage::{CSC, CSR};
+
+/// A selection of indices along an axis of a sparse matrix.
+///
+/// This trait is implemented for ranges of `usize`, for lists of indices
+/// (`[usize]` and `Vec<usize>`) and for boolean masks (`[bool]` and
+/// `Vec<bool>`).
+pub trait AxisSelection {
+    /// Return the selected indices for an axis of length `dim`, in the order
+    /// in which they should appear in the result.
+    ///
+    /// # Panics
+    ///
+    /// - if a selected index is out of bounds for `dim`.
+    /// - if a mask does not have exactly `dim` elements.
+    fn selected_indices(&self, dim: usize) -> Vec<usize>;
+}
+
+impl<S: AxisSelection + ?Sized> AxisSelection for &S {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        (**self).selected_indices(dim)
+    }
+}
+
+impl AxisSelection for Range<usize> {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        assert!(self.start <= self.end, "Invalid range");
+        assert!(self.end <= dim, "Out of bounds index");
+        self.clone().collect()
+    }
+}
+
+impl AxisSelection for RangeInclusive<usize> {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        (*self.start()..self.end().checked_add(1).unwrap())
+            .selected_indices(dim)
+    }
+}
+
+impl AxisSelection for RangeFrom<usize> {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        (self.start..dim).selected_indices(dim)
+    }
+}
+
+impl AxisSelection for RangeTo<usize> {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        (0..self.end).selected_indices(dim)
+    }
+}
+
+impl AxisSelection for RangeToInclusive<usize> {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        (0..=self.end).selected_indices(dim)
+    }
+}
+
+impl AxisSelection for RangeFull {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        (0..dim).collect()
+    }
+}
+
+impl AxisSelection for [usize] {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        assert!(self.iter().all(|&i| i < dim), "Out of bounds index");
+        self.to_vec()
+    }
+}
+
+impl AxisSelection for Vec<usize> {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        self[..].selected_indices(dim)
+    }
+}
+
+impl AxisSelection for [bool] {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        assert_eq!(self.len(), dim, "Mask length does not match dimension");
+        self.iter()
+            .enumerate()
+            .filter_map(|(i, &keep)| if keep { Some(i) } else { None })
+            .collect()
+    }
+}
+
+impl AxisSelection for Vec<bool> {
+    fn selected_indices(&self, dim: usize) -> Vec<usize> {
+        self[..].selected_indices(dim)
+    }
+}
+
+/// # Selection of rows and columns
+impl<N, I, Iptr, IptrStorage, IndStorage, DataStorage>
+    CsMatBase<N, I, IptrStorage, IndStorage, DataStorage, Iptr>
+where
+    I: SpIndex,
+    Iptr: SpIndex,
+    IptrStorage: Deref<Target = [Iptr]>,
+    IndStorage: Deref<Target = [I]>,
+    DataStorage: Deref<Target = [N]>,
+{
+    /// Create a new matrix containing the selected outer dimensions of
+    /// this matrix (eg the selected rows of a CSR matrix), in the order
+    /// given by the selection.
+    ///
+    /// This operation is cheap as it only copies the selected outer slices.
+    ///
+    /// # Panics
+    ///
+    /// - if a selected index is out of bounds.
+    /// - if a mask does not have exactly `self.outer_dims()` elements.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mat = CsMat::new(
+    ///     (3, 3),
+    ///     vec![0, 2, 3, 4],
+    ///     vec![0, 2, 1, 2],
+    ///     vec![1., 2., 3., 4.],
+    /// );
+    /// let rows = mat.select_outer(&[2, 0][..]);
+    /// assert_eq!(rows.shape(), (2, 3));
+    /// assert_eq!(rows.indices(), &[2, 0, 2]);
+    /// assert_eq!(rows.data(), &[4., 1., 2.]);
+    /// ```
+    pub fn select_outer<S>(&self, selection: S) -> CsMatI<N, I, Iptr>
+    where
+        N: Clone,
+        S: AxisSelection,
+    {
+        let outer_inds = selection.selected_indices(self.outer_dims());
+        let nnz = outer_inds
+            .iter()
+            .map(|&i| self.indptr[i + 1].index() - self.indptr[i].index())
+            .sum();
+        let mut indptr = Vec::with_capacity(outer_inds.len() + 1);
+        let mut indices = Vec::with_capacity(nnz);
+        let mut data = Vec::with_capacity(nnz);
+        indptr.push(Iptr::zero());
+        for &outer_ind in &outer_inds {
+            let start = self.indptr[outer_ind].index();
+            let stop = self.indptr[outer_ind + 1].index();
+            indices.extend_from_slice(&self.indices[start..stop]);
+            data.extend_from_slice(&self.data[start..stop]);
+            indptr.push(Iptr::from_usize(indices.len()));
+        }
+        let shape = match self.storage {
+            CSR => (outer_inds.len(), self.ncols),
+            CSC => (self.nrows, outer_inds.len()),
+        };
+        CsMatI::new_trusted(self.storage, shape, indptr, indices, data)
+    }
+
+    /// Create a new matrix containing the selected inner dimensions of
+    /// this matrix (eg the selected columns of a CSR matrix), in the order
+    /// given by the selection.
+    ///
+    /// Selecting inner dimensions requires remapping the inner indices of
+    /// each outer slice, and sorting them again if the selection is not
+    /// increasing.
+    ///
+    /// # Panics
+    ///
+    /// - if a selected index is out of bounds.
+    /// - if a mask does not have exactly `self.inner_dims()` elements.
+    /// - if the number of selected inner indices does not fit in the index
+    ///   type, as can happen when indices are selected several times.
+    pub fn select_inner<S>(&self, selection: S) -> CsMatI<N, I, Iptr>
+    where
+        N: Clone,
+        S: AxisSelection,
+    {
+        let inner_dims = self.inner_dims();
+        let inner_inds = selection.selected_indices(inner_dims);
+        let nb_selected = inner_inds.len();
+        // The new inner indices are below nb_selected, so they fit in the
+        // index type once it does
+        assert!(
+            I::try_from_usize(nb_selected).is_some(),
+            "the number of selected inner indices overflows the index type"
+        );
+
+        // An inner index can be selected several times, we thus store the
+        // new locations of each inner index in a compressed fashion.
+        let mut offsets = vec![0; inner_dims + 1];
+        for &ind in &inner_inds {
+            offsets[ind + 1] += 1;
+        }
+        for i in 0..inner_dims {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut new_locs = vec![0; nb_selected];
+        let mut cursors = offsets[..inner_dims].to_vec();
+        for (new_ind, &ind) in inner_inds.iter().enumerate() {
+            new_locs[cursors[ind]] = new_ind;
+            cursors[ind] += 1;
+        }
+        let increasing = inner_inds.windows(2).all(|w| w[0] < w[1]);
+
+        let mut indptr = Vec::with_capacity(self.outer_dims() + 1);
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        let mut buf: Vec<(usize, N)> = Vec::new();
+        indptr.push(Iptr::zero());
+        for vec in self.outer_iterator() {
+            buf.clear();
+            for (ind, val) in vec.iter() {
+                for &new_ind in &new_locs[offsets[ind]..offsets[ind + 1]] {
+                    buf.push((new_ind, val.clone()));
+                }
+            }
+            if !increasing {
+                buf.sort_unstable_by_key(|x| x.0);
+            }
+            for (new_ind, val) in buf.drain(..) {
+                indices.push(I::from_usize_unchecked(new_ind));
+                data.push(val);
+            }
+            indptr.push(Iptr::from_usize(indices.len()));
+        }
+        let shape = match self.storage {
+            CSR => (self.nrows, nb_selected),
+            CSC => (nb_selected, self.ncols),
+        };
+        CsMatI::new_trusted(self.storage, shape, indptr, indices, data)
+    }
+
+    /// Create a new matrix containing the selected rows of this matrix.
+    ///
+    /// The storage order is preserved. This is cheaper for a CSR matrix,
+    /// see `select_outer` and `select_inner`.
+    ///
+    /// # Panics
+    ///
+    /// - if a selected index is out of bounds.
+    /// - if a mask does not have exactly `self.rows()` elements.
+    pub fn select_rows<S>(&self, selection: S) -> CsMatI<N, I, Iptr>
+    where
+        N: Clone,
+        S: AxisSelection,
+    {
+        match self.storage {
+            CSR => self.select_outer(selection),
+            CSC => self.select_inner(selection),
+        }
+    }
+
+    /// Create a new matrix containing the selected columns of this matrix.
+    ///
+    /// The storage order is preserved. This is cheaper for a CSC matrix,
+    /// see `select_outer` and `select_inner`.
+    ///
+    /// # Panics
+    ///
+    /// - if a selected index is out of bounds.
+    /// - if a mask does not have exactly `self.cols()` elements.
+    pub fn select_cols<S>(&self, selection: S) -> CsMatI<N, I, Iptr>
+    where
+        N: Clone,
+        S: AxisSelection,
+    {
+        match self.storage {
+            CSR => self.select_inner(selection),
+            CSC => self.select_outer(selection),
+        }
+    }
+
+    /// Create a new matrix containing the intersection of the selected rows
+    /// and columns of this matrix.
+    ///
+    /// The storage order is preserved.
+    ///
+    /// # Panics
+    ///
+    /// - if a selected index is out of bounds.
+    /// - if a mask does not have the length of the dimension it applies to.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::CsMat;
+    /// let mat = CsMat::new(
+    ///     (3, 4),
+    ///     vec![0, 2, 3, 5],
+    ///     vec![0, 3, 1, 2, 3],
+    ///     vec![1, 2, 3, 4, 5],
+    /// );
+    /// let mask = vec![true, false, true];
+    /// let sub = mat.select(&mask, 2..4);
+    /// assert_eq!(sub.shape(), (2, 2));
+    /// assert_eq!(sub.to_dense(), ndarray::arr2(&[[0, 2], [4, 5]]));
+    /// ```
+    pub fn select<R, C>(&self, rows: R, cols: C) -> CsMatI<N, I, Iptr>
+    where
+        N: Clone,
+        R: AxisSelection,
+        C: AxisSelection,
+    {
+        match self.storage {
+            CSR => self.select_outer(rows).select_inner(cols),
+            CSC => self.select_outer(cols).select_inner(rows),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sparse::{CsMat, CsMatI};
+    use crate::test_data::{mat1, mat1_csc};
+    use ndarray::{arr2, s};
+
+    #[test]
+    fn select_outer_ranges() {
+        let mat = mat1();
+        let dense = mat.to_dense();
+        assert_eq!(
+            mat.select_outer(1..3).to_dense(),
+            dense.slice(s![1..3, ..])
+        );
+        assert_eq!(mat.select_outer(..2).to_dense(), dense.slice(s![..2, ..]));
+        assert_eq!(mat.select_outer(3..).to_dense(), dense.slice(s![3.., ..]));
+        assert_eq!(
+            mat.select_outer(1..=3).to_dense(),
+            dense.slice(s![1..4, ..])
+        );
+        assert_eq!(mat.select_outer(..).to_dense(), dense);
+        let empty = mat.select_outer(2..2);
+        assert_eq!(empty.shape(), (0, 5));
+        assert_eq!(empty.nnz(), 0);
+    }
+
+    #[test]
+    fn select_rows_and_cols_both_storages() {
+        let csr = mat1();
+        let csc = mat1_csc();
+        let dense = csr.to_dense();
+        let rows = vec![4, 0, 0, 2];
+        let cols = vec![3, 1, 2];
+        let expected =
+            arr2(&[[7., 0., 0.], [4., 0., 3.], [4., 0., 3.], [0., 0., 5.]]);
+        for mat in &[csr, csc] {
+            let sub = mat.select(&rows, &cols);
+            assert_eq!(sub.storage(), mat.storage());
+            assert_eq!(sub.to_dense(), expected);
+            sub.check_compressed_structure().unwrap();
+            let sub_rows = mat.select_rows(&rows);
+            assert_eq!(sub_rows.storage(), mat.storage());
+            assert_eq!(sub_rows.to_dense().row(0), dense.row(4));
+            assert_eq!(sub_rows.to_dense().row(2), dense.row(0));
+            sub_rows.check_compressed_structure().unwrap();
+            let sub_cols = mat.select_cols(&cols);
+            assert_eq!(sub_cols.to_dense().column(0), dense.column(3));
+            assert_eq!(sub_cols.to_dense().column(1), dense.column(1));
+            sub_cols.check_compressed_structure().unwrap();
+        }
+    }
+
+    #[test]
+    fn select_masks() {
+        let mat = mat1();
+        let row_mask = vec![true, false, false, true, true];
+        let col_mask = vec![false, false, true, true, false];
+        let sub = mat.select(&row_mask, &col_mask);
+        let expected = arr2(&[[3., 4.], [0., 0.], [0., 7.]]);
+        assert_eq!(sub.to_dense(), expected);
+        let sub = mat.to_csc().select(&row_mask, &col_mask);
+        assert_eq!(sub.to_dense(), expected);
+    }
+
+    #[test]
+    fn select_duplicated_inner() {
+        let mat =
+            CsMat::new((2, 3), vec![0, 2, 3], vec![0, 2, 1], vec![1, 2, 3]);
+        let sub = mat.select_inner(&[2, 0, 2][..]);
+        assert_eq!(sub.to_dense(), arr2(&[[2, 1, 2], [0, 0, 0]]));
+        sub.check_compressed_structure().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn select_out_of_bounds() {
+        let mat = mat1();
+        let _ = mat.select_rows(&[5][..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn select_bad_mask_length() {
+        let mat = mat1();
+        let _ = mat.select_cols(&[true, false][..]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "the number of selected inner indices overflows the index type"
+    )]
+    fn select_inner_index_overflow() {
+        let mat = CsMatI::<f64, i16>::new(
+            (2, 2),
+            vec![0, 1, 2],
+            vec![0, 1],
+            vec![1., 2.],
+        );
+        let _ = mat.select_cols(&vec![0; 40_000][..]);
+    }
+}
@@ -1,8 +1,16 @@
 /// Functions dealing with symmetric sparse matrices
-use std::ops::Deref;
+use std::ops::{Deref, Mul};
 
+use ndarray::linalg::Dot;
+use ndarray::{Array, ArrayBase, Ix1, Ix2};
+use num_traits::Num;
+
+use crate::errors::SprsError;
 use crate::indexing::SpIndex;
+use crate::sparse::permutation::PermViewI;
 use crate::sparse::prelude::*;
+use crate::sparse::CompressedStorage::{self, CSC, CSR};
+use crate::Shape;
 
 pub fn is_symmetric<N, I, Iptr, IpStorage, IStorage, DStorage>(
     mat: &CsMatBase<N, I, IpStorage, IStorage, DStorage, Iptr>,
@@ -33,11 +41,388 @@ where
     true
 }
 
+/// Check whether all the non-zero entries of a matrix lie on or above its
+/// diagonal.
+///
+/// Contrary to `is_symmetric`, this check does not need to look up
+/// transposed entries, and runs in time linear in the number of non-zeros.
+pub fn is_upper_triangular<N, I, Iptr, IpStorage, IStorage, DStorage>(
+    mat: &CsMatBase<N, I, IpStorage, IStorage, DStorage, Iptr>,
+) -> bool
+where
+    I: SpIndex,
+    Iptr: SpIndex,
+    IpStorage: Deref<Target = [Iptr]>,
+    IStorage: Deref<Target = [I]>,
+    DStorage: Deref<Target = [N]>,
+{
+    let storage = mat.storage();
+    mat.outer_iterator().enumerate().all(|(outer, vec)| {
+        vec.indices()
+            .iter()
+            .all(|inner| in_upper(storage, outer, inner.index()))
+    })
+}
+
+/// Whether the entry at `(outer, inner)` of a matrix with the given storage
+/// lies on or above the diagonal
+fn in_upper(storage: CompressedStorage, outer: usize, inner: usize) -> bool {
+    match storage {
+        CSR => outer <= inner,
+        CSC => inner <= outer,
+    }
+}
+
+impl<N, I: SpIndex, Iptr: SpIndex> SymCsMatI<N, I, Iptr> {
+    /// Try to create a symmetric matrix from its upper triangle.
+    ///
+    /// # Errors
+    ///
+    /// - if `upper` is not square,
+    /// - if `upper` has non-zero entries below its diagonal.
+    pub fn try_new(upper: CsMatI<N, I, Iptr>) -> Result<Self, SprsError> {
+        if upper.rows() != upper.cols() {
+            return Err(SprsError::IllegalArguments("Matrix is not square"));
+        }
+        if !is_upper_triangular(&upper) {
+            return Err(SprsError::IllegalArguments(
+                "Matrix is not upper triangular",
+            ));
+        }
+        Ok(Self { upper })
+    }
+
+    /// Create a symmetric matrix from its upper triangle.
+    ///
+    /// # Panics
+    ///
+    /// - if `upper` is not square or not upper triangular, see `try_new`.
+    pub fn new(upper: CsMatI<N, I, Iptr>) -> Self {
+        Self::try_new(upper).unwrap()
+    }
+
+    /// Create a symmetric matrix from the upper triangle of a full
+    /// symmetric matrix, keeping its storage.
+    ///
+    /// The entries below the diagonal are ignored, so the symmetry of `mat`
+    /// is not checked, see `is_symmetric` to validate it.
+    ///
+    /// # Panics
+    ///
+    /// - if `mat` is not square.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use sprs::{CsMat, SymCsMat};
+    /// let mat = CsMat::new(
+    ///     (3, 3),
+    ///     vec![0, 2, 4, 6],
+    ///     vec![0, 1, 0, 2, 1, 2],
+    ///     vec![2., -1., -1., -1., -1., 2.],
+    /// );
+    /// let sym = SymCsMat::from_full(&mat);
+    /// assert_eq!(sym.nnz(), 4);
+    /// assert_eq!(sym.upper().indices(), &[0, 1, 2, 2]);
+    /// assert_eq!(sym.to_full(), mat);
+    /// ```
+    pub fn from_full<IpS, IS, DS>(
+        mat: &CsMatBase<N, I, IpS, IS, DS, Iptr>,
+    ) -> Self
+    where
+        N: Clone,
+        IpS: Deref<Target = [Iptr]>,
+        IS: Deref<Target = [I]>,
+        DS: Deref<Target = [N]>,
+    {
+        assert_eq!(mat.rows(), mat.cols(), "Matrix is not square");
+        let storage = mat.storage();
+        let mut indptr = Vec::with_capacity(mat.outer_dims() + 1);
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        indptr.push(Iptr::zero());
+        for (outer, vec) in mat.outer_iterator().enumerate() {
+            for (inner, val) in vec.iter() {
+                if in_upper(storage, outer, inner) {
+                    indices.push(I::from_usize(inner));
+                    data.push(val.clone());
+                }
+            }
+            indptr.push(Iptr::from_usize(indices.len()));
+        }
+        let upper =
+            CsMatI::new_trusted(storage, mat.shape(), indptr, indices, data);
+        Self { upper }
+    }
+
+    /// The number of rows of the matrix
+    pub fn rows(&self) -> usize {
+        self.upper.rows()
+    }
+
+    /// The number of cols of the matrix
+    pub fn cols(&self) -> usize {
+        self.upper.cols()
+    }
+
+    /// The shape of the matrix, as a `(rows, cols)` tuple
+    pub fn shape(&self) -> Shape {
+        self.upper.shape()
+    }
+
+    /// The number of stored non-zero entries, the entries below the
+    /// diagonal not being stored
+    pub fn nnz(&self) -> usize {
+        self.upper.nnz()
+    }
+
+    /// A view of the stored upper triangle
+    pub fn upper(&self) -> CsMatViewI<N, I, Iptr> {
+        self.upper.view()
+    }
+
+    /// Get the stored upper triangle
+    pub fn into_upper(self) -> CsMatI<N, I, Iptr> {
+        self.upper
+    }
+
+    /// Create the full symmetric matrix, with the storage of the stored
+    /// upper triangle
+    pub fn to_full(&self) -> CsMatI<N, I, Iptr>
+    where
+        N: Clone,
+    {
+        let n = self.rows();
+        let upper = &self.upper;
+        let mut indptr = vec![Iptr::zero(); n + 1];
+        for (outer, vec) in upper.outer_iterator().enumerate() {
+            indptr[outer + 1] += Iptr::from_usize(vec.nnz());
+            for &inner in vec.indices() {
+                if inner.index() != outer {
+                    indptr[inner.index() + 1] += Iptr::one();
+                }
+            }
+        }
+        for i in 0..n {
+            let prev = indptr[i];
+            indptr[i + 1] += prev;
+        }
+        // Visiting the outer dimensions in order fills each outer dimension
+        // of the result by increasing inner indices: the mirrored entries
+        // of an outer dimension are all below or all above its stored
+        // entries, and arrive in the order of their inner index.
+        let nnz = indptr[n].index();
+        let mut next: Vec<usize> =
+            indptr[..n].iter().map(|p| p.index()).collect();
+        let mut indices = vec![I::zero(); nnz];
+        let mut values = vec![None; nnz];
+        for (outer, inner, val) in self.outer_inner_entries() {
+            indices[next[outer]] = I::from_usize(inner);
+            values[next[outer]] = Some(val);
+            next[outer] += 1;
+            if inner != outer {
+                indices[next[inner]] = I::from_usize(outer);
+                values[next[inner]] = Some(val);
+                next[inner] += 1;
+            }
+        }
+        let data = values.into_iter().map(|val| val.unwrap().clone()).collect();
+        CsMatI::new_trusted(
+            upper.storage(),
+            self.shape(),
+            indptr,
+            indices,
+            data,
+        )
+    }
+
+    /// Compute the symmetric matrix P * A * P^T, storing its upper
+    /// triangle with the storage of this matrix.
+    ///
+    /// This is the counterpart of `transform_mat_papt` for symmetric
+    /// matrices.
+    ///
+    /// # Panics
+    ///
+    /// - if the dimension of `perm` does not match the matrix.
+    pub fn transform_papt(&self, perm: PermViewI<I>) -> Self
+    where
+        N: Clone,
+    {
+        let n = self.rows();
+        assert_eq!(n, perm.dim(), "Dimension mismatch");
+        if perm.is_identity() {
+            return self.clone();
+        }
+        let storage = self.upper.storage();
+        // the entry (i, j) of A becomes the entry (pinv[i], pinv[j])
+        // of P * A * P^T, which is mirrored to the upper triangle if needed
+        let locate = |outer: usize, inner: usize| {
+            let (a, b) = (perm.at_inv(outer), perm.at_inv(inner));
+            if in_upper(storage, a, b) {
+                (a, b)
+            } else {
+                (b, a)
+            }
+        };
+        let mut indptr = vec![Iptr::zero(); n + 1];
+        for (outer, vec) in self.upper.outer_iterator().enumerate() {
+            for &inner in vec.indices() {
+                let (new_outer, _) = locate(outer, inner.index());
+                indptr[new_outer + 1] += Iptr::one();
+            }
+        }
+        for i in 0..n {
+            let prev = indptr[i];
+            indptr[i + 1] += prev;
+        }
+        let mut entries = vec![Vec::new(); n];
+        for (outer, inner, val) in self.outer_inner_entries() {
+            let (new_outer, new_inner) = locate(outer, inner);
+            entries[new_outer].push((new_inner, val));
+        }
+        let mut indices = Vec::with_capacity(self.nnz());
+        let mut data = Vec::with_capacity(self.nnz());
+        for mut outer_entries in entries {
+            outer_entries.sort_unstable_by_key(|&(inner, _)| inner);
+            for (inner, val) in outer_entries {
+                indices.push(I::from_usize(inner));
+                data.push(val.clone());
+            }
+        }
+        let upper =
+            CsMatI::new_trusted(storage, self.shape(), indptr, indices, data);
+        Self { upper }
+    }
+}
+
+impl<N, I: SpIndex, Iptr: SpIndex> SymCsMatI<N, I, Iptr> {
+    /// Iterate over the stored entries in storage order, yielding their
+    /// outer index, inner index and value
+    fn outer_inner_entries(&self) -> impl Iterator<Item = (usize, usize, &N)> {
+        let storage = self.upper.storage();
+        self.upper
+            .iter()
+            .map(move |(val, (row, col))| match storage {
+                CSR => (row.index(), col.index(), val),
+                CSC => (col.index(), row.index(), val),
+            })
+    }
+}
+
+impl<N, I: SpIndex, Iptr: SpIndex> SparseMat for SymCsMatI<N, I, Iptr> {
+    fn rows(&self) -> usize {
+        self.rows()
+    }
+
+    fn cols(&self) -> usize {
+        self.cols()
+    }
+
+    fn nnz(&self) -> usize {
+        self.nnz()
+    }
+}
+
+impl<'a, 'b, N, I, Iptr, DS2> Mul<&'b ArrayBase<DS2, Ix1>>
+    for &'a SymCsMatI<N, I, Iptr>
+where
+    N: 'a + Copy + Num,
+    I: 'a + SpIndex,
+    Iptr: 'a + SpIndex,
+    DS2: 'b + ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn mul(self, rhs: &'b ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        assert_eq!(self.cols(), rhs.len(), "Dimension mismatch");
+        let mut res = Array::zeros(self.rows());
+        for (&a, (i, j)) in self.upper.iter() {
+            let (i, j) = (i.index(), j.index());
+            res[i] = res[i] + a * rhs[j];
+            if i != j {
+                res[j] = res[j] + a * rhs[i];
+            }
+        }
+        res
+    }
+}
+
+impl<'a, 'b, N, I, Iptr, DS2> Mul<&'b ArrayBase<DS2, Ix2>>
+    for &'a SymCsMatI<N, I, Iptr>
+where
+    N: 'a + Copy + Num,
+    I: 'a + SpIndex,
+    Iptr: 'a + SpIndex,
+    DS2: 'b + ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix2>;
+
+    fn mul(self, rhs: &'b ArrayBase<DS2, Ix2>) -> Array<N, Ix2> {
+        assert_eq!(self.cols(), rhs.nrows(), "Dimension mismatch");
+        let mut res = Array::zeros((self.rows(), rhs.ncols()));
+        for (&a, (i, j)) in self.upper.iter() {
+            let (i, j) = (i.index(), j.index());
+            res.row_mut(i)
+                .zip_mut_with(&rhs.row(j), |y, &x| *y = *y + a * x);
+            if i != j {
+                res.row_mut(j)
+                    .zip_mut_with(&rhs.row(i), |y, &x| *y = *y + a * x);
+            }
+        }
+        res
+    }
+}
+
+impl<N, I, Iptr, DS2> Dot<ArrayBase<DS2, Ix1>> for SymCsMatI<N, I, Iptr>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    Iptr: SpIndex,
+    DS2: ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix1>;
+
+    fn dot(&self, rhs: &ArrayBase<DS2, Ix1>) -> Array<N, Ix1> {
+        Mul::mul(self, rhs)
+    }
+}
+
+impl<N, I, Iptr, DS2> Dot<ArrayBase<DS2, Ix2>> for SymCsMatI<N, I, Iptr>
+where
+    N: Copy + Num,
+    I: SpIndex,
+    Iptr: SpIndex,
+    DS2: ndarray::Data<Elem = N>,
+{
+    type Output = Array<N, Ix2>;
+
+    fn dot(&self, rhs: &ArrayBase<DS2, Ix2>) -> Array<N, Ix2> {
+        Mul::mul(self, rhs)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::is_symmetric;
+    use super::{is_symmetric, is_upper_triangular};
+    use crate::errors::SprsError;
     use crate::sparse::csmat::CompressedStorage::CSR;
-    use crate::sparse::CsMatView;
+    use crate::sparse::permutation::{transform_mat_papt, PermOwned};
+    use crate::sparse::{CsMat, CsMatView, SymCsMat};
+    use ndarray::{arr1, arr2};
+
+    fn sym_mat() -> CsMat<f64> {
+        // | 4 1 0 2 |
+        // | 1 0 3 0 |
+        // | 0 3 5 0 |
+        // | 2 0 0 6 |
+        CsMat::new(
+            (4, 4),
+            vec![0, 3, 5, 7, 9],
+            vec![0, 1, 3, 0, 2, 1, 2, 0, 3],
+            vec![4., 1., 2., 1., 3., 3., 5., 2., 6.],
+        )
+    }
 
     #[test]
     fn is_symmetric_simple() {
@@ -59,4 +444,64 @@ mod test {
     }
 
     // TODO: symmetry test on A^T*A products
+
+    #[test]
+    fn upper_triangular() {
+        let mat = sym_mat();
+        assert!(!is_upper_triangular(&mat));
+        let sym = SymCsMat::from_full(&mat);
+        assert!(is_upper_triangular(&sym.upper()));
+        assert!(is_upper_triangular(&sym.upper().to_csc()));
+        assert!(!is_upper_triangular(&sym.upper().transpose_view()));
+    }
+
+    #[test]
+    fn sym_full_roundtrip() {
+        let mat = sym_mat();
+        let sym = SymCsMat::from_full(&mat);
+        assert_eq!(sym.nnz(), 6);
+        assert_eq!(sym.upper().indices(), &[0, 1, 3, 2, 2, 3]);
+        assert_eq!(sym.to_full(), mat);
+        let sym_csc = SymCsMat::from_full(&mat.to_csc());
+        assert_eq!(sym_csc.upper(), sym.upper().to_csc().view());
+        assert_eq!(sym_csc.to_full(), mat.to_csc());
+        assert_eq!(SymCsMat::new(sym.upper().to_owned()), sym);
+
+        let res = SymCsMat::try_new(mat.clone());
+        assert_eq!(
+            res.unwrap_err(),
+            SprsError::IllegalArguments("Matrix is not upper triangular")
+        );
+        let res = SymCsMat::try_new(CsMat::<f64>::zero((2, 3)));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn sym_products() {
+        let mat = sym_mat();
+        let sym = SymCsMat::from_full(&mat);
+        let x = arr1(&[1., -1., 2., 0.5]);
+        assert_eq!(&sym * &x, &mat * &x);
+        let sym_csc = SymCsMat::from_full(&mat.to_csc());
+        assert_eq!(&sym_csc * &x, &mat * &x);
+        let rhs = arr2(&[[1., 0.], [2., -1.], [0., 3.], [1., 1.]]);
+        assert_eq!(&sym * &rhs, &mat * &rhs);
+        assert_eq!(&sym_csc * &rhs, &mat * &rhs);
+    }
+
+    #[test]
+    fn sym_transform_papt() {
+        let mat = sym_mat();
+        let perm = PermOwned::new(vec![2, 0, 3, 1]);
+        let expected = transform_mat_papt(mat.view(), perm.view());
+        let sym = SymCsMat::from_full(&mat);
+        let papt = sym.transform_papt(perm.view());
+        assert_eq!(papt.to_full(), expected);
+        assert_eq!(papt, SymCsMat::from_full(&expected));
+        let sym_csc = SymCsMat::from_full(&mat.to_csc());
+        let papt = sym_csc.transform_papt(perm.view());
+        assert_eq!(papt.to_full(), expected.to_csc());
+        let identity = PermOwned::identity(4);
+        assert_eq!(sym.transform_papt(identity.view()), sym);
+    }
 }
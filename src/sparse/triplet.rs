@@ -269,6 +269,45 @@ where
         self.triplet_iter().into_csr()
     }
 
+    /// Create a CSC structure matrix holding the non-zero locations of this
+    /// triplet matrix, ignoring its values. Duplicate locations are merged.
+    ///
+    /// This enables building the structure of matrices whose scalar type
+    /// cannot be summed, such as the `()` type used for pattern matrices.
+    pub fn to_csc_structure(&self) -> CsStructureI<I> {
+        self.transpose_view().to_csr_structure().transpose_into()
+    }
+
+    /// Create a CSR structure matrix holding the non-zero locations of this
+    /// triplet matrix, ignoring its values. Duplicate locations are merged.
+    pub fn to_csr_structure(&self) -> CsStructureI<I> {
+        let mut locations: Vec<(I, I)> = self
+            .row_inds
+            .iter()
+            .cloned()
+            .zip(self.col_inds.iter().cloned())
+            .collect();
+        locations.sort_unstable();
+        locations.dedup();
+        let mut indptr = vec![I::zero(); self.rows + 1];
+        for &(row, _) in &locations {
+            indptr[row.index() + 1] += I::one();
+        }
+        for i in 0..self.rows {
+            let prev = indptr[i];
+            indptr[i + 1] += prev;
+        }
+        let indices = locations.into_iter().map(|(_, col)| col).collect();
+        let data = vec![(); indptr[self.rows].index()];
+        CsMatI::new_trusted(
+            crate::CSR,
+            (self.rows, self.cols),
+            indptr,
+            indices,
+            data,
+        )
+    }
+
     pub fn view(&self) -> TriMatViewI<N, I> {
         TriMatViewI {
             rows: self.rows,
@@ -635,4 +674,25 @@ mod test {
         assert_eq!(m.indices(), &[1, 0]);
         assert_eq!(m.data(), &[1, 2]);
     }
+
+    #[test]
+    fn triplet_to_structure() {
+        // |. x .|
+        // |x . x|
+        let mut triplet_mat = TriMat::new((2, 3));
+        triplet_mat.add_triplet(1, 2, ());
+        triplet_mat.add_triplet(0, 1, ());
+        triplet_mat.add_triplet(1, 0, ());
+        triplet_mat.add_triplet(1, 2, ());
+
+        let csr = triplet_mat.to_csr_structure();
+        assert_eq!(csr.indptr(), &[0, 1, 3]);
+        assert_eq!(csr.indices(), &[1, 0, 2]);
+        assert_eq!(csr.nnz(), 3);
+
+        let csc = triplet_mat.to_csc_structure();
+        assert!(csc.is_csc());
+        assert_eq!(csc.indptr(), &[0, 1, 2, 3]);
+        assert_eq!(csc.indices(), &[1, 0, 1]);
+    }
 }
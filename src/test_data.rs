@@ -83,6 +83,40 @@ pub fn mat1_csc_matprod_mat4() -> CsMat<f64> {
     CsMat::new_csc((5, 5), indptr, indices, data)
 }
 
+/// The matrix of a five-point stencil on a n by n grid, whose nodes are
+/// numbered row by row. The diagonal entry of node `k` is `diag(k)`, and
+/// `couplings` holds the entries coupling a node to its neighbors at
+/// offsets `-n`, `-1`, `1` and `n`.
+pub fn five_point_stencil<F>(
+    n: usize,
+    diag: F,
+    couplings: [f64; 4],
+) -> CsMat<f64>
+where
+    F: Fn(usize) -> f64,
+{
+    let [prev_row, prev, next, next_row] = couplings;
+    let offsets = [-(n as isize), -1, 0, 1, n as isize];
+    let diagonals: Vec<Vec<f64>> = offsets
+        .iter()
+        .map(|&offset| {
+            let len = n * n - offset.abs() as usize;
+            (0..len)
+                .map(|k| match offset {
+                    0 => diag(k),
+                    // no coupling across the rows of the grid
+                    -1 | 1 if (k + 1) % n == 0 => 0.,
+                    -1 => prev,
+                    1 => next,
+                    _ if offset < 0 => prev_row,
+                    _ => next_row,
+                })
+                .collect()
+        })
+        .collect();
+    crate::diags((n * n, n * n), &diagonals, &offsets)
+}
+
 pub fn mat_dense1() -> Array<f64, Ix2> {
     let m = arr2(&[
         [0., 1., 2., 3., 4.],